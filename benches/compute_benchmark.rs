@@ -2,7 +2,9 @@
 use std::sync::Arc;
 
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
-use petite_ad::{mono_ops, types::MonoGradientFn, types::MultiGradientFn, MonoAD, MultiAD};
+use petite_ad::{
+    mono_ops, types::MonoGradientFn, types::MultiGradientFn, CompiledGraph, MonoAD, MultiAD,
+};
 
 fn bench_single_operations(c: &mut Criterion) {
     let mut group = c.benchmark_group("single_operation");
@@ -207,6 +209,49 @@ fn bench_multi_forward_backward(c: &mut Criterion) {
         })
     });
 
+    // A longer chain where per-node overhead dominates:
+    // alternating sin/mul/add nodes over 3 inputs
+    let mut long_exprs: Vec<(MultiAD, Vec<usize>)> = vec![
+        (MultiAD::Inp, vec![0]),
+        (MultiAD::Inp, vec![1]),
+        (MultiAD::Inp, vec![2]),
+    ];
+    for i in 3..100 {
+        let op = match i % 3 {
+            0 => (MultiAD::Sin, vec![i - 1]),
+            1 => (MultiAD::Mul, vec![i - 1, i % 3]),
+            _ => (MultiAD::Add, vec![i - 1, i - 2]),
+        };
+        long_exprs.push(op);
+    }
+
+    group.bench_function("compute_grad_100_nodes", |b| {
+        b.iter(|| {
+            let (value, backprop_fn) = MultiAD::compute_grad(
+                std::hint::black_box(&long_exprs),
+                std::hint::black_box(&[0.6, 1.4, 0.3]),
+            )
+            .unwrap();
+            std::hint::black_box(value);
+            let grads = backprop_fn(1.0);
+            std::hint::black_box(grads);
+        })
+    });
+
+    // Compiled once, evaluated repeatedly with a reused workspace
+    let compiled = CompiledGraph::new(&long_exprs, 3).unwrap();
+    group.bench_function("compiled_value_and_grad_100_nodes", |b| {
+        let mut ws = compiled.workspace();
+        let mut grads = [0.0; 3];
+        b.iter(|| {
+            let value = compiled
+                .value_and_grad(std::hint::black_box(&[0.6, 1.4, 0.3]), &mut ws, &mut grads)
+                .unwrap();
+            std::hint::black_box(value);
+            std::hint::black_box(&grads);
+        })
+    });
+
     group.finish();
 }
 
@@ -0,0 +1,52 @@
+//! Shared helpers for batched evaluation.
+
+use std::ops::Range;
+
+/// Split `0..len` into at most `num_threads` contiguous chunks and run `f` on
+/// each, returning the per-chunk results in order.
+///
+/// With a single chunk `f` runs on the calling thread; otherwise each chunk
+/// gets its own `std::thread::scope` worker.
+pub(crate) fn map_chunks<T, F>(len: usize, num_threads: usize, f: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(Range<usize>) -> T + Sync,
+{
+    let num_chunks = num_threads.clamp(1, len.max(1));
+    let chunk_len = len.div_ceil(num_chunks);
+    if num_chunks == 1 {
+        return vec![f(0..len)];
+    }
+
+    std::thread::scope(|scope| {
+        let f = &f;
+        let workers: Vec<_> = (0..len)
+            .step_by(chunk_len)
+            .map(|start| scope.spawn(move || f(start..(start + chunk_len).min(len))))
+            .collect();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().expect("batch worker panicked"))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_chunks_covers_range_in_order() {
+        for num_threads in [0, 1, 3, 8, 20] {
+            let chunks = map_chunks(10, num_threads, |range| range.collect::<Vec<_>>());
+            let flat: Vec<usize> = chunks.into_iter().flatten().collect();
+            assert_eq!(flat, (0..10).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn test_map_chunks_empty() {
+        let chunks = map_chunks(0, 4, |range| range.len());
+        assert_eq!(chunks, vec![0]);
+    }
+}
@@ -2,6 +2,42 @@
 
 use std::fmt;
 
+use crate::multi::Shape;
+
+/// Number of arguments an operation accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Arity {
+    /// Exactly this many arguments
+    Exact(usize),
+    /// This many arguments or more, for n-ary ops such as `Sum`
+    AtLeast(usize),
+}
+
+impl Arity {
+    /// Returns `true` if an operation with this arity can take `count` arguments.
+    pub fn accepts(self, count: usize) -> bool {
+        match self {
+            Arity::Exact(n) => count == n,
+            Arity::AtLeast(n) => count >= n,
+        }
+    }
+}
+
+impl From<usize> for Arity {
+    fn from(n: usize) -> Self {
+        Arity::Exact(n)
+    }
+}
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Arity::Exact(n) => write!(f, "{}", n),
+            Arity::AtLeast(n) => write!(f, "at least {}", n),
+        }
+    }
+}
+
 /// Errors that can occur during automatic differentiation computations.
 #[derive(Debug, Clone, PartialEq)]
 pub enum AutodiffError {
@@ -10,7 +46,7 @@ pub enum AutodiffError {
         /// Name of the operation
         operation: &'static str,
         /// Expected number of arguments
-        expected: usize,
+        expected: Arity,
         /// Actual number of arguments received
         actual: usize,
     },
@@ -23,6 +59,62 @@ pub enum AutodiffError {
         /// The maximum valid index
         max_index: usize,
     },
+    /// A vector argument has the wrong length (e.g. a tangent that does not
+    /// match the number of inputs).
+    DimensionMismatch {
+        /// Expected length
+        expected: usize,
+        /// Actual length received
+        actual: usize,
+    },
+    /// A node refers to itself or to a node that comes after it.
+    ForwardReference {
+        /// Index of the offending node
+        node: usize,
+        /// The index it refers to
+        index: usize,
+    },
+    /// The graph declares inputs with `Inp` nodes but skips one of them.
+    MissingInput {
+        /// The input number with no `Inp` node
+        input: usize,
+    },
+    /// The graph refers to more inputs than were supplied.
+    InputCountMismatch {
+        /// Number of inputs the graph requires
+        expected: usize,
+        /// Number of inputs supplied
+        actual: usize,
+    },
+    /// The graph reads more parameters than were supplied.
+    MissingParams {
+        /// Number of parameters the graph requires
+        expected: usize,
+        /// Number of parameters supplied
+        actual: usize,
+    },
+    /// A formula could not be parsed.
+    ParseError {
+        /// Description of the problem
+        message: String,
+        /// 1-based line of the offending token
+        line: usize,
+        /// 1-based column of the offending token
+        column: usize,
+    },
+    /// Symbolic differentiation reached an op whose derivative cannot be
+    /// written with the existing ops.
+    NoSymbolicDerivative {
+        /// Name of the operation
+        operation: &'static str,
+    },
+    /// A tensor operation received arguments whose shapes it cannot combine.
+    ShapeMismatch {
+        /// Name of the operation
+        operation: &'static str,
+        /// Shapes of the arguments
+        shapes: Vec<Shape>,
+    },
 }
 
 impl fmt::Display for AutodiffError {
@@ -41,6 +133,54 @@ impl fmt::Display for AutodiffError {
             AutodiffError::IndexOutOfBounds { index, max_index } => {
                 write!(f, "Index {} is out of bounds (max: {})", index, max_index)
             }
+            AutodiffError::DimensionMismatch { expected, actual } => write!(
+                f,
+                "Dimension mismatch: expected length {}, got {}",
+                expected, actual
+            ),
+            AutodiffError::ForwardReference { node, index } if node == index => {
+                write!(f, "Node {} refers to itself", node)
+            }
+            AutodiffError::ForwardReference { node, index } => {
+                write!(f, "Node {} refers to later node {}", node, index)
+            }
+            AutodiffError::MissingInput { input } => {
+                write!(f, "Input {} is not declared by any Inp node", input)
+            }
+            AutodiffError::InputCountMismatch { expected, actual } => write!(
+                f,
+                "Input count mismatch: graph requires {} inputs, got {}",
+                expected, actual
+            ),
+            AutodiffError::MissingParams { expected, actual } => write!(
+                f,
+                "Missing parameters: graph requires {} parameters, got {}",
+                expected, actual
+            ),
+            AutodiffError::ParseError {
+                message,
+                line,
+                column,
+            } => write!(
+                f,
+                "Parse error at line {}, column {}: {}",
+                line, column, message
+            ),
+            AutodiffError::NoSymbolicDerivative { operation } => write!(
+                f,
+                "{} has no symbolic derivative in terms of the existing ops",
+                operation
+            ),
+            AutodiffError::ShapeMismatch { operation, shapes } => {
+                write!(f, "Shape mismatch in {}: got ", operation)?;
+                for (i, shape) in shapes.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", shape)?;
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -49,21 +189,43 @@ impl std::error::Error for AutodiffError {}
 
 impl AutodiffError {
     /// Create an ArityError for an operation with incorrect argument count.
-    pub fn arity(operation: &'static str, expected: usize, actual: usize) -> Self {
+    pub fn arity(operation: &'static str, expected: impl Into<Arity>, actual: usize) -> Self {
         AutodiffError::ArityError {
             operation,
-            expected,
+            expected: expected.into(),
             actual,
         }
     }
 
+    /// Create a ParseError at the given 1-based position.
+    pub fn parse(message: impl Into<String>, line: usize, column: usize) -> Self {
+        AutodiffError::ParseError {
+            message: message.into(),
+            line,
+            column,
+        }
+    }
+
+    /// Validate that a vector argument has the expected length.
+    pub fn check_dimension(
+        expected: usize,
+        actual: usize,
+    ) -> std::result::Result<(), AutodiffError> {
+        if actual == expected {
+            Ok(())
+        } else {
+            Err(AutodiffError::DimensionMismatch { expected, actual })
+        }
+    }
+
     /// Validate that an operation received the correct number of arguments.
     pub fn check_arity(
         operation: &'static str,
-        expected: usize,
+        expected: impl Into<Arity>,
         actual: usize,
     ) -> std::result::Result<(), AutodiffError> {
-        if actual == expected {
+        let expected = expected.into();
+        if expected.accepts(actual) {
             Ok(())
         } else {
             Err(AutodiffError::arity(operation, expected, actual))
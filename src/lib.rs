@@ -7,7 +7,7 @@
 //!
 //! - **Single-variable autodiff** - Chain operations like `sin`, `cos`, `exp`
 //! - **Multi-variable autodiff** - Build computational graphs for multiple inputs
-//! - **Zero-copy backward pass** - Efficient gradient computation through closure chains
+//! - **Flat-tape backward pass** - Allocation-free reverse sweep over a contiguous tape
 //! - **Convenient macros** - Use `mono_ops![]` and `multi_ops![]` for concise notation
 //!
 //! ## Examples
@@ -40,6 +40,7 @@
 //! println!("∇f = {:?}", gradients);
 //! ```
 
+mod batch;
 mod error;
 mod macros;
 
@@ -48,14 +49,18 @@ mod test_utils;
 
 mod mono;
 mod multi;
+mod serialize;
 
 // Core types
-pub use mono::MonoAD;
+pub use mono::{MonoAD, MonoBatch};
 pub use multi::builder::GraphBuilder;
-pub use multi::MultiAD;
+pub use multi::{
+    CompiledGraph, MultiAD, MultiBatch, Rewrite, RewriteContext, RewriteRule, Shape, Simplifier,
+    SubgradientPolicy, Tape, Tensor, TensorAD, TensorGraphBuilder, Var, Workspace, Wrt,
+};
 
 // Error handling
-pub use error::{AutodiffError, Result};
+pub use error::{Arity, AutodiffError, Result};
 
 /// Type definitions for autodiff results and gradient functions.
 ///
@@ -67,7 +72,7 @@ pub mod types {
     };
     pub use crate::multi::types::{
         BackwardResultArc as MultiResultArc, BackwardResultBox as MultiResultBox,
-        DynGradFn as MultiGradientFn,
+        DynGradFn as MultiGradientFn, ParsedExpr, TensorGradFn, TensorResultBox,
     };
 }
 
@@ -36,7 +36,17 @@ macro_rules! mono_ops {
 /// - `add`, `sub`, `mul`, `div` - Binary operations (takes two indices)
 /// - `pow` - Power operation (takes two indices: base, exponent)
 /// - `sin`, `cos`, `tan`, `exp`, `ln` - Unary operations (takes single index)
-/// - `sqrt`, `abs` - Unary operations (takes single index)
+/// - `sqrt`, `abs`, `neg` - Unary operations (takes single index)
+/// - `tanh`, `sinh`, `cosh`, `asin`, `acos`, `atan` - Unary operations (takes single index)
+/// - `log1p`, `expm1`, `log2`, `log10`, `sigmoid`, `softplus` - Unary operations (takes single index)
+/// - `atan2`, `hypot` - Binary operations (takes two indices; `atan2` takes y, then x)
+/// - `relu`, `floor`, `ceil`, `round`, `sign` - Unary operations (takes single index)
+/// - `min`, `max`, `fmod` - Binary operations (takes two indices)
+/// - `erf`, `erfc`, `lgamma`, `digamma`, `trigamma`, `normal_cdf`, `normal_logpdf`, `lambert_w` - Unary operations (takes single index)
+/// - `sum`, `prod`, `mean`, `logsumexp`, `softmax_sum` - N-ary operations (takes one or more indices)
+/// - `clamp` - Takes three indices: value, lower bound, upper bound
+/// - `const` - Constant node (takes the value instead of an index, e.g. `(const, 2.0)`)
+/// - `param` - Trainable parameter (takes the parameter number, e.g. `(param, 0)`)
 ///
 /// # Example
 /// ```
@@ -52,6 +62,10 @@ macro_rules! mono_ops {
 /// ];
 ///
 /// let (value, grad_fn) = MultiAD::compute_grad(&exprs, &[0.6, 1.4]).unwrap();
+///
+/// // Build: g(x) = 2 * x
+/// let exprs = multi_ops![(inp, 0), (const, 2.0), (mul, 1, 0)];
+/// assert_eq!(MultiAD::compute(&exprs, &[3.0]).unwrap(), 6.0);
 /// ```
 ///
 #[macro_export]
@@ -64,12 +78,51 @@ macro_rules! multi_ops {
     (@op ln) => { $crate::MultiAD::Ln };
     (@op sqrt) => { $crate::MultiAD::Sqrt };
     (@op abs) => { $crate::MultiAD::Abs };
+    (@op neg) => { $crate::MultiAD::Neg };
+    (@op tanh) => { $crate::MultiAD::Tanh };
+    (@op sinh) => { $crate::MultiAD::Sinh };
+    (@op cosh) => { $crate::MultiAD::Cosh };
+    (@op asin) => { $crate::MultiAD::Asin };
+    (@op acos) => { $crate::MultiAD::Acos };
+    (@op atan) => { $crate::MultiAD::Atan };
+    (@op log1p) => { $crate::MultiAD::Log1p };
+    (@op expm1) => { $crate::MultiAD::Expm1 };
+    (@op log2) => { $crate::MultiAD::Log2 };
+    (@op log10) => { $crate::MultiAD::Log10 };
+    (@op sigmoid) => { $crate::MultiAD::Sigmoid };
+    (@op softplus) => { $crate::MultiAD::Softplus };
+    (@op relu) => { $crate::MultiAD::Relu };
+    (@op floor) => { $crate::MultiAD::Floor };
+    (@op ceil) => { $crate::MultiAD::Ceil };
+    (@op round) => { $crate::MultiAD::Round };
+    (@op sign) => { $crate::MultiAD::Sign };
+    (@op erf) => { $crate::MultiAD::Erf };
+    (@op erfc) => { $crate::MultiAD::Erfc };
+    (@op lgamma) => { $crate::MultiAD::Lgamma };
+    (@op digamma) => { $crate::MultiAD::Digamma };
+    (@op trigamma) => { $crate::MultiAD::Trigamma };
+    (@op normal_cdf) => { $crate::MultiAD::NormalCdf };
+    (@op normal_logpdf) => { $crate::MultiAD::NormalLogpdf };
+    (@op lambert_w) => { $crate::MultiAD::LambertW };
     // Binary operations
     (@op add) => { $crate::MultiAD::Add };
     (@op sub) => { $crate::MultiAD::Sub };
     (@op mul) => { $crate::MultiAD::Mul };
     (@op div) => { $crate::MultiAD::Div };
     (@op pow) => { $crate::MultiAD::Pow };
+    (@op atan2) => { $crate::MultiAD::Atan2 };
+    (@op hypot) => { $crate::MultiAD::Hypot };
+    (@op min) => { $crate::MultiAD::Min };
+    (@op max) => { $crate::MultiAD::Max };
+    (@op fmod) => { $crate::MultiAD::Fmod };
+    // Ternary operations
+    (@op clamp) => { $crate::MultiAD::Clamp };
+    // N-ary operations
+    (@op sum) => { $crate::MultiAD::Sum };
+    (@op prod) => { $crate::MultiAD::Prod };
+    (@op mean) => { $crate::MultiAD::Mean };
+    (@op logsumexp) => { $crate::MultiAD::LogSumExp };
+    (@op softmax_sum) => { $crate::MultiAD::SoftmaxSum };
     // Input
     (@op inp) => { $crate::MultiAD::Inp };
     // Error for unknown operations
@@ -78,10 +131,22 @@ macro_rules! multi_ops {
             concat!(
                 "Unsupported operation: ",
                 stringify!($x),
-                ". Use: inp, add, sub, mul, div, pow, sin, cos, tan, exp, ln, sqrt, or abs"
+                ". Use: inp, const, param, add, sub, mul, div, pow, atan2, hypot, sin, cos, tan, exp, ln, \
+                 sqrt, abs, neg, tanh, sinh, cosh, asin, acos, atan, log1p, expm1, log2, log10, sigmoid, \
+                 softplus, relu, floor, ceil, round, sign, erf, erfc, lgamma, digamma, trigamma, \
+                 normal_cdf, normal_logpdf, lambert_w, min, max, fmod, clamp, sum, prod, mean, logsumexp, \
+                 or softmax_sum"
             )
         )
     };
+    // Constants carry a value instead of argument indices
+    (@one (const, $value:expr)) => {
+        ($crate::MultiAD::Const($value), ::std::vec::Vec::<usize>::new())
+    };
+    // Parameters carry a parameter number instead of argument indices
+    (@one (param, $k:expr)) => {
+        ($crate::MultiAD::Param($k), ::std::vec::Vec::<usize>::new())
+    };
     // Main parsing rule: (op, indices...)
     (@one ($op:ident, $($idx:expr),+)) => {
         ($crate::multi_ops!(@op $op), vec![$($idx),+])
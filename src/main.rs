@@ -97,4 +97,27 @@ fn main() {
         "Analytical Gradients: ∂f/∂x = {:.4}, ∂f/∂y = {:.4}, ∂f/∂z = {:.4}",
         analytical_dx, analytical_dy, analytical_dz
     );
+
+    // Example 4: Parse a formula given as text (first command-line argument)
+    println!("\n=== Parsed Formula Example ===\n");
+    let formula = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "(x + y) * exp(z - sin(x))".to_string());
+    match MultiAD::parse(&formula) {
+        Ok((graph, vars)) => {
+            let inputs: Vec<f64> = (1..=vars.len()).map(|i| 0.5 * i as f64).collect();
+            match MultiAD::compute_grad(&graph, &inputs) {
+                Ok((value, grad_fn)) => {
+                    println!("Function: f({}) = {}", vars.join(", "), formula);
+                    println!("Inputs: {:?}", inputs);
+                    println!("Value: {}", value);
+                    for (name, grad) in vars.iter().zip(grad_fn(1.0)) {
+                        println!("∂f/∂{} = {:.4}", name, grad);
+                    }
+                }
+                Err(e) => println!("Evaluation failed: {}", e),
+            }
+        }
+        Err(e) => println!("Could not parse {:?}: {}", formula, e),
+    }
 }
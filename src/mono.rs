@@ -1,11 +1,16 @@
 pub mod types;
 
+mod batch;
+pub use batch::MonoBatch;
+
 #[cfg(test)]
 mod tests;
 
 mod mono_ad;
 pub use mono_ad::MonoAD;
 
+mod render;
+
 mod mono_fn;
 // Re-export trait for library extension - users can implement custom mono functions
 #[allow(unused_imports)] // May not be used internally, but part of public API
@@ -1,4 +1,5 @@
 use super::types::*;
+use crate::error::AutodiffError;
 
 /// Single-variable automatic differentiation operations.
 ///
@@ -47,12 +48,39 @@ pub enum MonoAD {
     Neg,
 }
 
+/// Writes the op name used by `mono_ops!`, e.g. `sin`.
+impl std::fmt::Display for MonoAD {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            MonoAD::Sin => "sin",
+            MonoAD::Cos => "cos",
+            MonoAD::Exp => "exp",
+            MonoAD::Neg => "neg",
+        })
+    }
+}
+
+/// Parses the op names written by `Display`.
+impl std::str::FromStr for MonoAD {
+    type Err = AutodiffError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sin" => Ok(MonoAD::Sin),
+            "cos" => Ok(MonoAD::Cos),
+            "exp" => Ok(MonoAD::Exp),
+            "neg" => Ok(MonoAD::Neg),
+            _ => Err(AutodiffError::parse(format!("unknown op '{s}'"), 1, 1)),
+        }
+    }
+}
+
 impl MonoAD {
     /// Compute the forward pass for a single operation.
     ///
     /// This is an internal helper that computes just the forward value
     /// without building gradient closures.
-    fn forward(&self, x: f64) -> f64 {
+    pub(super) fn forward(&self, x: f64) -> f64 {
         match self {
             MonoAD::Sin => x.sin(),
             MonoAD::Cos => x.cos(),
@@ -61,6 +89,16 @@ impl MonoAD {
         }
     }
 
+    /// Compute the derivative of a single operation at `x`.
+    pub(super) fn derivative(&self, x: f64) -> f64 {
+        match self {
+            MonoAD::Sin => x.cos(),
+            MonoAD::Cos => -x.sin(),
+            MonoAD::Exp => x.exp(),
+            MonoAD::Neg => -1.0,
+        }
+    }
+
     /// Compute the forward pass only (no gradient computation).
     ///
     /// Evaluates the composed function by applying operations sequentially.
@@ -93,28 +131,9 @@ impl MonoAD {
     where
         W: From<Box<DynMathFn>>,
     {
-        let (y, grad_fn): (f64, Box<dyn Fn(f64) -> f64>) = match self {
-            MonoAD::Sin => {
-                let y = x.sin();
-                let grad = Box::new(move |dy: f64| -> f64 { dy * x.cos() });
-                (y, grad)
-            }
-            MonoAD::Cos => {
-                let y = x.cos();
-                let grad = Box::new(move |dy: f64| -> f64 { dy * -x.sin() });
-                (y, grad)
-            }
-            MonoAD::Exp => {
-                let y = x.exp();
-                let grad = Box::new(move |dy: f64| -> f64 { dy * y });
-                (y, grad)
-            }
-            MonoAD::Neg => {
-                let y = -x;
-                let grad = Box::new(move |dy: f64| -> f64 { -dy * 1.0 });
-                (y, grad)
-            }
-        };
+        let y = self.forward(x);
+        let dy_dx = self.derivative(x);
+        let grad_fn: Box<DynMathFn> = Box::new(move |dy: f64| -> f64 { dy * dy_dx });
         // For backward(): Box::from(boxed_closure) → returns the Box as-is (identity)
         // For backward_arc(): Arc::from(boxed_closure) → converts Box to Arc
         (y, W::from(grad_fn))
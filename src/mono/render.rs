@@ -0,0 +1,76 @@
+//! Human-readable rendering of operation chains.
+
+use super::mono_ad::MonoAD;
+use crate::multi::render::{Notation, Renderer};
+use crate::multi::MultiAD;
+
+impl MonoAD {
+    /// The equivalent multi-variable operation.
+    fn to_multi(self) -> MultiAD {
+        match self {
+            MonoAD::Sin => MultiAD::Sin,
+            MonoAD::Cos => MultiAD::Cos,
+            MonoAD::Exp => MultiAD::Exp,
+            MonoAD::Neg => MultiAD::Neg,
+        }
+    }
+
+    /// The chain as a single-input graph.
+    fn to_graph(exprs: &[MonoAD]) -> Vec<(MultiAD, Vec<usize>)> {
+        std::iter::once((MultiAD::Inp, vec![0]))
+            .chain(
+                exprs
+                    .iter()
+                    .enumerate()
+                    .map(|(i, op)| (op.to_multi(), vec![i])),
+            )
+            .collect()
+    }
+
+    /// Render a chain as an infix formula in `x`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petite_ad::{MonoAD, mono_ops};
+    ///
+    /// assert_eq!(MonoAD::to_infix(&mono_ops![sin, cos, exp]), "exp(cos(sin(x)))");
+    /// assert_eq!(MonoAD::to_infix(&[]), "x");
+    /// ```
+    pub fn to_infix(exprs: &[MonoAD]) -> String {
+        Renderer::new(Notation::Infix)
+            .single_input()
+            .render(&Self::to_graph(exprs))
+    }
+
+    /// Render a chain as a LaTeX formula in `x`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petite_ad::{MonoAD, mono_ops};
+    ///
+    /// assert_eq!(MonoAD::to_latex(&mono_ops![sin, exp]), "e^{\\sin\\left(x\\right)}");
+    /// ```
+    pub fn to_latex(exprs: &[MonoAD]) -> String {
+        Renderer::new(Notation::Latex)
+            .single_input()
+            .render(&Self::to_graph(exprs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mono_ops;
+
+    #[test]
+    fn test_mono_rendering() {
+        let ops = mono_ops![neg, sin, neg, neg, cos];
+        assert_eq!(MonoAD::to_infix(&ops), "cos(-(-sin(-x)))");
+        assert_eq!(
+            MonoAD::to_latex(&ops),
+            "\\cos\\left(-\\left(-\\sin\\left(-x\\right)\\right)\\right)"
+        );
+    }
+}
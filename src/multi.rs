@@ -4,18 +4,44 @@
 //! multi-variable functions using computational graphs.
 
 // Example implementations - not part of public API
-mod f1;
-mod f2;
-mod f3;
+pub(crate) mod f1;
+pub(crate) mod f2;
+pub(crate) mod f3;
 
+mod batch;
 pub mod builder;
+mod compiled;
+mod dot;
+mod forward_mode;
+mod hessian;
+mod jacobian;
 mod multi_ad;
 mod multi_fn;
+mod optimize;
+mod params;
+mod parser;
+mod reduce;
+pub(crate) mod render;
+mod simplify;
+mod special;
+mod subgradient;
+mod symbolic;
+mod tape;
+mod tensor;
 #[cfg(test)]
 mod tests;
 pub mod types;
+mod validate;
+mod var;
 
+pub use batch::MultiBatch;
+pub use compiled::{CompiledGraph, Workspace};
 pub use multi_ad::MultiAD;
+pub use params::Wrt;
+pub use simplify::{Rewrite, RewriteContext, RewriteRule, Simplifier};
+pub use subgradient::SubgradientPolicy;
+pub use tensor::{Shape, Tensor, TensorAD, TensorGraphBuilder};
+pub use var::{Tape, Var};
 // Re-export trait for library extension - users can implement custom multi-variable functions
 #[allow(unused_imports)] // May not be used internally, but part of public API
 pub use multi_fn::MultiFn;
@@ -29,12 +29,13 @@ use super::multi_ad::MultiAD;
 #[derive(Debug, Clone)]
 pub struct GraphBuilder {
     /// Number of input variables
-    #[allow(dead_code)]
     num_inputs: usize,
     /// Operations in the computation graph
     operations: Vec<(MultiAD, Vec<usize>)>,
     /// Next available index for new operations
     next_index: usize,
+    /// Names and initial values of trainable parameters, by parameter index
+    params: Vec<(String, f64)>,
 }
 
 impl GraphBuilder {
@@ -56,13 +57,14 @@ impl GraphBuilder {
             num_inputs,
             operations: Vec::new(),
             next_index: num_inputs,
+            params: Vec::new(),
         }
     }
 
     /// Adds an input placeholder operation.
     ///
-    /// This is rarely needed directly as inputs are automatically available,
-    /// but can be useful for explicit graph construction.
+    /// Inputs are already available at indices `0..num_inputs`; this adds
+    /// another node holding the same value, which is rarely needed.
     ///
     /// # Arguments
     ///
@@ -73,6 +75,43 @@ impl GraphBuilder {
         self
     }
 
+    /// Adds a constant node.
+    ///
+    /// Constants take no arguments and contribute nothing to the gradient,
+    /// so they do not need to be passed as extra inputs.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The constant value
+    pub fn constant(&mut self, value: f64) -> &mut Self {
+        self.operations.push((MultiAD::Const(value), vec![]));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds a trainable parameter node.
+    ///
+    /// The first use of a name allocates the next parameter index with value
+    /// `init`; later uses of the same name add another node reading the same
+    /// parameter and ignore `init`.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Parameter name
+    /// * `init` - Initial value, see [`GraphBuilder::initial_params`]
+    pub fn param(&mut self, name: &str, init: f64) -> &mut Self {
+        let k = match self.params.iter().position(|(n, _)| n == name) {
+            Some(k) => k,
+            None => {
+                self.params.push((name.to_string(), init));
+                self.params.len() - 1
+            }
+        };
+        self.operations.push((MultiAD::Param(k), vec![]));
+        self.next_index += 1;
+        self
+    }
+
     /// Adds a sine operation.
     ///
     /// # Arguments
@@ -154,6 +193,293 @@ impl GraphBuilder {
         self
     }
 
+    /// Adds a negation operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_index` - Index of the input value
+    pub fn neg(&mut self, arg_index: usize) -> &mut Self {
+        self.operations.push((MultiAD::Neg, vec![arg_index]));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds a hyperbolic tangent operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_index` - Index of the input value
+    pub fn tanh(&mut self, arg_index: usize) -> &mut Self {
+        self.operations.push((MultiAD::Tanh, vec![arg_index]));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds a hyperbolic sine operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_index` - Index of the input value
+    pub fn sinh(&mut self, arg_index: usize) -> &mut Self {
+        self.operations.push((MultiAD::Sinh, vec![arg_index]));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds a hyperbolic cosine operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_index` - Index of the input value
+    pub fn cosh(&mut self, arg_index: usize) -> &mut Self {
+        self.operations.push((MultiAD::Cosh, vec![arg_index]));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds an inverse sine operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_index` - Index of the input value
+    pub fn asin(&mut self, arg_index: usize) -> &mut Self {
+        self.operations.push((MultiAD::Asin, vec![arg_index]));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds an inverse cosine operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_index` - Index of the input value
+    pub fn acos(&mut self, arg_index: usize) -> &mut Self {
+        self.operations.push((MultiAD::Acos, vec![arg_index]));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds an inverse tangent operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_index` - Index of the input value
+    pub fn atan(&mut self, arg_index: usize) -> &mut Self {
+        self.operations.push((MultiAD::Atan, vec![arg_index]));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds an `ln(1 + x)` operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_index` - Index of the input value
+    pub fn log1p(&mut self, arg_index: usize) -> &mut Self {
+        self.operations.push((MultiAD::Log1p, vec![arg_index]));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds an `exp(x) - 1` operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_index` - Index of the input value
+    pub fn expm1(&mut self, arg_index: usize) -> &mut Self {
+        self.operations.push((MultiAD::Expm1, vec![arg_index]));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds a base-2 logarithm operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_index` - Index of the input value
+    pub fn log2(&mut self, arg_index: usize) -> &mut Self {
+        self.operations.push((MultiAD::Log2, vec![arg_index]));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds a base-10 logarithm operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_index` - Index of the input value
+    pub fn log10(&mut self, arg_index: usize) -> &mut Self {
+        self.operations.push((MultiAD::Log10, vec![arg_index]));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds a logistic sigmoid operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_index` - Index of the input value
+    pub fn sigmoid(&mut self, arg_index: usize) -> &mut Self {
+        self.operations.push((MultiAD::Sigmoid, vec![arg_index]));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds a softplus operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_index` - Index of the input value
+    pub fn softplus(&mut self, arg_index: usize) -> &mut Self {
+        self.operations.push((MultiAD::Softplus, vec![arg_index]));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds a rectified linear unit operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_index` - Index of the input value
+    pub fn relu(&mut self, arg_index: usize) -> &mut Self {
+        self.operations.push((MultiAD::Relu, vec![arg_index]));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds a round-down operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_index` - Index of the input value
+    pub fn floor(&mut self, arg_index: usize) -> &mut Self {
+        self.operations.push((MultiAD::Floor, vec![arg_index]));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds a round-up operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_index` - Index of the input value
+    pub fn ceil(&mut self, arg_index: usize) -> &mut Self {
+        self.operations.push((MultiAD::Ceil, vec![arg_index]));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds a round-to-nearest operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_index` - Index of the input value
+    pub fn round(&mut self, arg_index: usize) -> &mut Self {
+        self.operations.push((MultiAD::Round, vec![arg_index]));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds a sign operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_index` - Index of the input value
+    pub fn sign(&mut self, arg_index: usize) -> &mut Self {
+        self.operations.push((MultiAD::Sign, vec![arg_index]));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds an error function operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_index` - Index of the input value
+    pub fn erf(&mut self, arg_index: usize) -> &mut Self {
+        self.operations.push((MultiAD::Erf, vec![arg_index]));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds a complementary error function operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_index` - Index of the input value
+    pub fn erfc(&mut self, arg_index: usize) -> &mut Self {
+        self.operations.push((MultiAD::Erfc, vec![arg_index]));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds a log-gamma operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_index` - Index of the input value
+    pub fn lgamma(&mut self, arg_index: usize) -> &mut Self {
+        self.operations.push((MultiAD::Lgamma, vec![arg_index]));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds a digamma operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_index` - Index of the input value
+    pub fn digamma(&mut self, arg_index: usize) -> &mut Self {
+        self.operations.push((MultiAD::Digamma, vec![arg_index]));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds a trigamma operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_index` - Index of the input value
+    pub fn trigamma(&mut self, arg_index: usize) -> &mut Self {
+        self.operations.push((MultiAD::Trigamma, vec![arg_index]));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds a standard normal CDF operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_index` - Index of the input value
+    pub fn normal_cdf(&mut self, arg_index: usize) -> &mut Self {
+        self.operations.push((MultiAD::NormalCdf, vec![arg_index]));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds a standard normal log-density operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_index` - Index of the input value
+    pub fn normal_logpdf(&mut self, arg_index: usize) -> &mut Self {
+        self.operations
+            .push((MultiAD::NormalLogpdf, vec![arg_index]));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds a Lambert W operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_index` - Index of the input value
+    pub fn lambert_w(&mut self, arg_index: usize) -> &mut Self {
+        self.operations.push((MultiAD::LambertW, vec![arg_index]));
+        self.next_index += 1;
+        self
+    }
+
     /// Adds an addition operation.
     ///
     /// # Arguments
@@ -219,10 +545,148 @@ impl GraphBuilder {
         self
     }
 
+    /// Adds a two-argument inverse tangent, the angle of the point `(x, y)`.
+    ///
+    /// # Arguments
+    ///
+    /// * `y_index` - Index of the y coordinate
+    /// * `x_index` - Index of the x coordinate
+    pub fn atan2(&mut self, y_index: usize, x_index: usize) -> &mut Self {
+        self.operations
+            .push((MultiAD::Atan2, vec![y_index, x_index]));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds a `sqrt(a² + b²)` operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `left_index` - Index of the left operand
+    /// * `right_index` - Index of the right operand
+    pub fn hypot(&mut self, left_index: usize, right_index: usize) -> &mut Self {
+        self.operations
+            .push((MultiAD::Hypot, vec![left_index, right_index]));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds a minimum operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `left_index` - Index of the left operand
+    /// * `right_index` - Index of the right operand
+    pub fn min(&mut self, left_index: usize, right_index: usize) -> &mut Self {
+        self.operations
+            .push((MultiAD::Min, vec![left_index, right_index]));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds a maximum operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `left_index` - Index of the left operand
+    /// * `right_index` - Index of the right operand
+    pub fn max(&mut self, left_index: usize, right_index: usize) -> &mut Self {
+        self.operations
+            .push((MultiAD::Max, vec![left_index, right_index]));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds a floating-point remainder operation, `a % b`.
+    ///
+    /// # Arguments
+    ///
+    /// * `left_index` - Index of the dividend
+    /// * `right_index` - Index of the divisor
+    pub fn fmod(&mut self, left_index: usize, right_index: usize) -> &mut Self {
+        self.operations
+            .push((MultiAD::Fmod, vec![left_index, right_index]));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds a clamp operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_index` - Index of the value to clamp
+    /// * `lo_index` - Index of the lower bound
+    /// * `hi_index` - Index of the upper bound
+    pub fn clamp(&mut self, arg_index: usize, lo_index: usize, hi_index: usize) -> &mut Self {
+        self.operations
+            .push((MultiAD::Clamp, vec![arg_index, lo_index, hi_index]));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds a sum of any number of values.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_indices` - Indices of the values to add; at least one
+    pub fn sum(&mut self, arg_indices: &[usize]) -> &mut Self {
+        self.operations.push((MultiAD::Sum, arg_indices.to_vec()));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds a product of any number of values.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_indices` - Indices of the values to multiply; at least one
+    pub fn prod(&mut self, arg_indices: &[usize]) -> &mut Self {
+        self.operations.push((MultiAD::Prod, arg_indices.to_vec()));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds a mean of any number of values.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_indices` - Indices of the values to average; at least one
+    pub fn mean(&mut self, arg_indices: &[usize]) -> &mut Self {
+        self.operations.push((MultiAD::Mean, arg_indices.to_vec()));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds a log-sum-exp of any number of values.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_indices` - Indices of the values to combine; at least one
+    pub fn logsumexp(&mut self, arg_indices: &[usize]) -> &mut Self {
+        self.operations
+            .push((MultiAD::LogSumExp, arg_indices.to_vec()));
+        self.next_index += 1;
+        self
+    }
+
+    /// Adds a softmax-weighted sum of any number of values.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_indices` - Indices of the values to combine; at least one
+    pub fn softmax_sum(&mut self, arg_indices: &[usize]) -> &mut Self {
+        self.operations
+            .push((MultiAD::SoftmaxSum, arg_indices.to_vec()));
+        self.next_index += 1;
+        self
+    }
+
     /// Builds the final computation graph.
     ///
     /// Returns a vector of `(operation, indices)` pairs that can be used
-    /// with `MultiAD::compute()` and `MultiAD::compute_grad()`.
+    /// with `MultiAD::compute()` and `MultiAD::compute_grad()`. The graph
+    /// starts with one `Inp` node per input, so node indices match the
+    /// indices used while building.
     ///
     /// # Examples
     ///
@@ -239,7 +703,20 @@ impl GraphBuilder {
     /// let (value, grad_fn) = MultiAD::compute_grad(&graph, inputs).unwrap();
     /// ```
     pub fn build(&self) -> Vec<(MultiAD, Vec<usize>)> {
-        self.operations.clone()
+        (0..self.num_inputs)
+            .map(|k| (MultiAD::Inp, vec![k]))
+            .chain(self.operations.iter().cloned())
+            .collect()
+    }
+
+    /// Initial parameter values, indexed like the `Param` nodes.
+    pub fn initial_params(&self) -> Vec<f64> {
+        self.params.iter().map(|(_, init)| *init).collect()
+    }
+
+    /// Parameter names, indexed like the `Param` nodes.
+    pub fn param_names(&self) -> Vec<&str> {
+        self.params.iter().map(|(name, _)| name.as_str()).collect()
     }
 
     /// Returns the current number of operations in the graph.
@@ -372,6 +849,25 @@ mod tests {
         assert_eq!(builder.len(), 2);
     }
 
+    #[test]
+    fn test_builder_constant() {
+        // Build: f(x) = 2 * x^3
+        let graph = GraphBuilder::new(1)
+            .constant(3.0)
+            .pow(0, 1)
+            .constant(2.0)
+            .mul(3, 2)
+            .build();
+        assert_eq!(graph[0], (MultiAD::Inp, vec![0]));
+        assert_eq!(graph[1], (MultiAD::Const(3.0), vec![]));
+
+        let (value, grad_fn) = MultiAD::compute_grad(&graph, &[1.5]).unwrap();
+        assert!(approx_eq(value, 6.75, 1e-10));
+        // Only the real input gets a gradient: d/dx = 6x^2
+        assert_eq!(grad_fn(1.0).len(), 1);
+        assert!(approx_eq(grad_fn(1.0)[0], 13.5, 1e-10));
+    }
+
     #[test]
     fn test_builder_custom_operation() {
         // Use custom to add an operation not in the fluent API
@@ -0,0 +1,508 @@
+//! Graphs compiled once for repeated evaluation.
+//!
+//! [`CompiledGraph`] validates a graph up front and flattens it into
+//! contiguous arrays. Evaluation then only needs a [`Workspace`] of scratch
+//! buffers, which can be reused across calls so that evaluating the same graph
+//! at many points performs no graph analysis and no heap allocation.
+
+use super::multi_ad::MultiAD;
+use super::subgradient::SubgradientPolicy;
+use crate::error::{AutodiffError, Result};
+
+/// A validated, flattened computation graph.
+///
+/// Inputs occupy value slots `0..num_inputs`; `Inp` nodes alias the slot of
+/// their input, so they need no storage. Operation `i` (the `i`-th non-`Inp`
+/// node) stores its value in slot `num_inputs + i`, and its argument slots are
+/// `args[arg_offsets[i]..arg_offsets[i + 1]]`.
+///
+/// # Examples
+///
+/// ```
+/// use petite_ad::{multi_ops, CompiledGraph};
+///
+/// // f(x, y) = sin(x) * (x + y)
+/// let exprs = multi_ops![(inp, 0), (inp, 1), (add, 0, 1), (sin, 0), (mul, 2, 3)];
+/// let graph = CompiledGraph::new(&exprs, 2).unwrap();
+/// let mut ws = graph.workspace();
+/// let mut grad = [0.0; 2];
+///
+/// for x in [0.1, 0.2, 0.3] {
+///     let value = graph.value_and_grad(&[x, 1.0], &mut ws, &mut grad).unwrap();
+///     assert!((value - x.sin() * (x + 1.0)).abs() < 1e-10);
+///     assert!((grad[1] - x.sin()).abs() < 1e-10);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct CompiledGraph {
+    num_inputs: usize,
+    ops: Vec<MultiAD>,
+    arg_offsets: Vec<usize>,
+    args: Vec<usize>,
+    max_arity: usize,
+    /// `(node, k)` for every `Param(k)` node
+    param_nodes: Vec<(usize, usize)>,
+    num_params: usize,
+    /// Value slot of every node index of the source graph
+    slots: Vec<usize>,
+    policy: SubgradientPolicy,
+}
+
+/// Reusable scratch buffers for evaluating a [`CompiledGraph`].
+///
+/// Buffers are sized on first use, so a workspace may be created with
+/// [`Workspace::default`] or with [`CompiledGraph::workspace`], which sizes
+/// them up front.
+#[derive(Debug, Clone, Default)]
+pub struct Workspace {
+    values: Vec<f64>,
+    partials: Vec<f64>,
+    adjoints: Vec<f64>,
+    arg_values: Vec<f64>,
+}
+
+impl CompiledGraph {
+    /// Validate and flatten a graph with `num_inputs` inputs.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(AutodiffError)` if the graph is malformed; see
+    /// [`MultiAD::validate`].
+    pub fn new(exprs: &[(MultiAD, Vec<usize>)], num_inputs: usize) -> Result<Self> {
+        MultiAD::validate(exprs, num_inputs)?;
+        let mut graph = Self {
+            num_inputs,
+            ops: Vec::with_capacity(exprs.len()),
+            arg_offsets: Vec::with_capacity(exprs.len() + 1),
+            args: Vec::new(),
+            max_arity: 0,
+            param_nodes: Vec::new(),
+            num_params: 0,
+            slots: Vec::with_capacity(exprs.len()),
+            policy: SubgradientPolicy::default(),
+        };
+        graph.arg_offsets.push(0);
+
+        // Without Inp nodes, node indices 0..num_inputs are the inputs themselves
+        if !exprs.iter().any(|(op, _)| *op == MultiAD::Inp) {
+            graph.slots.extend(0..num_inputs);
+        }
+
+        for (op, args) in exprs {
+            if *op == MultiAD::Inp {
+                graph.slots.push(args[0]);
+                continue;
+            }
+            if let MultiAD::Param(k) = *op {
+                graph.param_nodes.push((graph.ops.len(), k));
+                graph.num_params = graph.num_params.max(k + 1);
+            }
+            graph.slots.push(graph.num_inputs + graph.ops.len());
+            graph.ops.push(*op);
+            graph.args.extend(args.iter().map(|&arg| graph.slots[arg]));
+            graph.arg_offsets.push(graph.args.len());
+            graph.max_arity = graph.max_arity.max(args.len());
+        }
+
+        Ok(graph)
+    }
+
+    /// Use `policy` for the derivatives of non-smooth ops at their kinks.
+    pub fn with_subgradient_policy(mut self, policy: SubgradientPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Policy used for the derivatives of non-smooth ops at their kinks.
+    pub fn subgradient_policy(&self) -> SubgradientPolicy {
+        self.policy
+    }
+
+    /// Number of inputs the graph expects.
+    pub fn num_inputs(&self) -> usize {
+        self.num_inputs
+    }
+
+    /// Number of parameters the graph reads (one more than the largest
+    /// `Param` index, or zero).
+    pub fn num_params(&self) -> usize {
+        self.num_params
+    }
+
+    /// Number of values the graph produces, inputs included.
+    pub fn num_values(&self) -> usize {
+        self.num_inputs + self.ops.len()
+    }
+
+    /// Create a workspace with buffers sized for this graph.
+    pub fn workspace(&self) -> Workspace {
+        let mut ws = Workspace::default();
+        self.prepare(&mut ws);
+        ws
+    }
+
+    /// Evaluate the graph output at `inputs`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(AutodiffError::DimensionMismatch)` if `inputs` does not have
+    /// one entry per graph input, and `Err(AutodiffError::MissingParams)` if
+    /// the graph has `Param` nodes; see [`CompiledGraph::value_with_params`].
+    pub fn value(&self, inputs: &[f64], ws: &mut Workspace) -> Result<f64> {
+        self.value_with_params(inputs, &[], ws)
+    }
+
+    /// Evaluate the graph output at `inputs`, with `Param(k)` nodes reading
+    /// `params[k]`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(AutodiffError::DimensionMismatch)` if `inputs` does not have
+    /// one entry per graph input, and `Err(AutodiffError::MissingParams)` if
+    /// `params` has fewer than [`CompiledGraph::num_params`] entries.
+    pub fn value_with_params(
+        &self,
+        inputs: &[f64],
+        params: &[f64],
+        ws: &mut Workspace,
+    ) -> Result<f64> {
+        self.forward(inputs, params, ws, false)?;
+        Ok(ws.values[self.output()])
+    }
+
+    /// Evaluate the graph output and write its gradient into `grad_out`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(AutodiffError::DimensionMismatch)` if `inputs` or `grad_out`
+    /// does not have one entry per graph input, and
+    /// `Err(AutodiffError::MissingParams)` if the graph has `Param` nodes; see
+    /// [`CompiledGraph::value_and_grad_with_params`].
+    pub fn value_and_grad(
+        &self,
+        inputs: &[f64],
+        ws: &mut Workspace,
+        grad_out: &mut [f64],
+    ) -> Result<f64> {
+        self.value_and_grad_with_params(inputs, &[], ws, grad_out, &mut [])
+    }
+
+    /// Evaluate the graph output with `Param(k)` nodes reading `params[k]`,
+    /// writing the gradient with respect to the inputs into `grad_out` and
+    /// with respect to the parameters into `param_grad_out`.
+    ///
+    /// `param_grad_out` has one entry per supplied parameter; entries for
+    /// parameters the graph does not read are set to zero.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(AutodiffError::DimensionMismatch)` if `inputs` or `grad_out`
+    /// does not have one entry per graph input or `param_grad_out` does not
+    /// have one entry per parameter, and `Err(AutodiffError::MissingParams)`
+    /// if `params` has fewer than [`CompiledGraph::num_params`] entries.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petite_ad::{multi_ops, CompiledGraph};
+    ///
+    /// // f(x; w) = w * x
+    /// let exprs = multi_ops![(inp, 0), (param, 0), (mul, 1, 0)];
+    /// let graph = CompiledGraph::new(&exprs, 1).unwrap();
+    /// let mut ws = graph.workspace();
+    /// let (mut grad, mut param_grad) = ([0.0], [0.0]);
+    /// let value = graph
+    ///     .value_and_grad_with_params(&[3.0], &[2.0], &mut ws, &mut grad, &mut param_grad)
+    ///     .unwrap();
+    /// assert_eq!((value, grad, param_grad), (6.0, [2.0], [3.0]));
+    /// ```
+    pub fn value_and_grad_with_params(
+        &self,
+        inputs: &[f64],
+        params: &[f64],
+        ws: &mut Workspace,
+        grad_out: &mut [f64],
+        param_grad_out: &mut [f64],
+    ) -> Result<f64> {
+        AutodiffError::check_dimension(self.num_inputs, grad_out.len())?;
+        AutodiffError::check_dimension(params.len(), param_grad_out.len())?;
+        self.forward(inputs, params, ws, true)?;
+
+        ws.adjoints.fill(0.0);
+        ws.adjoints[self.output()] = 1.0;
+        self.pull_back(&ws.partials, &mut ws.adjoints);
+        grad_out.copy_from_slice(&ws.adjoints[..self.num_inputs]);
+        param_grad_out.fill(0.0);
+        for &(node, k) in &self.param_nodes {
+            param_grad_out[k] += ws.adjoints[self.num_inputs + node];
+        }
+
+        Ok(ws.values[self.output()])
+    }
+
+    /// Value slot of the graph output, the last node of the source graph.
+    pub(crate) fn output(&self) -> usize {
+        // Validation guarantees at least one node
+        self.slots[self.slots.len() - 1]
+    }
+
+    /// Value slot of node `node` of the source graph, if it exists.
+    pub(crate) fn slot(&self, node: usize) -> Option<usize> {
+        self.slots.get(node).copied()
+    }
+
+    /// Number of addressable nodes of the source graph, implicit inputs included.
+    pub(crate) fn num_nodes(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Op code of node `i`.
+    pub(crate) fn op(&self, i: usize) -> MultiAD {
+        self.ops[i]
+    }
+
+    /// Number of (non-input) operation nodes.
+    pub(crate) fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// Argument indices of node `i`.
+    pub(crate) fn args(&self, i: usize) -> &[usize] {
+        &self.args[self.arg_offsets[i]..self.arg_offsets[i + 1]]
+    }
+
+    /// `(node, k)` for every `Param(k)` node.
+    pub(crate) fn param_nodes(&self) -> &[(usize, usize)] {
+        &self.param_nodes
+    }
+
+    /// Range of node `i` within the flattened argument and partial arrays.
+    pub(crate) fn arg_range(&self, i: usize) -> std::ops::Range<usize> {
+        self.arg_offsets[i]..self.arg_offsets[i + 1]
+    }
+
+    /// Size the workspace buffers for this graph; a no-op once they fit.
+    fn prepare(&self, ws: &mut Workspace) {
+        ws.values.resize(self.num_values(), 0.0);
+        ws.adjoints.resize(self.num_values(), 0.0);
+        ws.partials.resize(self.args.len(), 0.0);
+        ws.arg_values.resize(self.max_arity, 0.0);
+    }
+
+    /// Forward pass into the workspace, optionally recording local partials.
+    ///
+    /// `params` must hold at least [`CompiledGraph::num_params`] values.
+    pub(crate) fn forward(
+        &self,
+        inputs: &[f64],
+        params: &[f64],
+        ws: &mut Workspace,
+        record_partials: bool,
+    ) -> Result<()> {
+        AutodiffError::check_dimension(self.num_inputs, inputs.len())?;
+        if params.len() < self.num_params {
+            return Err(AutodiffError::MissingParams {
+                expected: self.num_params,
+                actual: params.len(),
+            });
+        }
+        self.prepare(ws);
+        ws.values[..self.num_inputs].copy_from_slice(inputs);
+
+        for (i, op) in self.ops.iter().enumerate() {
+            let range = self.arg_range(i);
+            let arg_values = &mut ws.arg_values[..range.len()];
+            for (slot, &arg) in arg_values.iter_mut().zip(&self.args[range.clone()]) {
+                *slot = ws.values[arg];
+            }
+            ws.values[self.num_inputs + i] = op.apply(arg_values, params);
+            if record_partials {
+                op.partials(arg_values, self.policy, &mut ws.partials[range]);
+            }
+        }
+        Ok(())
+    }
+
+    /// Propagate node cotangents backward in place using recorded partials.
+    ///
+    /// `cotangents` holds one seed per value on entry; on return its first
+    /// `num_inputs` entries hold the accumulated input cotangents. Zero
+    /// cotangents and zero partials are skipped, as in
+    /// [`FlatTape::push_forward`](super::tape::FlatTape::push_forward).
+    pub(crate) fn pull_back(&self, partials: &[f64], cotangents: &mut [f64]) {
+        for i in (0..self.ops.len()).rev() {
+            let cotangent = cotangents[self.num_inputs + i];
+            if cotangent == 0.0 {
+                continue;
+            }
+            let range = self.arg_range(i);
+            for (&arg, &d) in self.args[range.clone()].iter().zip(&partials[range]) {
+                if d != 0.0 {
+                    cotangents[arg] += cotangent * d;
+                }
+            }
+        }
+    }
+}
+
+impl Workspace {
+    /// Values of every node from the last evaluation, inputs first.
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+
+    /// Local partials recorded by the last gradient evaluation.
+    pub(crate) fn partials(&self) -> &[f64] {
+        &self.partials
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::multi_ops;
+    use crate::test_utils::{analytic_fns, approx_eq_eps as approx_eq};
+
+    #[test]
+    fn test_workspace_reuse_across_graphs() {
+        // One workspace, sized for none of the graphs, serves all of them in turn
+        let mut ws = Workspace::default();
+        let mut grad = [0.0; 2];
+        for _ in 0..2 {
+            for f in analytic_fns() {
+                let graph = CompiledGraph::new(f.graph(), 2).unwrap();
+                let value = graph
+                    .value_and_grad(&f.inputs(), &mut ws, &mut grad)
+                    .unwrap();
+                assert!(approx_eq(value, f.expected_value(), 1e-10));
+                for (g, e) in grad.iter().zip(f.expected_gradients()) {
+                    assert!(approx_eq(*g, e, 1e-10));
+                }
+                let value = graph.value(&f.inputs(), &mut ws).unwrap();
+                assert!(approx_eq(value, f.expected_value(), 1e-10));
+            }
+        }
+    }
+
+    #[test]
+    fn test_workspace_reuse_across_points() {
+        let exprs = multi_ops![(inp, 0), (inp, 1), (mul, 0, 1), (exp, 2)];
+        let graph = CompiledGraph::new(&exprs, 2).unwrap();
+        let mut ws = Workspace::default();
+        let mut grad = [0.0; 2];
+
+        for (x, y) in [(0.1, 0.2), (1.0, -1.0), (0.5, 0.5)] {
+            let value = graph.value_and_grad(&[x, y], &mut ws, &mut grad).unwrap();
+            let e = (x * y).exp();
+            assert!(approx_eq(value, e, 1e-10));
+            assert!(approx_eq(grad[0], y * e, 1e-10));
+            assert!(approx_eq(grad[1], x * e, 1e-10));
+        }
+    }
+
+    #[test]
+    fn test_grad_out_size_is_checked() {
+        let exprs = multi_ops![(inp, 0), (inp, 1), (mul, 0, 1)];
+        let graph = CompiledGraph::new(&exprs, 2).unwrap();
+        let mut ws = graph.workspace();
+        for len in [1, 3] {
+            let mut grad = vec![7.0; len];
+            assert_eq!(
+                graph.value_and_grad(&[1.0, 2.0], &mut ws, &mut grad),
+                Err(AutodiffError::DimensionMismatch {
+                    expected: 2,
+                    actual: len
+                })
+            );
+            // The buffer is left untouched on error
+            assert!(grad.iter().all(|&g| g == 7.0));
+        }
+        let mut grad = [0.0; 2];
+        assert_eq!(
+            graph.value_and_grad(&[1.0, 2.0], &mut ws, &mut grad),
+            Ok(2.0)
+        );
+        assert_eq!(grad, [2.0, 1.0]);
+    }
+
+    #[test]
+    fn test_compile_errors() {
+        let exprs = [(MultiAD::Sin, vec![0, 1])];
+        assert_eq!(
+            CompiledGraph::new(&exprs, 2).unwrap_err(),
+            AutodiffError::arity("Sin", 1, 2)
+        );
+
+        // Node at index 2 may only reference 0 and 1
+        let exprs = [(MultiAD::Add, vec![0, 2])];
+        assert_eq!(
+            CompiledGraph::new(&exprs, 2).unwrap_err(),
+            AutodiffError::ForwardReference { node: 2, index: 2 }
+        );
+        assert_eq!(
+            CompiledGraph::new(&[], 2).unwrap_err(),
+            AutodiffError::EmptyGraph
+        );
+    }
+
+    #[test]
+    fn test_params() {
+        // f(x; w, b) = w * x + b
+        let exprs = multi_ops![(inp, 0), (param, 0), (param, 1), (mul, 1, 0), (add, 3, 2)];
+        let graph = CompiledGraph::new(&exprs, 1).unwrap();
+        let mut ws = graph.workspace();
+        assert_eq!(graph.num_params(), 2);
+        assert_eq!(
+            graph.value_with_params(&[3.0], &[2.0, 1.0], &mut ws),
+            Ok(7.0)
+        );
+
+        let (mut grad, mut param_grad) = ([0.0], [9.0; 3]);
+        let value = graph
+            .value_and_grad_with_params(
+                &[3.0],
+                &[2.0, 1.0, 5.0],
+                &mut ws,
+                &mut grad,
+                &mut param_grad,
+            )
+            .unwrap();
+        assert_eq!((value, grad, param_grad), (7.0, [2.0], [3.0, 1.0, 0.0]));
+
+        let missing = Err(AutodiffError::MissingParams {
+            expected: 2,
+            actual: 0,
+        });
+        assert_eq!(graph.value(&[3.0], &mut ws), missing);
+        assert_eq!(graph.value_and_grad(&[3.0], &mut ws, &mut grad), missing);
+        assert_eq!(
+            graph.value_and_grad_with_params(&[3.0], &[2.0, 1.0], &mut ws, &mut grad, &mut [0.0]),
+            Err(AutodiffError::DimensionMismatch {
+                expected: 2,
+                actual: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_input_length_mismatch() {
+        let exprs = multi_ops![(inp, 0), (inp, 1), (add, 0, 1)];
+        let graph = CompiledGraph::new(&exprs, 2).unwrap();
+        let mut ws = graph.workspace();
+        assert_eq!(
+            graph.value(&[1.0], &mut ws),
+            Err(AutodiffError::DimensionMismatch {
+                expected: 2,
+                actual: 1
+            })
+        );
+        let mut grad = [0.0; 3];
+        assert_eq!(
+            graph.value_and_grad(&[1.0, 2.0], &mut ws, &mut grad),
+            Err(AutodiffError::DimensionMismatch {
+                expected: 2,
+                actual: 3
+            })
+        );
+    }
+}
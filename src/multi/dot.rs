@@ -0,0 +1,255 @@
+//! Graphviz DOT export.
+//!
+//! [`MultiAD::to_dot`] draws the structure of a graph: one box per node,
+//! labelled with its operation, and one edge per argument. The annotated
+//! variant [`MultiAD::to_dot_annotated`] evaluates the graph first and adds
+//! each node's value and adjoint, and each edge's local partial, which shows
+//! a whole backward pass at a glance.
+
+use std::fmt::Write;
+
+use super::multi_ad::MultiAD;
+use super::subgradient::SubgradientPolicy;
+use super::tape::FlatTape;
+use crate::error::Result;
+
+/// Label of a node, with the input, constant or parameter it holds.
+fn op_label(op: MultiAD, args: &[usize]) -> String {
+    match op {
+        // Drawn without validation, so an Inp node may lack its argument
+        MultiAD::Inp => match args.first() {
+            Some(input) => format!("Inp {input}"),
+            None => op.op_name().to_string(),
+        },
+        MultiAD::Const(c) => format!("Const {c}"),
+        MultiAD::Param(k) => format!("Param {k}"),
+        _ => op.op_name().to_string(),
+    }
+}
+
+/// A node to draw: its label and incoming edges as `(argument, edge label)`.
+struct DotNode {
+    label: String,
+    edges: Vec<(usize, String)>,
+}
+
+/// Render nodes as a DOT digraph, marking the last node as the output.
+fn render(nodes: &[DotNode]) -> String {
+    let mut dot = String::from("digraph {\n    rankdir=BT;\n    node [shape=box];\n");
+    for (index, node) in nodes.iter().enumerate() {
+        let output = if index + 1 == nodes.len() {
+            ", peripheries=2"
+        } else {
+            ""
+        };
+        // Writing to a String cannot fail
+        let _ = writeln!(dot, "    n{index} [label=\"{}\"{output}];", node.label);
+    }
+    for (index, node) in nodes.iter().enumerate() {
+        for (arg, label) in &node.edges {
+            if label.is_empty() {
+                let _ = writeln!(dot, "    n{arg} -> n{index};");
+            } else {
+                let _ = writeln!(dot, "    n{arg} -> n{index} [label=\"{label}\"];");
+            }
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+impl MultiAD {
+    /// Render a graph in Graphviz DOT format.
+    ///
+    /// Node `i` is drawn as `n{i}` and labelled with its operation; edges run
+    /// from each argument to the node using it, labelled with the argument
+    /// position for operations with several arguments. The output node has a
+    /// double border. The graph is drawn as given, without validation; a graph
+    /// without `Inp` nodes gets one drawn for each of its `num_inputs` inputs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petite_ad::{MultiAD, multi_ops};
+    ///
+    /// let exprs = multi_ops![(inp, 0), (inp, 1), (sub, 0, 1)];
+    /// let dot = MultiAD::to_dot(&exprs, 2);
+    /// assert!(dot.contains("n2 [label=\"Sub\", peripheries=2];"));
+    /// assert!(dot.contains("n1 -> n2 [label=\"1\"];"));
+    /// // Render with: dot -Tsvg graph.dot -o graph.svg
+    /// ```
+    pub fn to_dot(exprs: &[(MultiAD, Vec<usize>)], num_inputs: usize) -> String {
+        let nodes: Vec<DotNode> = Self::with_input_nodes(exprs, num_inputs)
+            .iter()
+            .map(|(op, args)| DotNode {
+                label: op_label(*op, args),
+                edges: match op {
+                    MultiAD::Inp => Vec::new(),
+                    _ => edge_positions(args),
+                },
+            })
+            .collect();
+        render(&nodes)
+    }
+
+    /// Render a graph in DOT format, annotated with one evaluation.
+    ///
+    /// Like [`MultiAD::to_dot`], but every node also shows its value and its
+    /// adjoint `∂f/∂node` at `inputs`, and every edge shows the local partial
+    /// of the node with respect to that argument. `Inp` nodes show the total
+    /// adjoint of their input, summed over every use. Graphs without `Inp`
+    /// nodes get one drawn per input.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(AutodiffError)` if the graph is malformed (see
+    /// [`MultiAD::validate`]) or reads parameters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petite_ad::{MultiAD, multi_ops};
+    ///
+    /// let exprs = multi_ops![(inp, 0), (inp, 1), (mul, 0, 1)];
+    /// let dot = MultiAD::to_dot_annotated(&exprs, &[3.0, 4.0]).unwrap();
+    /// assert!(dot.contains("Inp 0\\nvalue = 3\\nadjoint = 4"));
+    /// assert!(dot.contains("n0 -> n2 [label=\"0: ∂ = 4\"];"));
+    /// ```
+    pub fn to_dot_annotated(exprs: &[(MultiAD, Vec<usize>)], inputs: &[f64]) -> Result<String> {
+        let tape = FlatTape::record(exprs, inputs, &[], SubgradientPolicy::default())?;
+        let mut adjoints = vec![0.0; tape.values().len()];
+        adjoints[tape.output_slot()] = 1.0;
+        tape.pull_back(&mut adjoints);
+
+        // Graphs without Inp nodes take their inputs at the first indices
+        let implicit = tape.num_nodes() - exprs.len();
+        let nodes: Vec<DotNode> = (0..tape.num_nodes())
+            .map(|index| {
+                let (op, args) = match index.checked_sub(implicit) {
+                    Some(position) => (exprs[position].0, exprs[position].1.as_slice()),
+                    None => (MultiAD::Inp, std::slice::from_ref(&index)),
+                };
+                // The slot exists for every node index below num_nodes
+                let slot = tape.slot(index).unwrap_or_default();
+                let label = format!(
+                    "{}\\nvalue = {}\\nadjoint = {}",
+                    op_label(op, args),
+                    format_number(tape.values()[slot]),
+                    format_number(adjoints[slot]),
+                );
+                let edges = match op {
+                    MultiAD::Inp => Vec::new(),
+                    _ => edge_positions(args)
+                        .into_iter()
+                        .zip(tape.partials(slot - tape.num_inputs()))
+                        .map(|((arg, position), d)| {
+                            let sep = if position.is_empty() { "" } else { ": " };
+                            (arg, format!("{position}{sep}∂ = {}", format_number(*d)))
+                        })
+                        .collect(),
+                };
+                DotNode { label, edges }
+            })
+            .collect();
+        Ok(render(&nodes))
+    }
+}
+
+/// Edges of a node, labelled with the argument position when it has several.
+fn edge_positions(args: &[usize]) -> Vec<(usize, String)> {
+    args.iter()
+        .enumerate()
+        .map(|(position, &arg)| {
+            let label = if args.len() > 1 {
+                position.to_string()
+            } else {
+                String::new()
+            };
+            (arg, label)
+        })
+        .collect()
+}
+
+/// Shortest of the full value and four decimals, so labels stay readable.
+fn format_number(x: f64) -> String {
+    let full = x.to_string();
+    let short = format!("{x:.4}");
+    if full.len() <= short.len() {
+        full
+    } else {
+        short
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::AutodiffError;
+    use crate::multi_ops;
+
+    #[test]
+    fn test_to_dot() {
+        let exprs = multi_ops![(inp, 0), (const, 2.5), (mul, 0, 1), (sin, 2)];
+        assert_eq!(
+            MultiAD::to_dot(&exprs, 1),
+            "digraph {
+    rankdir=BT;
+    node [shape=box];
+    n0 [label=\"Inp 0\"];
+    n1 [label=\"Const 2.5\"];
+    n2 [label=\"Mul\"];
+    n3 [label=\"Sin\", peripheries=2];
+    n0 -> n2 [label=\"0\"];
+    n1 -> n2 [label=\"1\"];
+    n2 -> n3;
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_to_dot_implicit_inputs_and_malformed_nodes() {
+        let dot = MultiAD::to_dot(&multi_ops![(exp, 0)], 1);
+        assert!(dot.contains("n0 [label=\"Inp 0\"];"));
+        assert!(dot.contains("n1 [label=\"Exp\", peripheries=2];"));
+        assert!(dot.contains("n0 -> n1;"));
+        assert!(!dot.contains("n1 -> n1"));
+
+        // x * z: node 2 is input z, not the dead sin(x) node
+        let dot = MultiAD::to_dot(&multi_ops![(sin, 0), (mul, 0, 2)], 3);
+        assert!(dot.contains("n2 [label=\"Inp 2\"];"));
+        assert!(dot.contains("n3 [label=\"Sin\"];"));
+        assert!(dot.contains("n2 -> n4 [label=\"1\"];"));
+
+        // Drawn without validation, so a bad arity is shown rather than panicking
+        let dot = MultiAD::to_dot(&[(MultiAD::Inp, vec![]), (MultiAD::Sin, vec![0])], 1);
+        assert!(dot.contains("n0 [label=\"Inp\"];"));
+    }
+
+    #[test]
+    fn test_to_dot_annotated() {
+        // f(x, y) = sin(x) * (x + y) at (0, 2): ∂f/∂x = 2, ∂f/∂y = 0
+        let exprs = multi_ops![(inp, 0), (inp, 1), (add, 0, 1), (sin, 0), (mul, 2, 3)];
+        let dot = MultiAD::to_dot_annotated(&exprs, &[0.0, 2.0]).unwrap();
+        assert!(dot.contains("n0 [label=\"Inp 0\\nvalue = 0\\nadjoint = 2\"];"));
+        assert!(dot.contains("n1 [label=\"Inp 1\\nvalue = 2\\nadjoint = 0\"];"));
+        assert!(dot.contains("n2 [label=\"Add\\nvalue = 2\\nadjoint = 0\"];"));
+        assert!(dot.contains("n4 [label=\"Mul\\nvalue = 0\\nadjoint = 1\", peripheries=2];"));
+        assert!(dot.contains("n0 -> n3 [label=\"∂ = 1\"];"));
+        assert!(dot.contains("n3 -> n4 [label=\"1: ∂ = 2\"];"));
+    }
+
+    #[test]
+    fn test_to_dot_annotated_implicit_inputs() {
+        let exprs = multi_ops![(exp, 0)];
+        let dot = MultiAD::to_dot_annotated(&exprs, &[1.0]).unwrap();
+        assert!(dot.contains("n0 [label=\"Inp 0\\nvalue = 1\\nadjoint = 2.7183\"];"));
+        assert!(dot.contains("n1 [label=\"Exp\\nvalue = 2.7183\\nadjoint = 1\", peripheries=2];"));
+        assert!(dot.contains("n0 -> n1 [label=\"∂ = 2.7183\"];"));
+
+        assert_eq!(
+            MultiAD::to_dot_annotated(&multi_ops![(inp, 0), (sin, 1)], &[1.0]),
+            Err(AutodiffError::ForwardReference { node: 1, index: 1 })
+        );
+    }
+}
@@ -41,3 +41,14 @@ impl MultiFn for F1 {
         vec![df_dx1, df_dx2]
     }
 }
+
+#[cfg(test)]
+impl F1 {
+    /// Analytical Hessian of f:
+    /// ∂²f/∂x₁² = 2cos(x₁) - sin(x₁) * (x₁ + x₂), ∂²f/∂x₁∂x₂ = cos(x₁), ∂²f/∂x₂² = 0
+    pub fn expected_hessian(&self) -> Vec<Vec<f64>> {
+        let d11 = 2.0 * self.0.cos() - self.0.sin() * (self.0 + self.1);
+        let d12 = self.0.cos();
+        vec![vec![d11, d12], vec![d12, 0.0]]
+    }
+}
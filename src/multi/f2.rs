@@ -41,3 +41,16 @@ impl MultiFn for F2 {
         vec![df_dx1, df_dx2]
     }
 }
+
+#[cfg(test)]
+impl F2 {
+    /// Analytical Hessian of f, writing u = x₁ - x₂:
+    /// ∂²f/∂x₁² = -sin/u - 2cos/u² + 2sin/u³, ∂²f/∂x₁∂x₂ = cos/u² - 2sin/u³, ∂²f/∂x₂² = 2sin/u³
+    pub fn expected_hessian(&self) -> Vec<Vec<f64>> {
+        let (s, c, u) = (self.0.sin(), self.0.cos(), self.0 - self.1);
+        let d11 = -s / u - 2.0 * c / u.powi(2) + 2.0 * s / u.powi(3);
+        let d12 = c / u.powi(2) - 2.0 * s / u.powi(3);
+        let d22 = 2.0 * s / u.powi(3);
+        vec![vec![d11, d12], vec![d12, d22]]
+    }
+}
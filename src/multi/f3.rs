@@ -41,3 +41,15 @@ impl MultiFn for F3 {
         vec![df_dx1, df_dx2]
     }
 }
+
+#[cfg(test)]
+impl F3 {
+    /// Analytical Hessian of f:
+    /// ∂²f/∂x₁² = -sin(x₁) ln(x₂), ∂²f/∂x₁∂x₂ = cos(x₁) / x₂, ∂²f/∂x₂² = -sin(x₁) / x₂²
+    pub fn expected_hessian(&self) -> Vec<Vec<f64>> {
+        let d11 = -self.0.sin() * self.1.ln();
+        let d12 = self.0.cos() / self.1;
+        let d22 = -self.0.sin() / self.1.powi(2);
+        vec![vec![d11, d12], vec![d12, d22]]
+    }
+}
@@ -0,0 +1,335 @@
+//! Second derivatives of multi-variable graphs.
+//!
+//! Hessian-vector products are computed forward-over-reverse: a forward sweep
+//! pushes the direction `v` through the graph, then a reverse sweep propagates
+//! both the adjoints and their directional derivatives using each operation's
+//! second-derivative rule.
+
+use super::multi_ad::MultiAD;
+use super::subgradient::SubgradientPolicy;
+use super::tape::FlatTape;
+use crate::error::{AutodiffError, Result};
+
+impl MultiAD {
+    /// Compute the Hessian-vector product `H(x) · v`.
+    ///
+    /// Costs roughly one forward and one backward pass, independently of the
+    /// number of inputs, so it is much cheaper than forming the full Hessian.
+    ///
+    /// # Arguments
+    ///
+    /// * `exprs` - Computational graph as (operation, indices) pairs
+    /// * `inputs` - Input values to evaluate at
+    /// * `v` - Direction vector, one entry per input
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(AutodiffError)` if an operation receives incorrect arity,
+    /// or if `v` does not have one entry per input.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petite_ad::{MultiAD, multi_ops};
+    ///
+    /// // f(x, y) = x * y has Hessian [[0, 1], [1, 0]]
+    /// let exprs = multi_ops![(inp, 0), (inp, 1), (mul, 0, 1)];
+    /// let hv = MultiAD::hvp(&exprs, &[3.0, 4.0], &[1.0, 2.0]).unwrap();
+    /// assert_eq!(hv, vec![2.0, 1.0]);
+    /// ```
+    #[must_use = "hessian computation is expensive; discarding the result is likely a bug"]
+    pub fn hvp(exprs: &[(MultiAD, Vec<usize>)], inputs: &[f64], v: &[f64]) -> Result<Vec<f64>> {
+        AutodiffError::check_dimension(inputs.len(), v.len())?;
+
+        // Forward sweep: primal values and local partials, then tangents along v
+        let tape = FlatTape::record(exprs, inputs, &[], SubgradientPolicy::default())?;
+        let mut tangents = Vec::with_capacity(tape.values().len());
+        tape.push_forward(v, &mut tangents);
+        Ok(second_order_sweep(&tape, &tangents, inputs.len()))
+    }
+
+    /// Compute the full Hessian matrix of second derivatives.
+    ///
+    /// Row `i` holds `∂²f/∂xᵢ∂xⱼ` for every `j`. The graph is recorded once,
+    /// then each row takes one tangent sweep and one second-order reverse
+    /// sweep along a unit vector, as in [`MultiAD::hvp`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(AutodiffError)` if an operation receives incorrect arity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petite_ad::{MultiAD, multi_ops};
+    ///
+    /// // f(x, y) = x^y
+    /// let exprs = multi_ops![(inp, 0), (inp, 1), (pow, 0, 1)];
+    /// let hessian = MultiAD::hessian(&exprs, &[2.0, 3.0]).unwrap();
+    /// // ∂²f/∂x² = y (y - 1) x^(y - 2) = 12
+    /// assert!((hessian[0][0] - 12.0).abs() < 1e-10);
+    /// ```
+    #[must_use = "hessian computation is expensive; discarding the result is likely a bug"]
+    pub fn hessian(exprs: &[(MultiAD, Vec<usize>)], inputs: &[f64]) -> Result<Vec<Vec<f64>>> {
+        let tape = FlatTape::record(exprs, inputs, &[], SubgradientPolicy::default())?;
+        let mut tangents = Vec::with_capacity(tape.values().len());
+        let mut unit = vec![0.0; inputs.len()];
+        Ok((0..inputs.len())
+            .map(|i| {
+                unit[i] = 1.0;
+                tape.push_forward(&unit, &mut tangents);
+                unit[i] = 0.0;
+                second_order_sweep(&tape, &tangents, inputs.len())
+            })
+            .collect())
+    }
+}
+
+/// Reverse sweep of adjoints and their derivatives along the direction whose
+/// node tangents are `tangents`, returning the first `num_inputs` adjoint
+/// tangents: the Hessian-vector product.
+fn second_order_sweep(tape: &FlatTape, tangents: &[f64], num_inputs: usize) -> Vec<f64> {
+    let num_nodes = tape.values().len();
+    let mut adjoints = vec![0.0; num_nodes];
+    let mut adjoint_tangents = vec![0.0; num_nodes];
+    adjoints[tape.output_slot()] = 1.0;
+
+    // Scratch buffers reused across nodes
+    let mut arg_values: Vec<f64> = Vec::new();
+    let mut second_partials: Vec<f64> = Vec::new();
+
+    for i in (0..tape.len()).rev() {
+        let output_idx = tape.num_inputs() + i;
+        let adjoint = adjoints[output_idx];
+        let adjoint_tangent = adjoint_tangents[output_idx];
+        let args = tape.args(i);
+        let partials = tape.partials(i);
+        let n = args.len();
+
+        arg_values.clear();
+        arg_values.extend(args.iter().map(|&arg| tape.values()[arg]));
+        second_partials.resize(n * n, 0.0);
+        tape.op(i)
+            .second_partials(&arg_values, &mut second_partials);
+
+        // Terms with a zero factor are skipped, as in `FlatTape::push_forward`
+        for (j, &arg_j) in args.iter().enumerate() {
+            let partial = partials[j];
+            if adjoint_tangent != 0.0 && partial != 0.0 {
+                adjoint_tangents[arg_j] += adjoint_tangent * partial;
+            }
+            if adjoint == 0.0 {
+                continue;
+            }
+            let curvature: f64 = args
+                .iter()
+                .enumerate()
+                .map(|(k, &arg_k)| (second_partials[j * n + k], tangents[arg_k]))
+                .filter(|&(d2, t)| d2 != 0.0 && t != 0.0)
+                .map(|(d2, t)| d2 * t)
+                .sum();
+            if partial != 0.0 {
+                adjoints[arg_j] += adjoint * partial;
+            }
+            adjoint_tangents[arg_j] += adjoint * curvature;
+        }
+    }
+
+    adjoint_tangents.truncate(num_inputs);
+    adjoint_tangents
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::multi::f1::F1;
+    use crate::multi::f2::F2;
+    use crate::multi::f3::F3;
+    use crate::multi::multi_fn::MultiFn;
+    use crate::multi_ops;
+    use crate::test_utils::approx_eq_eps as approx_eq;
+
+    fn assert_matrix_eq(actual: &[Vec<f64>], expected: &[Vec<f64>], epsilon: f64) {
+        assert_eq!(actual.len(), expected.len());
+        for (row_a, row_e) in actual.iter().zip(expected) {
+            for (a, e) in row_a.iter().zip(row_e) {
+                assert!(
+                    approx_eq(*a, *e, epsilon),
+                    "hessian mismatch: got {:?}, expected {:?}",
+                    actual,
+                    expected
+                );
+            }
+        }
+    }
+
+    /// Central differences of the reverse-mode gradient.
+    fn finite_difference_hessian(exprs: &[(MultiAD, Vec<usize>)], inputs: &[f64]) -> Vec<Vec<f64>> {
+        let h = 1e-6;
+        (0..inputs.len())
+            .map(|i| {
+                let mut plus = inputs.to_vec();
+                let mut minus = inputs.to_vec();
+                plus[i] += h;
+                minus[i] -= h;
+                let grad_plus = MultiAD::compute_grad(exprs, &plus).unwrap().1(1.0);
+                let grad_minus = MultiAD::compute_grad(exprs, &minus).unwrap().1(1.0);
+                grad_plus
+                    .iter()
+                    .zip(&grad_minus)
+                    .map(|(p, m)| (p - m) / (2.0 * h))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_hessian_f1() {
+        let f = F1(0.5, 1.0);
+        let hessian = MultiAD::hessian(f.graph(), &f.inputs()).unwrap();
+        assert_matrix_eq(&hessian, &f.expected_hessian(), 1e-10);
+    }
+
+    #[test]
+    fn test_hessian_f2() {
+        let f = F2(0.5, 1.0);
+        let hessian = MultiAD::hessian(f.graph(), &f.inputs()).unwrap();
+        assert_matrix_eq(&hessian, &f.expected_hessian(), 1e-10);
+    }
+
+    #[test]
+    fn test_hessian_f3() {
+        let f = F3(0.5, 1.3);
+        let hessian = MultiAD::hessian(f.graph(), &f.inputs()).unwrap();
+        assert_matrix_eq(&hessian, &f.expected_hessian(), 1e-10);
+    }
+
+    #[test]
+    fn test_hessian_all_ops_against_finite_differences() {
+        // f(x, y) = x^y / tan(x) + ln(y) * sqrt(x) - abs(x - y)
+        let exprs = multi_ops![
+            (inp, 0),
+            (inp, 1),
+            (pow, 0, 1),
+            (tan, 0),
+            (div, 2, 3),
+            (ln, 1),
+            (sqrt, 0),
+            (mul, 5, 6),
+            (add, 4, 7),
+            (sub, 0, 1),
+            (abs, 9),
+            (sub, 8, 10),
+        ];
+        for inputs in [[1.2, 2.5], [0.7, 0.4]] {
+            let hessian = MultiAD::hessian(&exprs, &inputs).unwrap();
+            let expected = finite_difference_hessian(&exprs, &inputs);
+            assert_matrix_eq(&hessian, &expected, 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_hessian_extended_ops_against_finite_differences() {
+        let (exprs, _) = MultiAD::parse(
+            "tanh(x * y) + sinh(x) * cosh(y) + asin(x) * acos(x / y) + atan(y) + atan2(y, x) \
+             + log1p(x) * expm1(y) + log2(y) - log10(x) + sigmoid(x - y) * softplus(x * y) \
+             + hypot(x, y)",
+        )
+        .unwrap();
+        for inputs in [[0.4, 1.5], [0.7, 2.0]] {
+            let hessian = MultiAD::hessian(&exprs, &inputs).unwrap();
+            let expected = finite_difference_hessian(&exprs, &inputs);
+            assert_matrix_eq(&hessian, &expected, 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_hessian_special_ops_against_finite_differences() {
+        let (exprs, _) = MultiAD::parse(
+            "erf(x * y) + erfc(y) * lgamma(x + y) + digamma(y) * normal_cdf(x - y) \
+             + normal_logpdf(x * y) + lambert_w(x / y) + trigamma(x + y)",
+        )
+        .unwrap();
+        for inputs in [[0.4, 1.5], [-0.7, 2.0]] {
+            let hessian = MultiAD::hessian(&exprs, &inputs).unwrap();
+            let expected = finite_difference_hessian(&exprs, &inputs);
+            assert_matrix_eq(&hessian, &expected, 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_hessian_nary_ops_against_finite_differences() {
+        let (exprs, _) = MultiAD::parse(
+            "sum(x, y, x * y) * prod(x, y, z) + mean(x, z) ^ 2 + logsumexp(x, y, z) \
+             - softmax_sum(x * z, y, z)",
+        )
+        .unwrap();
+        for inputs in [[0.4, 1.5, -0.3], [0.0, 2.0, 0.5]] {
+            let hessian = MultiAD::hessian(&exprs, &inputs).unwrap();
+            let expected = finite_difference_hessian(&exprs, &inputs);
+            assert_matrix_eq(&hessian, &expected, 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_hessian_negative_base_with_const_exponent() {
+        // ln of the negative base makes the exponent partials NaN, but the
+        // exponent is constant so they never contribute
+        let exprs = multi_ops![(inp, 0), (const, 2.0), (pow, 0, 1)];
+        let hessian = MultiAD::hessian(&exprs, &[-1.5]).unwrap();
+        assert_matrix_eq(&hessian, &[vec![2.0]], 1e-10);
+
+        let (exprs, _) = MultiAD::parse("x^2 + y").unwrap();
+        let hessian = MultiAD::hessian(&exprs, &[-1.5, 0.5]).unwrap();
+        assert_matrix_eq(&hessian, &[vec![2.0, 0.0], vec![0.0, 0.0]], 1e-10);
+        let hv = MultiAD::hvp(&exprs, &[-1.5, 0.5], &[1.0, 3.0]).unwrap();
+        assert_eq!(hv, vec![2.0, 0.0]);
+    }
+
+    #[test]
+    fn test_hvp_matches_hessian_product() {
+        let exprs = multi_ops![
+            (inp, 0),
+            (inp, 1),
+            (inp, 2),
+            (mul, 0, 1),
+            (exp, 2),
+            (cos, 3),
+            (mul, 5, 4),
+        ];
+        let inputs = [0.3, -1.1, 0.8];
+        let v = [1.5, -0.5, 2.0];
+
+        let hessian = MultiAD::hessian(&exprs, &inputs).unwrap();
+        let hv = MultiAD::hvp(&exprs, &inputs, &v).unwrap();
+        for (row, actual) in hessian.iter().zip(&hv) {
+            let expected: f64 = row.iter().zip(&v).map(|(h, v)| h * v).sum();
+            assert!(approx_eq(*actual, expected, 1e-10));
+        }
+    }
+
+    #[test]
+    fn test_hessian_is_symmetric() {
+        let f = F2(0.9, -0.4);
+        let hessian = MultiAD::hessian(f.graph(), &f.inputs()).unwrap();
+        assert!(approx_eq(hessian[0][1], hessian[1][0], 1e-12));
+    }
+
+    #[test]
+    fn test_hvp_errors() {
+        let exprs = multi_ops![(inp, 0), (inp, 1), (mul, 0, 1)];
+        assert_eq!(
+            MultiAD::hvp(&exprs, &[1.0, 2.0], &[1.0, 0.0, 0.0]),
+            Err(AutodiffError::DimensionMismatch {
+                expected: 2,
+                actual: 3
+            })
+        );
+
+        let exprs = [(MultiAD::Mul, vec![0])];
+        assert_eq!(
+            MultiAD::hessian(&exprs, &[1.0]),
+            Err(AutodiffError::arity("Mul", 2, 1))
+        );
+    }
+}
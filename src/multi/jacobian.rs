@@ -0,0 +1,330 @@
+//! Multi-output evaluation and Jacobians.
+//!
+//! Any set of nodes can be marked as outputs by passing their indices. The
+//! graph is recorded once on a flat tape (values plus local partials per node)
+//! and then swept forward per input or backward per output, whichever is
+//! cheaper.
+
+use super::multi_ad::MultiAD;
+use super::subgradient::SubgradientPolicy;
+use super::tape::FlatTape;
+use crate::error::{AutodiffError, Result};
+
+/// Record the tape and map every output node to its value slot.
+fn record_with_outputs(
+    exprs: &[(MultiAD, Vec<usize>)],
+    inputs: &[f64],
+    outputs: &[usize],
+) -> Result<(FlatTape, Vec<usize>)> {
+    let tape = FlatTape::record(exprs, inputs, &[], SubgradientPolicy::default())?;
+    let slots = outputs
+        .iter()
+        .map(|&index| {
+            tape.slot(index).ok_or(AutodiffError::IndexOutOfBounds {
+                index,
+                max_index: tape.num_nodes().saturating_sub(1),
+            })
+        })
+        .collect::<Result<_>>()?;
+    Ok((tape, slots))
+}
+
+impl MultiAD {
+    /// Compute the values of several output nodes in one forward pass.
+    ///
+    /// # Arguments
+    ///
+    /// * `exprs` - Computational graph as (operation, indices) pairs
+    /// * `outputs` - Indices of the nodes to read out
+    /// * `inputs` - Input values to evaluate at
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(AutodiffError)` if an operation receives incorrect arity or
+    /// an output index does not refer to a node.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petite_ad::{MultiAD, multi_ops};
+    ///
+    /// let exprs = multi_ops![(inp, 0), (inp, 1), (add, 0, 1), (mul, 0, 1)];
+    /// let values = MultiAD::compute_outputs(&exprs, &[2, 3], &[2.0, 3.0]).unwrap();
+    /// assert_eq!(values, vec![5.0, 6.0]);
+    /// ```
+    #[must_use = "forward computation is expensive; discarding the result is likely a bug"]
+    pub fn compute_outputs(
+        exprs: &[(MultiAD, Vec<usize>)],
+        outputs: &[usize],
+        inputs: &[f64],
+    ) -> Result<Vec<f64>> {
+        let (tape, slots) = record_with_outputs(exprs, inputs, outputs)?;
+        Ok(slots.iter().map(|&i| tape.values()[i]).collect())
+    }
+
+    /// Vector-Jacobian product: seed every output at once and sweep backward.
+    ///
+    /// Returns the output values and `Σₖ cotangents[k] · ∇outputs[k]`, the
+    /// gradient of the cotangent-weighted sum of the outputs.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(AutodiffError)` if an operation receives incorrect arity,
+    /// an output index does not refer to a node, or `cotangents` does not have
+    /// one entry per output.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petite_ad::{MultiAD, multi_ops};
+    ///
+    /// // Outputs: x + y and x * y
+    /// let exprs = multi_ops![(inp, 0), (inp, 1), (add, 0, 1), (mul, 0, 1)];
+    /// let (values, grads) = MultiAD::vjp(&exprs, &[2, 3], &[2.0, 3.0], &[1.0, 1.0]).unwrap();
+    /// assert_eq!(values, vec![5.0, 6.0]);
+    /// assert_eq!(grads, vec![4.0, 3.0]); // (1 + y, 1 + x)
+    /// ```
+    #[must_use = "gradient computation is expensive; discarding the result is likely a bug"]
+    pub fn vjp(
+        exprs: &[(MultiAD, Vec<usize>)],
+        outputs: &[usize],
+        inputs: &[f64],
+        cotangents: &[f64],
+    ) -> Result<(Vec<f64>, Vec<f64>)> {
+        AutodiffError::check_dimension(outputs.len(), cotangents.len())?;
+        let (tape, slots) = record_with_outputs(exprs, inputs, outputs)?;
+
+        let mut seeds = vec![0.0; tape.values().len()];
+        for (&slot, cotangent) in slots.iter().zip(cotangents) {
+            seeds[slot] += cotangent;
+        }
+        tape.pull_back(&mut seeds);
+        seeds.truncate(inputs.len());
+
+        let values = slots.iter().map(|&i| tape.values()[i]).collect();
+        Ok((values, seeds))
+    }
+
+    /// Compute the Jacobian matrix of the selected outputs.
+    ///
+    /// Row `k` holds `∂outputs[k]/∂xⱼ` for every input `j`. Uses forward mode
+    /// (one sweep per input) when there are fewer inputs than outputs and
+    /// reverse mode (one sweep per output) otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(AutodiffError)` if an operation receives incorrect arity or
+    /// an output index does not refer to a node.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petite_ad::{MultiAD, multi_ops};
+    ///
+    /// // f(x, y) = (x + y, x * y, sin(x))
+    /// let exprs = multi_ops![(inp, 0), (inp, 1), (add, 0, 1), (mul, 0, 1), (sin, 0)];
+    /// let jac = MultiAD::jacobian(&exprs, &[2, 3, 4], &[2.0, 3.0]).unwrap();
+    /// assert_eq!(jac[0], vec![1.0, 1.0]);
+    /// assert_eq!(jac[1], vec![3.0, 2.0]);
+    /// ```
+    #[must_use = "jacobian computation is expensive; discarding the result is likely a bug"]
+    pub fn jacobian(
+        exprs: &[(MultiAD, Vec<usize>)],
+        outputs: &[usize],
+        inputs: &[f64],
+    ) -> Result<Vec<Vec<f64>>> {
+        let (tape, slots) = record_with_outputs(exprs, inputs, outputs)?;
+        let num_inputs = inputs.len();
+
+        if num_inputs < outputs.len() {
+            // Forward mode: each sweep fills one column
+            let mut jac = vec![vec![0.0; num_inputs]; outputs.len()];
+            let mut unit = vec![0.0; num_inputs];
+            let mut tangents = Vec::with_capacity(tape.values().len());
+            for j in 0..num_inputs {
+                unit[j] = 1.0;
+                tape.push_forward(&unit, &mut tangents);
+                unit[j] = 0.0;
+                for (row, &slot) in jac.iter_mut().zip(&slots) {
+                    row[j] = tangents[slot];
+                }
+            }
+            Ok(jac)
+        } else {
+            // Reverse mode: each sweep fills one row
+            let mut seeds = vec![0.0; tape.values().len()];
+            Ok(slots
+                .iter()
+                .map(|&slot| {
+                    seeds.fill(0.0);
+                    seeds[slot] = 1.0;
+                    tape.pull_back(&mut seeds);
+                    seeds[..num_inputs].to_vec()
+                })
+                .collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::multi::compiled::CompiledGraph;
+    use crate::multi_ops;
+    use crate::test_utils::approx_eq_eps as approx_eq;
+
+    /// f(x, y) = (x * y, sin(x) + y, exp(x - y))
+    fn three_outputs() -> Vec<(MultiAD, Vec<usize>)> {
+        Vec::from(multi_ops![
+            (inp, 0),
+            (inp, 1),
+            (mul, 0, 1), // 2
+            (sin, 0),    // 3
+            (add, 3, 1), // 4
+            (sub, 0, 1), // 5
+            (exp, 5),    // 6
+        ])
+    }
+
+    fn expected_jacobian(x: f64, y: f64) -> Vec<Vec<f64>> {
+        let e = (x - y).exp();
+        vec![vec![y, x], vec![x.cos(), 1.0], vec![e, -e]]
+    }
+
+    fn assert_matrix_eq(actual: &[Vec<f64>], expected: &[Vec<f64>]) {
+        assert_eq!(actual.len(), expected.len());
+        for (row_a, row_e) in actual.iter().zip(expected) {
+            assert_eq!(row_a.len(), row_e.len());
+            for (a, e) in row_a.iter().zip(row_e) {
+                assert!(
+                    approx_eq(*a, *e, 1e-10),
+                    "got {:?}, expected {:?}",
+                    actual,
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_jacobian_forward_mode() {
+        // 2 inputs < 3 outputs selects forward mode
+        let exprs = three_outputs();
+        let jac = MultiAD::jacobian(&exprs, &[2, 4, 6], &[0.7, 0.2]).unwrap();
+        assert_matrix_eq(&jac, &expected_jacobian(0.7, 0.2));
+    }
+
+    #[test]
+    fn test_jacobian_reverse_mode() {
+        // 2 inputs >= 2 outputs selects reverse mode
+        let exprs = three_outputs();
+        let jac = MultiAD::jacobian(&exprs, &[6, 2], &[0.7, 0.2]).unwrap();
+        let expected = expected_jacobian(0.7, 0.2);
+        assert_matrix_eq(&jac, &[expected[2].clone(), expected[0].clone()]);
+    }
+
+    #[test]
+    fn test_jacobian_single_output_matches_compute_grad() {
+        let exprs = three_outputs();
+        let inputs = [0.4, 1.6];
+        let (_value, grad_fn) = MultiAD::compute_grad(&exprs, &inputs).unwrap();
+        let jac = MultiAD::jacobian(&exprs, &[6], &inputs).unwrap();
+        assert_matrix_eq(&jac, &[grad_fn(1.0)]);
+    }
+
+    #[test]
+    fn test_modes_agree_on_infinite_partials() {
+        let cases = [
+            // sqrt(x) * 0 at x = 0: the infinite partial of sqrt is scaled by zero
+            (
+                multi_ops![(inp, 0), (sqrt, 0), (const, 0.0), (mul, 1, 2)],
+                vec![0.0],
+                vec![0.0],
+            ),
+            // ln(x) at x = 0 has an infinite partial but feeds nothing
+            (
+                multi_ops![(inp, 0), (inp, 1), (ln, 0), (mul, 0, 1)],
+                vec![0.0, 2.0],
+                vec![2.0, 0.0],
+            ),
+        ];
+        for (exprs, inputs, expected) in cases {
+            let output = exprs.len() - 1;
+            let reverse = MultiAD::jacobian(&exprs, &[output], &inputs).unwrap();
+            let forward =
+                MultiAD::jacobian(&exprs, &vec![output; inputs.len() + 1], &inputs).unwrap();
+            assert_matrix_eq(&reverse, std::slice::from_ref(&expected));
+            assert_matrix_eq(&forward, &vec![expected.clone(); inputs.len() + 1]);
+            assert_eq!(
+                MultiAD::compute_grad(&exprs, &inputs).unwrap().1(1.0),
+                expected
+            );
+            for (i, e) in expected.iter().enumerate() {
+                let mut tangent = vec![0.0; inputs.len()];
+                tangent[i] = 1.0;
+                let (_, jvp) = MultiAD::compute_jvp(&exprs, &inputs, &tangent).unwrap();
+                assert_eq!(jvp, *e);
+            }
+
+            let graph = CompiledGraph::new(&exprs, inputs.len()).unwrap();
+            let mut ws = graph.workspace();
+            let mut grad = vec![0.0; inputs.len()];
+            graph.value_and_grad(&inputs, &mut ws, &mut grad).unwrap();
+            assert_eq!(grad, expected);
+            let hessian = MultiAD::hessian(&exprs, &inputs).unwrap();
+            assert!(hessian.iter().flatten().all(|h| h.is_finite()));
+        }
+    }
+
+    #[test]
+    fn test_vjp_seeds_all_outputs() {
+        let exprs = three_outputs();
+        let (x, y) = (0.7, 0.2);
+        let cotangents = [2.0, -1.0, 0.5];
+        let (values, grads) = MultiAD::vjp(&exprs, &[2, 4, 6], &[x, y], &cotangents).unwrap();
+
+        assert_eq!(values.len(), 3);
+        assert!(approx_eq(values[0], x * y, 1e-10));
+        assert!(approx_eq(values[1], x.sin() + y, 1e-10));
+        assert!(approx_eq(values[2], (x - y).exp(), 1e-10));
+
+        let jac = expected_jacobian(x, y);
+        for (j, grad) in grads.iter().enumerate() {
+            let expected: f64 = jac.iter().zip(&cotangents).map(|(row, c)| row[j] * c).sum();
+            assert!(approx_eq(*grad, expected, 1e-10));
+        }
+    }
+
+    #[test]
+    fn test_outputs_can_be_inputs() {
+        let exprs = three_outputs();
+        let jac = MultiAD::jacobian(&exprs, &[1, 2], &[3.0, 4.0]).unwrap();
+        assert_matrix_eq(&jac, &[vec![0.0, 1.0], vec![4.0, 3.0]]);
+    }
+
+    #[test]
+    fn test_output_index_out_of_bounds() {
+        let exprs = three_outputs();
+        let result = MultiAD::compute_outputs(&exprs, &[2, 7], &[1.0, 2.0]);
+        assert_eq!(
+            result,
+            Err(AutodiffError::IndexOutOfBounds {
+                index: 7,
+                max_index: 6
+            })
+        );
+    }
+
+    #[test]
+    fn test_vjp_cotangent_length_mismatch() {
+        let exprs = three_outputs();
+        let result = MultiAD::vjp(&exprs, &[2, 4], &[1.0, 2.0], &[1.0]);
+        assert_eq!(
+            result,
+            Err(AutodiffError::DimensionMismatch {
+                expected: 2,
+                actual: 1
+            })
+        );
+    }
+}
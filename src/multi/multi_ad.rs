@@ -1,5 +1,10 @@
+use super::compiled::CompiledGraph;
+use super::reduce;
+use super::special;
+use super::subgradient::SubgradientPolicy;
+use super::tape::FlatTape;
 use super::types::*;
-use crate::error::{AutodiffError, Result};
+use crate::error::{Arity, AutodiffError, Result};
 
 /// Multi-variable automatic differentiation operations.
 ///
@@ -25,7 +30,7 @@ use crate::error::{AutodiffError, Result};
 /// println!("f(0.6, 1.4) = {}", value);
 /// println!("∇f = {:?}", gradients);
 /// ```
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy)]
 pub enum MultiAD {
     /// Input placeholder - references an input variable
     Inp,
@@ -89,13 +94,400 @@ pub enum MultiAD {
     ///
     /// # Notes
     /// - Delegates to `f64::abs()`
-    /// - Subgradient at x=0 is 0 (consistent with common practice)
+    /// - The derivative at x=0 follows the [`SubgradientPolicy`], 0 by default
     Abs,
+    /// Negation: -x
+    Neg,
+    /// Hyperbolic tangent: tanh(x)
+    ///
+    /// # Notes
+    /// - Delegates to `f64::tanh()`
+    /// - Returns values in the range `[-1.0, 1.0]`
+    Tanh,
+    /// Hyperbolic sine: sinh(x)
+    ///
+    /// # Notes
+    /// - Delegates to `f64::sinh()`
+    Sinh,
+    /// Hyperbolic cosine: cosh(x)
+    ///
+    /// # Notes
+    /// - Delegates to `f64::cosh()`
+    Cosh,
+    /// Inverse sine: asin(x)
+    ///
+    /// # Notes
+    /// - Delegates to `f64::asin()`
+    /// - Returns `NaN` outside `[-1.0, 1.0]`; the derivative is infinite at the ends
+    Asin,
+    /// Inverse cosine: acos(x)
+    ///
+    /// # Notes
+    /// - Delegates to `f64::acos()`
+    /// - Returns `NaN` outside `[-1.0, 1.0]`; the derivative is infinite at the ends
+    Acos,
+    /// Inverse tangent: atan(x)
+    ///
+    /// # Notes
+    /// - Delegates to `f64::atan()`
+    /// - Returns values in the range `[-π/2, π/2]`
+    Atan,
+    /// Two-argument inverse tangent: atan2(y, x), the angle of the point (x, y)
+    ///
+    /// # Notes
+    /// - Delegates to `f64::atan2()`, with `y` as the first argument
+    /// - Returns values in the range `[-π, π]`
+    Atan2,
+    /// ln(1 + x), accurate for small x
+    ///
+    /// # Notes
+    /// - Delegates to `f64::ln_1p()`
+    Log1p,
+    /// exp(x) - 1, accurate for small x
+    ///
+    /// # Notes
+    /// - Delegates to `f64::exp_m1()`
+    Expm1,
+    /// Base-2 logarithm: log2(x)
+    ///
+    /// # Notes
+    /// - Delegates to `f64::log2()`
+    Log2,
+    /// Base-10 logarithm: log10(x)
+    ///
+    /// # Notes
+    /// - Delegates to `f64::log10()`
+    Log10,
+    /// Logistic sigmoid: 1 / (1 + exp(-x))
+    ///
+    /// # Notes
+    /// - Evaluated without overflow for any finite x
+    /// - Returns values in the range `[0.0, 1.0]`
+    Sigmoid,
+    /// Softplus: ln(1 + exp(x))
+    ///
+    /// # Notes
+    /// - Evaluated as `max(x, 0) + ln_1p(exp(-|x|))`, which does not overflow
+    ///   for large x and keeps precision for very negative x
+    Softplus,
+    /// Euclidean norm of two values: sqrt(a² + b²)
+    ///
+    /// # Notes
+    /// - Delegates to `f64::hypot()`, which avoids intermediate overflow
+    /// - The gradient is undefined (`NaN`) at `(0, 0)`
+    Hypot,
+    /// Smaller of two values: min(a, b)
+    ///
+    /// # Notes
+    /// - Delegates to `f64::min()`, so a `NaN` argument is ignored
+    /// - The derivatives at a tie follow the [`SubgradientPolicy`]
+    Min,
+    /// Larger of two values: max(a, b)
+    ///
+    /// # Notes
+    /// - Delegates to `f64::max()`, so a `NaN` argument is ignored
+    /// - The derivatives at a tie follow the [`SubgradientPolicy`]
+    Max,
+    /// Clamp to a range: clamp(x, lo, hi), takes three arguments
+    ///
+    /// # Notes
+    /// - Computed as `x.max(lo).min(hi)`, so it returns `hi` when `lo > hi`
+    ///   instead of panicking like `f64::clamp()`
+    /// - The derivatives at `x = lo` and `x = hi` follow the [`SubgradientPolicy`]
+    Clamp,
+    /// Rectified linear unit: max(x, 0)
+    ///
+    /// # Notes
+    /// - Propagates `NaN`
+    /// - The derivative at x=0 follows the [`SubgradientPolicy`]
+    Relu,
+    /// Round down: floor(x)
+    ///
+    /// # Notes
+    /// - Delegates to `f64::floor()`
+    /// - The derivative is 0 except under [`SubgradientPolicy::StraightThrough`]
+    Floor,
+    /// Round up: ceil(x)
+    ///
+    /// # Notes
+    /// - Delegates to `f64::ceil()`
+    /// - The derivative is 0 except under [`SubgradientPolicy::StraightThrough`]
+    Ceil,
+    /// Round to nearest, halves away from zero: round(x)
+    ///
+    /// # Notes
+    /// - Delegates to `f64::round()`
+    /// - The derivative is 0 except under [`SubgradientPolicy::StraightThrough`]
+    Round,
+    /// Sign: -1, 0 or 1
+    ///
+    /// # Notes
+    /// - Unlike `f64::signum()`, returns 0 for `±0.0`; propagates `NaN`
+    /// - The derivative is 0 except under [`SubgradientPolicy::StraightThrough`]
+    Sign,
+    /// Floating-point remainder: fmod(a, b), with the sign of `a`
+    ///
+    /// # Notes
+    /// - Same as `a % b` on `f64`
+    /// - ∂/∂a is 1 and ∂/∂b is `-trunc(a / b)`; where `a / b` is a nonzero
+    ///   integer the value jumps and ∂/∂b follows the [`SubgradientPolicy`]
+    Fmod,
+    /// Error function: erf(x) = 2/√π ∫₀ˣ exp(-t²) dt
+    ///
+    /// # Notes
+    /// - Accurate to a few ulps for all x; returns values in `[-1.0, 1.0]`
+    Erf,
+    /// Complementary error function: erfc(x) = 1 - erf(x)
+    ///
+    /// # Notes
+    /// - Keeps its relative precision in the right tail, where `1 - erf(x)`
+    ///   would round to zero
+    Erfc,
+    /// Log of the absolute value of the gamma function: ln|Γ(x)|
+    ///
+    /// # Notes
+    /// - Defined for negative x through the reflection formula
+    /// - Returns `+∞` at the poles x = 0, -1, -2, …
+    /// - The derivative is [`MultiAD::Digamma`]
+    Lgamma,
+    /// Digamma function: ψ(x) = d/dx ln Γ(x)
+    ///
+    /// # Notes
+    /// - Returns `NaN` at the poles x = 0, -1, -2, …
+    /// - The derivative is [`MultiAD::Trigamma`]
+    Digamma,
+    /// Trigamma function: ψ₁(x) = d/dx ψ(x)
+    ///
+    /// # Notes
+    /// - Returns `NaN` at the poles x = 0, -1, -2, …
+    /// - The derivative is the tetragamma function, which is not an op, so
+    ///   [`MultiAD::derivative_graph`] cannot differentiate through it
+    Trigamma,
+    /// Standard normal cumulative distribution function: Φ(x)
+    ///
+    /// # Notes
+    /// - Computed as `erfc(-x/√2) / 2`, accurate far into the left tail
+    NormalCdf,
+    /// Log of the standard normal density: -x²/2 - ln(2π)/2
+    NormalLogpdf,
+    /// Principal branch of the Lambert W function: the w ≥ -1 with w eʷ = x
+    ///
+    /// # Notes
+    /// - Returns `NaN` below the branch point x = -1/e, where W = -1
+    /// - The derivative 1/(x + e^W) is infinite at the branch point
+    LambertW,
+    /// Sum of any number of values: x₁ + x₂ + … + xₙ; takes at least one argument
+    ///
+    /// # Notes
+    /// - One node replaces a chain of n - 1 `Add` nodes
+    Sum,
+    /// Product of any number of values: x₁ x₂ … xₙ; takes at least one argument
+    ///
+    /// # Notes
+    /// - Each partial is the product of the other arguments, computed without
+    ///   division, so it is exact when some arguments are zero
+    Prod,
+    /// Arithmetic mean: (x₁ + … + xₙ) / n; takes at least one argument
+    Mean,
+    /// Log-sum-exp: ln(exp(x₁) + … + exp(xₙ)); takes at least one argument
+    ///
+    /// # Notes
+    /// - Shifted by the largest argument, so it neither overflows nor
+    ///   underflows; the partials are softmax(x)
+    LogSumExp,
+    /// Softmax-weighted sum: Σ softmax(x)ᵢ xᵢ; takes at least one argument
+    ///
+    /// # Notes
+    /// - A smooth maximum that lies between the mean and the largest argument
+    /// - Shifted by the largest argument, like [`MultiAD::LogSumExp`]
+    SoftmaxSum,
+    /// Constant value; takes no arguments
+    ///
+    /// # Notes
+    /// - Compared and hashed by bit pattern, so `Const(f64::NAN)` equals itself
+    Const(f64),
+    /// Trainable parameter `k`; takes no arguments
+    ///
+    /// # Notes
+    /// - Reads `params[k]` from the parameter vector passed to
+    ///   [`MultiAD::compute_grad_with_params`]
+    /// - Evaluating a graph containing parameters without a parameter vector
+    ///   returns `AutodiffError::MissingParams`
+    Param(usize),
+}
+
+impl PartialEq for MultiAD {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (MultiAD::Const(a), MultiAD::Const(b)) => a.to_bits() == b.to_bits(),
+            (MultiAD::Param(a), MultiAD::Param(b)) => a == b,
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+}
+
+impl Eq for MultiAD {}
+
+impl std::hash::Hash for MultiAD {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            MultiAD::Const(c) => c.to_bits().hash(state),
+            MultiAD::Param(k) => k.hash(state),
+            _ => {}
+        }
+    }
+}
+
+/// Writes the op name used by `multi_ops!`, e.g. `add` or `const`.
+///
+/// The value of `Const` and the index of `Param` are not part of the name.
+impl std::fmt::Display for MultiAD {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            MultiAD::Inp => "inp",
+            MultiAD::Add => "add",
+            MultiAD::Sub => "sub",
+            MultiAD::Mul => "mul",
+            MultiAD::Div => "div",
+            MultiAD::Pow => "pow",
+            MultiAD::Sin => "sin",
+            MultiAD::Cos => "cos",
+            MultiAD::Tan => "tan",
+            MultiAD::Exp => "exp",
+            MultiAD::Ln => "ln",
+            MultiAD::Sqrt => "sqrt",
+            MultiAD::Abs => "abs",
+            MultiAD::Neg => "neg",
+            MultiAD::Tanh => "tanh",
+            MultiAD::Sinh => "sinh",
+            MultiAD::Cosh => "cosh",
+            MultiAD::Asin => "asin",
+            MultiAD::Acos => "acos",
+            MultiAD::Atan => "atan",
+            MultiAD::Atan2 => "atan2",
+            MultiAD::Log1p => "log1p",
+            MultiAD::Expm1 => "expm1",
+            MultiAD::Log2 => "log2",
+            MultiAD::Log10 => "log10",
+            MultiAD::Sigmoid => "sigmoid",
+            MultiAD::Softplus => "softplus",
+            MultiAD::Hypot => "hypot",
+            MultiAD::Min => "min",
+            MultiAD::Max => "max",
+            MultiAD::Clamp => "clamp",
+            MultiAD::Relu => "relu",
+            MultiAD::Floor => "floor",
+            MultiAD::Ceil => "ceil",
+            MultiAD::Round => "round",
+            MultiAD::Sign => "sign",
+            MultiAD::Fmod => "fmod",
+            MultiAD::Erf => "erf",
+            MultiAD::Erfc => "erfc",
+            MultiAD::Lgamma => "lgamma",
+            MultiAD::Digamma => "digamma",
+            MultiAD::Trigamma => "trigamma",
+            MultiAD::NormalCdf => "normal_cdf",
+            MultiAD::NormalLogpdf => "normal_logpdf",
+            MultiAD::LambertW => "lambert_w",
+            MultiAD::Sum => "sum",
+            MultiAD::Prod => "prod",
+            MultiAD::Mean => "mean",
+            MultiAD::LogSumExp => "logsumexp",
+            MultiAD::SoftmaxSum => "softmax_sum",
+            MultiAD::Const(_) => "const",
+            MultiAD::Param(_) => "param",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Parses the op names written by `Display`.
+///
+/// `const` and `param` need a value, so they cannot be parsed from their
+/// name alone; the serialisation formats read that value as the first
+/// argument, as in `multi_ops!`.
+impl std::str::FromStr for MultiAD {
+    type Err = AutodiffError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "inp" => MultiAD::Inp,
+            "add" => MultiAD::Add,
+            "sub" => MultiAD::Sub,
+            "mul" => MultiAD::Mul,
+            "div" => MultiAD::Div,
+            "pow" => MultiAD::Pow,
+            "sin" => MultiAD::Sin,
+            "cos" => MultiAD::Cos,
+            "tan" => MultiAD::Tan,
+            "exp" => MultiAD::Exp,
+            "ln" => MultiAD::Ln,
+            "sqrt" => MultiAD::Sqrt,
+            "abs" => MultiAD::Abs,
+            "neg" => MultiAD::Neg,
+            "tanh" => MultiAD::Tanh,
+            "sinh" => MultiAD::Sinh,
+            "cosh" => MultiAD::Cosh,
+            "asin" => MultiAD::Asin,
+            "acos" => MultiAD::Acos,
+            "atan" => MultiAD::Atan,
+            "atan2" => MultiAD::Atan2,
+            "log1p" => MultiAD::Log1p,
+            "expm1" => MultiAD::Expm1,
+            "log2" => MultiAD::Log2,
+            "log10" => MultiAD::Log10,
+            "sigmoid" => MultiAD::Sigmoid,
+            "softplus" => MultiAD::Softplus,
+            "hypot" => MultiAD::Hypot,
+            "min" => MultiAD::Min,
+            "max" => MultiAD::Max,
+            "clamp" => MultiAD::Clamp,
+            "relu" => MultiAD::Relu,
+            "floor" => MultiAD::Floor,
+            "ceil" => MultiAD::Ceil,
+            "round" => MultiAD::Round,
+            "sign" => MultiAD::Sign,
+            "fmod" => MultiAD::Fmod,
+            "erf" => MultiAD::Erf,
+            "erfc" => MultiAD::Erfc,
+            "lgamma" => MultiAD::Lgamma,
+            "digamma" => MultiAD::Digamma,
+            "trigamma" => MultiAD::Trigamma,
+            "normal_cdf" => MultiAD::NormalCdf,
+            "normal_logpdf" => MultiAD::NormalLogpdf,
+            "lambert_w" => MultiAD::LambertW,
+            "sum" => MultiAD::Sum,
+            "prod" => MultiAD::Prod,
+            "mean" => MultiAD::Mean,
+            "logsumexp" => MultiAD::LogSumExp,
+            "softmax_sum" => MultiAD::SoftmaxSum,
+            "const" | "param" => {
+                return Err(AutodiffError::parse(format!("'{s}' needs a value"), 1, 1))
+            }
+            _ => return Err(AutodiffError::parse(format!("unknown op '{s}'"), 1, 1)),
+        })
+    }
+}
+
+/// Logistic sigmoid, using whichever form cannot overflow `exp`.
+fn sigmoid(x: f64) -> f64 {
+    if x >= 0.0 {
+        1.0 / (1.0 + (-x).exp())
+    } else {
+        let e = x.exp();
+        e / (1.0 + e)
+    }
+}
+
+/// ln(1 + exp(x)), split so `exp` only sees non-positive arguments.
+fn softplus(x: f64) -> f64 {
+    x.max(0.0) + (-x.abs()).exp().ln_1p()
 }
 
 impl MultiAD {
     /// Get the name of this operation (for error messages and arity checking)
-    fn op_name(&self) -> &'static str {
+    pub(super) fn op_name(&self) -> &'static str {
         match self {
             MultiAD::Inp => "Inp",
             MultiAD::Add => "Add",
@@ -110,11 +502,50 @@ impl MultiAD {
             MultiAD::Ln => "Ln",
             MultiAD::Sqrt => "Sqrt",
             MultiAD::Abs => "Abs",
+            MultiAD::Neg => "Neg",
+            MultiAD::Tanh => "Tanh",
+            MultiAD::Sinh => "Sinh",
+            MultiAD::Cosh => "Cosh",
+            MultiAD::Asin => "Asin",
+            MultiAD::Acos => "Acos",
+            MultiAD::Atan => "Atan",
+            MultiAD::Atan2 => "Atan2",
+            MultiAD::Log1p => "Log1p",
+            MultiAD::Expm1 => "Expm1",
+            MultiAD::Log2 => "Log2",
+            MultiAD::Log10 => "Log10",
+            MultiAD::Sigmoid => "Sigmoid",
+            MultiAD::Softplus => "Softplus",
+            MultiAD::Hypot => "Hypot",
+            MultiAD::Min => "Min",
+            MultiAD::Max => "Max",
+            MultiAD::Clamp => "Clamp",
+            MultiAD::Relu => "Relu",
+            MultiAD::Floor => "Floor",
+            MultiAD::Ceil => "Ceil",
+            MultiAD::Round => "Round",
+            MultiAD::Sign => "Sign",
+            MultiAD::Fmod => "Fmod",
+            MultiAD::Erf => "Erf",
+            MultiAD::Erfc => "Erfc",
+            MultiAD::Lgamma => "Lgamma",
+            MultiAD::Digamma => "Digamma",
+            MultiAD::Trigamma => "Trigamma",
+            MultiAD::NormalCdf => "NormalCdf",
+            MultiAD::NormalLogpdf => "NormalLogpdf",
+            MultiAD::LambertW => "LambertW",
+            MultiAD::Sum => "Sum",
+            MultiAD::Prod => "Prod",
+            MultiAD::Mean => "Mean",
+            MultiAD::LogSumExp => "LogSumExp",
+            MultiAD::SoftmaxSum => "SoftmaxSum",
+            MultiAD::Const(_) => "Const",
+            MultiAD::Param(_) => "Param",
         }
     }
 
     /// Get the expected arity for this operation
-    fn expected_arity(&self) -> usize {
+    pub(crate) fn expected_arity(&self) -> Arity {
         match self {
             MultiAD::Inp
             | MultiAD::Sin
@@ -123,156 +554,410 @@ impl MultiAD {
             | MultiAD::Exp
             | MultiAD::Ln
             | MultiAD::Sqrt
-            | MultiAD::Abs => 1,
-            MultiAD::Add | MultiAD::Sub | MultiAD::Mul | MultiAD::Div | MultiAD::Pow => 2,
+            | MultiAD::Abs
+            | MultiAD::Neg
+            | MultiAD::Tanh
+            | MultiAD::Sinh
+            | MultiAD::Cosh
+            | MultiAD::Asin
+            | MultiAD::Acos
+            | MultiAD::Atan
+            | MultiAD::Log1p
+            | MultiAD::Expm1
+            | MultiAD::Log2
+            | MultiAD::Log10
+            | MultiAD::Sigmoid
+            | MultiAD::Softplus
+            | MultiAD::Relu
+            | MultiAD::Floor
+            | MultiAD::Ceil
+            | MultiAD::Round
+            | MultiAD::Sign
+            | MultiAD::Erf
+            | MultiAD::Erfc
+            | MultiAD::Lgamma
+            | MultiAD::Digamma
+            | MultiAD::Trigamma
+            | MultiAD::NormalCdf
+            | MultiAD::NormalLogpdf
+            | MultiAD::LambertW => Arity::Exact(1),
+            MultiAD::Add
+            | MultiAD::Sub
+            | MultiAD::Mul
+            | MultiAD::Div
+            | MultiAD::Pow
+            | MultiAD::Atan2
+            | MultiAD::Hypot
+            | MultiAD::Min
+            | MultiAD::Max
+            | MultiAD::Fmod => Arity::Exact(2),
+            MultiAD::Clamp => Arity::Exact(3),
+            MultiAD::Sum
+            | MultiAD::Prod
+            | MultiAD::Mean
+            | MultiAD::LogSumExp
+            | MultiAD::SoftmaxSum => Arity::AtLeast(1),
+            MultiAD::Const(_) | MultiAD::Param(_) => Arity::Exact(0),
         }
     }
+
     /// Forward pass: compute the output of this operation given inputs
-    fn forward(&self, args: &[f64]) -> Result<f64> {
-        Ok(match self {
-            MultiAD::Inp => {
-                AutodiffError::check_arity("Inp", 1, args.len())?;
-                args[0]
-            }
-            MultiAD::Sin => {
-                AutodiffError::check_arity("Sin", 1, args.len())?;
-                args[0].sin()
-            }
-            MultiAD::Cos => {
-                AutodiffError::check_arity("Cos", 1, args.len())?;
-                args[0].cos()
+    pub(super) fn forward(&self, args: &[f64]) -> Result<f64> {
+        AutodiffError::check_arity(self.op_name(), self.expected_arity(), args.len())?;
+        // Parameter values are supplied through `apply`
+        if let MultiAD::Param(k) = self {
+            return Err(AutodiffError::MissingParams {
+                expected: k + 1,
+                actual: 0,
+            });
+        }
+        Ok(self.apply(args, &[]))
+    }
+
+    /// Unchecked forward pass, with `Param(k)` reading `params[k]`.
+    ///
+    /// Arity and parameter count are assumed to have been checked already, as
+    /// [`CompiledGraph`](super::compiled::CompiledGraph) does once up front.
+    pub(super) fn apply(&self, args: &[f64], params: &[f64]) -> f64 {
+        match self {
+            MultiAD::Inp => args[0],
+            MultiAD::Sin => args[0].sin(),
+            MultiAD::Cos => args[0].cos(),
+            MultiAD::Tan => args[0].tan(),
+            MultiAD::Exp => args[0].exp(),
+            MultiAD::Ln => args[0].ln(),
+            MultiAD::Sqrt => args[0].sqrt(),
+            MultiAD::Abs => args[0].abs(),
+            MultiAD::Neg => -args[0],
+            MultiAD::Tanh => args[0].tanh(),
+            MultiAD::Sinh => args[0].sinh(),
+            MultiAD::Cosh => args[0].cosh(),
+            MultiAD::Asin => args[0].asin(),
+            MultiAD::Acos => args[0].acos(),
+            MultiAD::Atan => args[0].atan(),
+            MultiAD::Log1p => args[0].ln_1p(),
+            MultiAD::Expm1 => args[0].exp_m1(),
+            MultiAD::Log2 => args[0].log2(),
+            MultiAD::Log10 => args[0].log10(),
+            MultiAD::Sigmoid => sigmoid(args[0]),
+            MultiAD::Softplus => softplus(args[0]),
+            MultiAD::Relu => {
+                if args[0] < 0.0 {
+                    0.0
+                } else {
+                    args[0]
+                }
             }
-            MultiAD::Tan => {
-                AutodiffError::check_arity("Tan", 1, args.len())?;
-                args[0].tan()
+            MultiAD::Floor => args[0].floor(),
+            MultiAD::Ceil => args[0].ceil(),
+            MultiAD::Round => args[0].round(),
+            MultiAD::Sign => {
+                if args[0] == 0.0 {
+                    0.0
+                } else {
+                    args[0].signum()
+                }
             }
-            MultiAD::Exp => {
-                AutodiffError::check_arity("Exp", 1, args.len())?;
-                args[0].exp()
+            MultiAD::Erf => special::erf(args[0]),
+            MultiAD::Erfc => special::erfc(args[0]),
+            MultiAD::Lgamma => special::lgamma(args[0]),
+            MultiAD::Digamma => special::digamma(args[0]),
+            MultiAD::Trigamma => special::trigamma(args[0]),
+            MultiAD::NormalCdf => special::normal_cdf(args[0]),
+            MultiAD::NormalLogpdf => special::normal_logpdf(args[0]),
+            MultiAD::LambertW => special::lambert_w(args[0]),
+            MultiAD::Const(c) => *c,
+            MultiAD::Param(k) => params[*k],
+            MultiAD::Add => args[0] + args[1],
+            MultiAD::Sub => args[0] - args[1],
+            MultiAD::Mul => args[0] * args[1],
+            MultiAD::Div => args[0] / args[1],
+            MultiAD::Pow => args[0].powf(args[1]),
+            MultiAD::Atan2 => args[0].atan2(args[1]),
+            MultiAD::Hypot => args[0].hypot(args[1]),
+            MultiAD::Min => args[0].min(args[1]),
+            MultiAD::Max => args[0].max(args[1]),
+            MultiAD::Fmod => args[0] % args[1],
+            MultiAD::Clamp => args[0].max(args[1]).min(args[2]),
+            MultiAD::Sum => args.iter().sum(),
+            MultiAD::Prod => args.iter().product(),
+            MultiAD::Mean => args.iter().sum::<f64>() / args.len() as f64,
+            MultiAD::LogSumExp => reduce::log_sum_exp(args),
+            MultiAD::SoftmaxSum => reduce::softmax_sum(args),
+        }
+    }
+
+    /// Local partial derivatives ∂output/∂args, written into `out`.
+    ///
+    /// `out` must have one slot per argument; arity is assumed to have been
+    /// checked already, as for [`MultiAD::apply`]. `policy` picks the
+    /// derivatives of non-smooth ops where they are not differentiable.
+    pub(super) fn partials(&self, args: &[f64], policy: SubgradientPolicy, out: &mut [f64]) {
+        match self {
+            MultiAD::Inp => out[0] = 1.0,
+            MultiAD::Sin => out[0] = args[0].cos(),
+            MultiAD::Cos => out[0] = -args[0].sin(),
+            MultiAD::Tan => out[0] = 1.0 / args[0].cos().powi(2),
+            MultiAD::Exp => out[0] = args[0].exp(),
+            MultiAD::Ln => out[0] = 1.0 / args[0],
+            // d(sqrt(x))/dx = 1/(2*sqrt(x))
+            MultiAD::Sqrt => out[0] = 1.0 / (2.0 * args[0].sqrt()),
+            // d(|x|)/dx = sign(x), with one-sided derivatives -1 and 1 at zero
+            MultiAD::Abs => {
+                out[0] = match args[0] {
+                    x if x > 0.0 => 1.0,
+                    x if x < 0.0 => -1.0,
+                    _ => policy.kink(-1.0, 1.0, 1.0),
+                }
             }
-            MultiAD::Ln => {
-                AutodiffError::check_arity("Ln", 1, args.len())?;
-                args[0].ln()
+            MultiAD::Relu => {
+                out[0] = match args[0] {
+                    x if x > 0.0 => 1.0,
+                    x if x < 0.0 => 0.0,
+                    _ => policy.kink(0.0, 1.0, 1.0),
+                }
             }
-            MultiAD::Sqrt => {
-                AutodiffError::check_arity("Sqrt", 1, args.len())?;
-                args[0].sqrt()
+            MultiAD::Floor | MultiAD::Ceil | MultiAD::Round | MultiAD::Sign => {
+                out[0] = policy.step()
             }
-            MultiAD::Abs => {
-                AutodiffError::check_arity("Abs", 1, args.len())?;
-                args[0].abs()
+            MultiAD::Neg => out[0] = -1.0,
+            MultiAD::Tanh => out[0] = 1.0 - args[0].tanh().powi(2),
+            MultiAD::Sinh => out[0] = args[0].cosh(),
+            MultiAD::Cosh => out[0] = args[0].sinh(),
+            // d(asin(x))/dx = 1/sqrt(1 - x²)
+            MultiAD::Asin => out[0] = 1.0 / (1.0 - args[0].powi(2)).sqrt(),
+            MultiAD::Acos => out[0] = -1.0 / (1.0 - args[0].powi(2)).sqrt(),
+            MultiAD::Atan => out[0] = 1.0 / (1.0 + args[0].powi(2)),
+            MultiAD::Log1p => out[0] = 1.0 / (1.0 + args[0]),
+            MultiAD::Expm1 => out[0] = args[0].exp(),
+            MultiAD::Log2 => out[0] = 1.0 / (args[0] * std::f64::consts::LN_2),
+            MultiAD::Log10 => out[0] = 1.0 / (args[0] * std::f64::consts::LN_10),
+            // d(σ(x))/dx = σ(x) (1 - σ(x)) = σ(x) σ(-x), which does not
+            // round to zero for large x
+            MultiAD::Sigmoid => out[0] = sigmoid(args[0]) * sigmoid(-args[0]),
+            // d(softplus(x))/dx = σ(x)
+            MultiAD::Softplus => out[0] = sigmoid(args[0]),
+            // d(erf(x))/dx = 2/√π exp(-x²)
+            MultiAD::Erf => out[0] = special::erf_derivative(args[0]),
+            MultiAD::Erfc => out[0] = -special::erf_derivative(args[0]),
+            // d(ln Γ(x))/dx = ψ(x)
+            MultiAD::Lgamma => out[0] = special::digamma(args[0]),
+            MultiAD::Digamma => out[0] = special::trigamma(args[0]),
+            MultiAD::Trigamma => out[0] = special::tetragamma(args[0]),
+            MultiAD::NormalCdf => out[0] = special::normal_pdf(args[0]),
+            MultiAD::NormalLogpdf => out[0] = -args[0],
+            // d(W(x))/dx = 1 / (x + e^W(x))
+            MultiAD::LambertW => {
+                let w = special::lambert_w(args[0]);
+                out[0] = special::lambert_w_derivative(args[0], w);
             }
+            MultiAD::Const(_) | MultiAD::Param(_) => {}
             MultiAD::Add => {
-                AutodiffError::check_arity("Add", 2, args.len())?;
-                args[0] + args[1]
+                out[0] = 1.0;
+                out[1] = 1.0;
             }
             MultiAD::Sub => {
-                AutodiffError::check_arity("Sub", 2, args.len())?;
-                args[0] - args[1]
+                out[0] = 1.0;
+                out[1] = -1.0;
             }
             MultiAD::Mul => {
-                AutodiffError::check_arity("Mul", 2, args.len())?;
-                args[0] * args[1]
+                out[0] = args[1];
+                out[1] = args[0];
             }
             MultiAD::Div => {
-                AutodiffError::check_arity("Div", 2, args.len())?;
-                args[0] / args[1]
+                out[0] = 1.0 / args[1];
+                out[1] = -args[0] / args[1].powi(2);
             }
             MultiAD::Pow => {
-                AutodiffError::check_arity("Pow", 2, args.len())?;
-                args[0].powf(args[1])
+                let (base, exp) = (args[0], args[1]);
+                // d(a^b)/da = b * a^(b-1)
+                out[0] = exp * base.powf(exp - 1.0);
+                // d(a^b)/db = a^b * ln(a)
+                out[1] = base.powf(exp) * base.ln();
             }
-        })
-    }
-
-    /// Backward pass: compute local gradients ∂output/∂inputs
-    /// Returns a boxed closure that computes gradients given a cotangent value
-    fn backward_generic<W>(&self, args: &[f64]) -> Result<W>
-    where
-        W: From<Box<DynGradFn>>,
-    {
-        AutodiffError::check_arity(self.op_name(), self.expected_arity(), args.len())?;
-
-        let backward_fn: Box<dyn Fn(f64) -> Vec<f64>> = match self {
-            MultiAD::Inp => Box::new(|zcotangent: f64| vec![zcotangent]),
-            MultiAD::Sin => {
-                let arg_val = args[0];
-                Box::new(move |z_cotangent: f64| {
-                    let x_cotangent = z_cotangent * arg_val.cos();
-                    vec![x_cotangent]
-                })
+            // d(atan2(y, x))/dy = x/(x² + y²), d/dx = -y/(x² + y²)
+            MultiAD::Atan2 => {
+                let (y, x) = (args[0], args[1]);
+                let r2 = x * x + y * y;
+                out[0] = x / r2;
+                out[1] = -y / r2;
+            }
+            // d(hypot(a, b))/da = a / hypot(a, b)
+            MultiAD::Hypot => {
+                let h = args[0].hypot(args[1]);
+                out[0] = args[0] / h;
+                out[1] = args[1] / h;
             }
-            MultiAD::Cos => {
-                let arg_val = args[0];
-                Box::new(move |z_cotangent: f64| {
-                    let x_cotangent = z_cotangent * -arg_val.sin();
-                    vec![x_cotangent]
-                })
+            // At a tie, raising an argument raises the output and lowering it does not
+            MultiAD::Max => {
+                let (a, b) = (args[0], args[1]);
+                (out[0], out[1]) = if a > b {
+                    (1.0, 0.0)
+                } else if a < b {
+                    (0.0, 1.0)
+                } else {
+                    (policy.kink(0.0, 1.0, 1.0), policy.kink(0.0, 1.0, 0.0))
+                };
             }
-            MultiAD::Tan => {
-                let arg_val = args[0];
-                Box::new(move |z_cotangent: f64| {
-                    let x_cotangent = z_cotangent * (1.0 / arg_val.cos().powi(2));
-                    vec![x_cotangent]
-                })
+            MultiAD::Min => {
+                let (a, b) = (args[0], args[1]);
+                (out[0], out[1]) = if a < b {
+                    (1.0, 0.0)
+                } else if a > b {
+                    (0.0, 1.0)
+                } else {
+                    (policy.kink(1.0, 0.0, 1.0), policy.kink(1.0, 0.0, 0.0))
+                };
             }
-            MultiAD::Exp => {
-                let exp_val = args[0].exp();
-                Box::new(move |z_cotangent: f64| {
-                    let x_cotangent = z_cotangent * exp_val;
-                    vec![x_cotangent]
-                })
+            MultiAD::Clamp => {
+                let (x, lo, hi) = (args[0], args[1], args[2]);
+                (out[0], out[1], out[2]) = if hi < lo || x > hi {
+                    (0.0, 0.0, 1.0)
+                } else if x < lo {
+                    (0.0, 1.0, 0.0)
+                } else if x > lo && x < hi {
+                    (1.0, 0.0, 0.0)
+                } else if x == lo {
+                    (policy.kink(0.0, 1.0, 1.0), policy.kink(0.0, 1.0, 0.0), 0.0)
+                } else {
+                    (policy.kink(1.0, 0.0, 1.0), 0.0, policy.kink(1.0, 0.0, 0.0))
+                };
             }
-            MultiAD::Ln => {
-                let arg_val = args[0];
-                Box::new(move |z_cotangent: f64| {
-                    let x_cotangent = z_cotangent * (1.0 / arg_val);
-                    vec![x_cotangent]
-                })
+            // fmod(a, b) = a - b trunc(a / b)
+            MultiAD::Fmod => {
+                let (a, b) = (args[0], args[1]);
+                // Read trunc(a / b) off the remainder: the rounded quotient
+                // can land on an integer when the result is not at a jump
+                let r = a % b;
+                let q = ((a - r) / b).round();
+                out[0] = 1.0;
+                out[1] = if r != 0.0 || q == 0.0 || !q.is_finite() {
+                    -q
+                } else {
+                    // At a jump trunc(a / b) differs on each side; as b grows,
+                    // a / b moves in the direction of `step`
+                    let step = -(q / b).signum() * 0.5;
+                    policy.kink(-(q - step).trunc(), -(q + step).trunc(), 0.0)
+                };
+            }
+            MultiAD::Sum => out.fill(1.0),
+            MultiAD::Mean => out.fill(1.0 / args.len() as f64),
+            MultiAD::Prod => reduce::product_partials(args, out),
+            // d(logsumexp(x))/dxᵢ = softmax(x)ᵢ
+            MultiAD::LogSumExp => reduce::softmax(args, out),
+            MultiAD::SoftmaxSum => reduce::softmax_sum_partials(args, out),
+        }
+    }
+
+    /// Local second partial derivatives ∂²output/∂args_i∂args_j, written into
+    /// `out` as a row-major `n × n` matrix where `n` is the number of arguments.
+    pub(super) fn second_partials(&self, args: &[f64], out: &mut [f64]) {
+        out.fill(0.0);
+        match self {
+            MultiAD::Inp
+            | MultiAD::Add
+            | MultiAD::Sub
+            | MultiAD::Abs
+            | MultiAD::Neg
+            | MultiAD::Min
+            | MultiAD::Max
+            | MultiAD::Clamp
+            | MultiAD::Relu
+            | MultiAD::Floor
+            | MultiAD::Ceil
+            | MultiAD::Round
+            | MultiAD::Sign
+            | MultiAD::Fmod
+            | MultiAD::Sum
+            | MultiAD::Mean
+            | MultiAD::Const(_)
+            | MultiAD::Param(_) => {}
+            MultiAD::Prod => reduce::product_second_partials(args, out),
+            MultiAD::LogSumExp => reduce::log_sum_exp_second_partials(args, out),
+            MultiAD::SoftmaxSum => reduce::softmax_sum_second_partials(args, out),
+            MultiAD::Sin => out[0] = -args[0].sin(),
+            MultiAD::Cos => out[0] = -args[0].cos(),
+            // d²(tan(x))/dx² = 2 tan(x) / cos²(x)
+            MultiAD::Tan => out[0] = 2.0 * args[0].tan() / args[0].cos().powi(2),
+            MultiAD::Exp => out[0] = args[0].exp(),
+            MultiAD::Ln => out[0] = -1.0 / args[0].powi(2),
+            // d²(sqrt(x))/dx² = -1/(4 x^(3/2))
+            MultiAD::Sqrt => out[0] = -1.0 / (4.0 * args[0].powf(1.5)),
+            // d²(tanh(x))/dx² = -2 tanh(x) (1 - tanh²(x))
+            MultiAD::Tanh => {
+                let t = args[0].tanh();
+                out[0] = -2.0 * t * (1.0 - t * t);
+            }
+            MultiAD::Sinh => out[0] = args[0].sinh(),
+            MultiAD::Cosh => out[0] = args[0].cosh(),
+            // d²(asin(x))/dx² = x / (1 - x²)^(3/2)
+            MultiAD::Asin => out[0] = args[0] / (1.0 - args[0].powi(2)).powf(1.5),
+            MultiAD::Acos => out[0] = -args[0] / (1.0 - args[0].powi(2)).powf(1.5),
+            // d²(atan(x))/dx² = -2x / (1 + x²)²
+            MultiAD::Atan => out[0] = -2.0 * args[0] / (1.0 + args[0].powi(2)).powi(2),
+            MultiAD::Log1p => out[0] = -1.0 / (1.0 + args[0]).powi(2),
+            MultiAD::Expm1 => out[0] = args[0].exp(),
+            MultiAD::Log2 => out[0] = -1.0 / (args[0].powi(2) * std::f64::consts::LN_2),
+            MultiAD::Log10 => out[0] = -1.0 / (args[0].powi(2) * std::f64::consts::LN_10),
+            // d²(σ(x))/dx² = σ(x) (1 - σ(x)) (1 - 2σ(x)) = -σ(x) σ(-x) tanh(x/2)
+            MultiAD::Sigmoid => {
+                out[0] = -sigmoid(args[0]) * sigmoid(-args[0]) * (args[0] / 2.0).tanh();
+            }
+            MultiAD::Softplus => out[0] = sigmoid(args[0]) * sigmoid(-args[0]),
+            // d²(erf(x))/dx² = -2x erf'(x)
+            MultiAD::Erf => out[0] = -2.0 * args[0] * special::erf_derivative(args[0]),
+            MultiAD::Erfc => out[0] = 2.0 * args[0] * special::erf_derivative(args[0]),
+            MultiAD::Lgamma => out[0] = special::trigamma(args[0]),
+            MultiAD::Digamma => out[0] = special::tetragamma(args[0]),
+            MultiAD::Trigamma => out[0] = special::pentagamma(args[0]),
+            // d²(Φ(x))/dx² = -x φ(x)
+            MultiAD::NormalCdf => out[0] = -args[0] * special::normal_pdf(args[0]),
+            MultiAD::NormalLogpdf => out[0] = -1.0,
+            // d²(W(x))/dx² = -W'(x)² (2 + W) / (1 + W)
+            MultiAD::LambertW => {
+                let w = special::lambert_w(args[0]);
+                let dw = special::lambert_w_derivative(args[0], w);
+                out[0] = -dw * dw * (2.0 + w) / (1.0 + w);
             }
-            MultiAD::Add => Box::new(|z_cotangent: f64| vec![z_cotangent, z_cotangent]),
-            MultiAD::Sub => Box::new(|z_cotangent: f64| vec![z_cotangent, -z_cotangent]),
             MultiAD::Mul => {
-                let arg0 = args[0];
-                let arg1 = args[1];
-                Box::new(move |z_cotangent: f64| vec![z_cotangent * arg1, z_cotangent * arg0])
+                out[1] = 1.0;
+                out[2] = 1.0;
             }
             MultiAD::Div => {
-                let arg0 = args[0];
-                let arg1 = args[1];
-                Box::new(move |z_cotangent: f64| {
-                    vec![z_cotangent / arg1, -z_cotangent * arg0 / arg1.powi(2)]
-                })
+                let (a, b) = (args[0], args[1]);
+                out[1] = -1.0 / b.powi(2);
+                out[2] = out[1];
+                out[3] = 2.0 * a / b.powi(3);
             }
             MultiAD::Pow => {
-                let base = args[0];
-                let exp = args[1];
-                Box::new(move |z_cotangent: f64| {
-                    // d(a^b)/da = b * a^(b-1)
-                    let d_base = z_cotangent * exp * base.powf(exp - 1.0);
-                    // d(a^b)/db = a^b * ln(a)
-                    let d_exp = z_cotangent * base.powf(exp) * base.ln();
-                    vec![d_base, d_exp]
-                })
+                let (base, exp) = (args[0], args[1]);
+                let ln_base = base.ln();
+                // d²(a^b)/da² = b (b-1) a^(b-2)
+                out[0] = exp * (exp - 1.0) * base.powf(exp - 2.0);
+                // d²(a^b)/da db = a^(b-1) (1 + b ln(a))
+                out[1] = base.powf(exp - 1.0) * (1.0 + exp * ln_base);
+                out[2] = out[1];
+                // d²(a^b)/db² = a^b ln²(a)
+                out[3] = base.powf(exp) * ln_base.powi(2);
             }
-            MultiAD::Sqrt => {
-                let arg_val = args[0];
-                Box::new(move |z_cotangent: f64| {
-                    // d(sqrt(x))/dx = 1/(2*sqrt(x))
-                    let x_cotangent = z_cotangent / (2.0 * arg_val.sqrt());
-                    vec![x_cotangent]
-                })
+            MultiAD::Atan2 => {
+                let (y, x) = (args[0], args[1]);
+                let r4 = (x * x + y * y).powi(2);
+                out[0] = -2.0 * x * y / r4;
+                out[1] = (y * y - x * x) / r4;
+                out[2] = out[1];
+                out[3] = 2.0 * x * y / r4;
             }
-            MultiAD::Abs => {
-                let arg_val = args[0];
-                Box::new(move |z_cotangent: f64| {
-                    // d(|x|)/dx = sign(x) where sign(0) = 0
-                    let sign = if arg_val >= 0.0 { 1.0 } else { -1.0 };
-                    vec![z_cotangent * sign]
-                })
+            // d²(hypot(a, b))/da² = b² / hypot³, d²/da db = -a b / hypot³
+            MultiAD::Hypot => {
+                let (a, b) = (args[0], args[1]);
+                let h3 = a.hypot(b).powi(3);
+                out[0] = b * b / h3;
+                out[1] = -a * b / h3;
+                out[2] = out[1];
+                out[3] = a * a / h3;
             }
-        };
-        Ok(W::from(backward_fn))
+        }
     }
 
     /// Compute forward pass only (no gradient computation).
@@ -286,7 +971,11 @@ impl MultiAD {
     ///
     /// # Errors
     ///
-    /// Returns `Err(AutodiffError)` if an operation receives incorrect arity.
+    /// Returns `Err(AutodiffError)` if the graph is malformed; see
+    /// [`MultiAD::validate`]. Graphs with `Param` nodes return
+    /// `Err(AutodiffError::MissingParams)`; evaluate them with
+    /// [`MultiAD::compute_grad_with_params`] or
+    /// [`CompiledGraph::value_with_params`] instead.
     ///
     /// # Examples
     ///
@@ -299,23 +988,9 @@ impl MultiAD {
     /// ```
     #[must_use = "forward computation is expensive; discarding the result is likely a bug"]
     pub fn compute(exprs: &[(MultiAD, Vec<usize>)], inputs: &[f64]) -> Result<f64> {
-        let mut values: Vec<f64> = inputs.to_vec();
-
-        for (op, arg_indices) in exprs {
-            if *op == MultiAD::Inp {
-                continue; // Input values are already in the values array
-            }
-
-            // Gather the argument values from the computation graph
-            let arg_values: Vec<f64> = arg_indices.iter().map(|&i| values[i]).collect();
-
-            // Compute this operation
-            let value = op.forward(&arg_values)?;
-            values.push(value);
-        }
-
-        // Return the final computed value
-        Ok(values.last().copied().unwrap_or(0.0))
+        let graph = CompiledGraph::new(exprs, inputs.len())?;
+        let mut ws = graph.workspace();
+        graph.value(inputs, &mut ws)
     }
 
     /// Compute forward pass and return gradient function.
@@ -338,7 +1013,8 @@ impl MultiAD {
     ///
     /// # Errors
     ///
-    /// Returns `Err(AutodiffError)` if an operation receives incorrect arity.
+    /// Returns `Err(AutodiffError)` if the graph is malformed; see
+    /// [`MultiAD::validate`].
     ///
     /// # Examples
     ///
@@ -364,57 +1040,12 @@ impl MultiAD {
     where
         W: From<Box<DynGradFn>> + std::ops::Deref<Target = DynGradFn> + 'static,
     {
-        // Pre-allocate with capacity for better performance
-        let estimated_size = inputs.len() + exprs.len();
-        let mut values: Vec<f64> = Vec::with_capacity(estimated_size);
-        values.extend_from_slice(inputs);
-
-        let mut backward_ops: Vec<Box<DynGradFn>> = Vec::with_capacity(exprs.len());
-        let mut arg_indices_list: Vec<Vec<usize>> = Vec::with_capacity(exprs.len());
-
-        // Forward pass: compute all values and track backward operations
-        for (op, args) in exprs {
-            if *op == MultiAD::Inp {
-                continue;
-            }
-            let arg_values: Vec<f64> = args.iter().map(|&i| values[i]).collect();
-            let value = op.forward(&arg_values)?;
-            values.push(value);
-
-            // Store the backward operation (which captures necessary values)
-            backward_ops.push(op.backward_generic(&arg_values)?);
-            arg_indices_list.push(args.clone());
-        }
-
-        let final_value = values.last().copied().unwrap_or(0.0);
-
-        // Clone the data we need for the backward pass
-        let num_inputs = inputs.len();
-        let values_clone = values;
-
-        let backward_fn = Box::new(move |cotangent: f64| -> Vec<f64> {
-            let mut cotangent_values = vec![0.0; values_clone.len()];
-            cotangent_values[values_clone.len() - 1] = cotangent;
-
-            // Backward pass: propagate cotangents from output to inputs
-            for (i, (backward_op, arg_indices)) in backward_ops
-                .iter()
-                .zip(arg_indices_list.iter())
-                .rev() // Process operations in reverse order
-                .enumerate()
-            {
-                let output_idx = values_clone.len() - 1 - i;
-                let current_cotangent_value = cotangent_values[output_idx];
-                let argv_cotangents = backward_op(current_cotangent_value);
-
-                // Accumulate gradients for each input argument
-                for (arg_idx, arg_cotangent) in arg_indices.iter().zip(argv_cotangents) {
-                    cotangent_values[*arg_idx] += arg_cotangent;
-                }
-            }
+        // Forward pass: record values and local partials on a flat tape
+        let tape = FlatTape::record(exprs, inputs, &[], SubgradientPolicy::default())?;
+        let final_value = tape.output();
 
-            cotangent_values[..num_inputs].to_vec()
-        });
+        // Backward pass: one sweep over the tape per cotangent
+        let backward_fn = Box::new(move |cotangent: f64| -> Vec<f64> { tape.gradient(cotangent) });
 
         Ok((final_value, W::from(backward_fn)))
     }
@@ -0,0 +1,308 @@
+//! Graph optimisation passes.
+//!
+//! [`MultiAD::optimize`] rewrites a graph into an equivalent, smaller one:
+//! constant subgraphs are folded into `Const` nodes, repeated subexpressions
+//! are merged, nodes that never reach the output are dropped, and the
+//! remaining nodes are renumbered. Values and gradients are unchanged.
+
+use std::collections::HashMap;
+
+use super::multi_ad::MultiAD;
+use crate::error::Result;
+
+/// Nodes after folding and merging, with the representative of every
+/// original node.
+struct Merged {
+    nodes: Vec<(MultiAD, Vec<usize>)>,
+    canonical: Vec<usize>,
+}
+
+impl MultiAD {
+    /// Whether swapping the two arguments leaves the result unchanged.
+    fn is_commutative(&self) -> bool {
+        matches!(self, MultiAD::Add | MultiAD::Mul)
+    }
+
+    /// Optimise a graph in place without changing what it computes.
+    ///
+    /// Runs, in one pipeline:
+    /// - constant folding: nodes whose arguments are all `Const` become `Const`
+    /// - common subexpression elimination: identical nodes (same op, same
+    ///   arguments, in either order for `Add` and `Mul`) are merged
+    /// - dead-code elimination: nodes the output does not depend on are removed
+    /// - index remapping: the graph starts with one `Inp` node per input,
+    ///   followed by the remaining operations in their original order
+    ///
+    /// Every input keeps its `Inp` node even if it is unused, so the optimised
+    /// graph takes the same inputs and returns gradients of the same length.
+    /// The graph takes `num_inputs` inputs, laid out as for
+    /// [`MultiAD::validate`]; a graph without `Inp` nodes comes out with one
+    /// for each of them.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(AutodiffError)` if the graph is malformed (see
+    /// [`MultiAD::validate`]); the graph is left untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petite_ad::{MultiAD, multi_ops};
+    ///
+    /// // sin(x) * sin(x) + (2 * 3), with a dead cos(x)
+    /// let mut exprs = multi_ops![
+    ///     (inp, 0), (sin, 0), (sin, 0), (mul, 1, 2), (cos, 0),
+    ///     (const, 2.0), (const, 3.0), (mul, 5, 6), (add, 3, 7)
+    /// ].to_vec();
+    /// MultiAD::optimize(&mut exprs, 1).unwrap();
+    /// assert_eq!(
+    ///     exprs,
+    ///     multi_ops![(inp, 0), (sin, 0), (mul, 1, 1), (const, 6.0), (add, 2, 3)]
+    /// );
+    /// ```
+    pub fn optimize(exprs: &mut Vec<(MultiAD, Vec<usize>)>, num_inputs: usize) -> Result<()> {
+        Self::validate(exprs, num_inputs)?;
+
+        let merged = Self::fold_and_merge(&Self::with_input_nodes(exprs, num_inputs))?;
+        let output = merged.canonical[merged.canonical.len() - 1];
+        *exprs = Self::prune(&merged.nodes, output, num_inputs);
+        Ok(())
+    }
+
+    /// Dead-code elimination and index remapping: keep the nodes `output`
+    /// depends on, behind one `Inp` node per input, with `output` last.
+    pub(super) fn prune(
+        nodes: &[(MultiAD, Vec<usize>)],
+        output: usize,
+        num_inputs: usize,
+    ) -> Vec<(MultiAD, Vec<usize>)> {
+        let live = Self::live_nodes(nodes, output);
+        Self::renumber(nodes, &live, output, num_inputs)
+    }
+
+    /// Constant folding and common subexpression elimination in one forward
+    /// sweep, so folded constants are themselves merged.
+    fn fold_and_merge(exprs: &[(MultiAD, Vec<usize>)]) -> Result<Merged> {
+        let mut nodes: Vec<(MultiAD, Vec<usize>)> = Vec::with_capacity(exprs.len());
+        let mut canonical = Vec::with_capacity(exprs.len());
+        let mut seen: HashMap<(MultiAD, Vec<usize>), usize> = HashMap::new();
+        let mut arg_values = Vec::new();
+
+        for (op, args) in exprs {
+            let mut node = match op {
+                MultiAD::Inp => (*op, args.clone()),
+                _ => (*op, args.iter().map(|&arg| canonical[arg]).collect()),
+            };
+
+            let foldable = !matches!(op, MultiAD::Inp | MultiAD::Const(_) | MultiAD::Param(_));
+            arg_values.clear();
+            if foldable {
+                arg_values.extend(node.1.iter().map_while(|&arg| match nodes[arg].0 {
+                    MultiAD::Const(c) => Some(c),
+                    _ => None,
+                }));
+            }
+            if foldable && arg_values.len() == node.1.len() {
+                node = (MultiAD::Const(op.forward(&arg_values)?), Vec::new());
+            } else if op.is_commutative() {
+                node.1.sort_unstable();
+            }
+
+            let index = *seen.entry(node).or_insert_with_key(|node| {
+                nodes.push(node.clone());
+                nodes.len() - 1
+            });
+            canonical.push(index);
+        }
+        Ok(Merged { nodes, canonical })
+    }
+
+    /// Mark the nodes `output` depends on.
+    fn live_nodes(nodes: &[(MultiAD, Vec<usize>)], output: usize) -> Vec<bool> {
+        let mut live = vec![false; nodes.len()];
+        live[output] = true;
+        for (index, (op, args)) in nodes.iter().enumerate().rev() {
+            if live[index] && *op != MultiAD::Inp {
+                for &arg in args {
+                    live[arg] = true;
+                }
+            }
+        }
+        live
+    }
+
+    /// Emit the `Inp` prefix and the live operations with remapped arguments.
+    fn renumber(
+        nodes: &[(MultiAD, Vec<usize>)],
+        live: &[bool],
+        output: usize,
+        num_inputs: usize,
+    ) -> Vec<(MultiAD, Vec<usize>)> {
+        let mut remap = vec![0; nodes.len()];
+        for (index, (op, args)) in nodes.iter().enumerate() {
+            if *op == MultiAD::Inp {
+                remap[index] = args[0];
+            }
+        }
+
+        let mut graph: Vec<(MultiAD, Vec<usize>)> =
+            (0..num_inputs).map(|k| (MultiAD::Inp, vec![k])).collect();
+        for (index, (op, args)) in nodes.iter().enumerate() {
+            if live[index] && *op != MultiAD::Inp {
+                remap[index] = graph.len();
+                graph.push((*op, args.iter().map(|&arg| remap[arg]).collect()));
+            }
+        }
+
+        // The output must be the last node; an input output is declared again
+        if let (MultiAD::Inp, args) = &nodes[output] {
+            if args[0] + 1 != graph.len() {
+                graph.push((MultiAD::Inp, vec![args[0]]));
+            }
+        }
+        graph
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::AutodiffError;
+    use crate::multi::params::Wrt;
+    use crate::multi_ops;
+    use crate::test_utils::approx_eq_eps as approx_eq;
+
+    fn assert_equivalent(before: &[(MultiAD, Vec<usize>)], after: &[(MultiAD, Vec<usize>)]) {
+        for inputs in [[0.7, 1.9], [1.3, 0.4]] {
+            let (v0, g0) = MultiAD::compute_grad(before, &inputs).unwrap();
+            let (v1, g1) = MultiAD::compute_grad(after, &inputs).unwrap();
+            assert!(approx_eq(v0, v1, 1e-12));
+            for (a, b) in g0(1.0).iter().zip(g1(1.0)) {
+                assert!(approx_eq(*a, b, 1e-12));
+            }
+        }
+    }
+
+    #[test]
+    fn test_optimize_preserves_values_and_gradients() {
+        let (graph, _) =
+            MultiAD::parse("sin(x) * sin(x) + (2 + 3) * y - sin(x) * y / exp(1)").unwrap();
+        let mut optimized = graph.clone();
+        MultiAD::optimize(&mut optimized, 2).unwrap();
+        assert!(optimized.len() < graph.len());
+        assert_equivalent(&graph, &optimized);
+    }
+
+    #[test]
+    fn test_common_subexpressions() {
+        // x * y and y * x are the same node
+        let mut exprs = multi_ops![
+            (inp, 0),
+            (inp, 1),
+            (mul, 0, 1),
+            (inp, 1),
+            (mul, 3, 0),
+            (sub, 2, 4)
+        ]
+        .to_vec();
+        let original = exprs.clone();
+        MultiAD::optimize(&mut exprs, 2).unwrap();
+        assert_eq!(
+            exprs,
+            multi_ops![(inp, 0), (inp, 1), (mul, 0, 1), (sub, 2, 2)]
+        );
+        assert_equivalent(&original, &exprs);
+    }
+
+    #[test]
+    fn test_dead_nodes_and_unused_inputs() {
+        let mut exprs = multi_ops![(inp, 0), (inp, 1), (exp, 1), (cos, 0)].to_vec();
+        MultiAD::optimize(&mut exprs, 2).unwrap();
+        assert_eq!(exprs, multi_ops![(inp, 0), (inp, 1), (cos, 0)]);
+        assert_eq!(
+            MultiAD::compute_grad(&exprs, &[0.0, 1.0]).unwrap().1(1.0).len(),
+            2
+        );
+
+        // An input as the output is declared again at the end
+        let mut exprs = multi_ops![(inp, 0), (inp, 1), (sin, 1), (inp, 0)].to_vec();
+        MultiAD::optimize(&mut exprs, 2).unwrap();
+        assert_eq!(exprs, multi_ops![(inp, 0), (inp, 1), (inp, 0)]);
+    }
+
+    #[test]
+    fn test_implicit_inputs() {
+        // sin(x) * sin(x) + y with x and y at indices 0 and 1
+        let mut exprs = multi_ops![(sin, 0), (sin, 0), (mul, 2, 3), (add, 4, 1)].to_vec();
+        let original = exprs.clone();
+        MultiAD::optimize(&mut exprs, 2).unwrap();
+        assert_eq!(
+            exprs,
+            multi_ops![(inp, 0), (inp, 1), (sin, 0), (mul, 2, 2), (add, 1, 3)]
+        );
+        assert_equivalent(&original, &exprs);
+
+        // x * z reads input 2 past a dead sin(x) node
+        let mut exprs = multi_ops![(sin, 0), (mul, 0, 2)].to_vec();
+        MultiAD::optimize(&mut exprs, 3).unwrap();
+        assert_eq!(exprs, multi_ops![(inp, 0), (inp, 1), (inp, 2), (mul, 0, 2)]);
+        assert_eq!(MultiAD::compute(&exprs, &[2.0, 5.0, 7.0]).unwrap(), 14.0);
+    }
+
+    #[test]
+    fn test_constant_folding() {
+        let mut exprs = multi_ops![
+            (inp, 0),
+            (const, 4.0),
+            (sqrt, 1),
+            (const, 3.0),
+            (pow, 2, 3),
+            (mul, 0, 4)
+        ]
+        .to_vec();
+        MultiAD::optimize(&mut exprs, 1).unwrap();
+        assert_eq!(exprs, multi_ops![(inp, 0), (const, 8.0), (mul, 0, 1)]);
+
+        // A fully constant graph folds to a single node
+        let mut exprs = multi_ops![(const, 1.0), (neg, 0), (exp, 1)].to_vec();
+        MultiAD::optimize(&mut exprs, 0).unwrap();
+        assert_eq!(exprs, multi_ops![(const, (-1.0_f64).exp())]);
+    }
+
+    #[test]
+    fn test_params_are_merged_not_folded() {
+        let mut exprs =
+            multi_ops![(inp, 0), (param, 0), (param, 0), (mul, 1, 2), (mul, 3, 0)].to_vec();
+        let original = exprs.clone();
+        MultiAD::optimize(&mut exprs, 1).unwrap();
+        assert_eq!(
+            exprs,
+            multi_ops![(inp, 0), (param, 0), (mul, 1, 1), (mul, 0, 2)]
+        );
+
+        let grads = |exprs| {
+            MultiAD::compute_grad_with_params(exprs, &[3.0], &[2.0], Wrt::Both)
+                .unwrap()
+                .1(1.0)
+        };
+        assert_eq!(grads(&original), grads(&exprs));
+    }
+
+    #[test]
+    fn test_optimize_rejects_malformed_graphs() {
+        let mut exprs = multi_ops![(inp, 0), (sin, 2)].to_vec();
+        let original = exprs.clone();
+        assert_eq!(
+            MultiAD::optimize(&mut exprs, 1),
+            Err(AutodiffError::IndexOutOfBounds {
+                index: 2,
+                max_index: 1
+            })
+        );
+        assert_eq!(exprs, original);
+        assert_eq!(
+            MultiAD::optimize(&mut Vec::new(), 0),
+            Err(AutodiffError::EmptyGraph)
+        );
+    }
+}
@@ -0,0 +1,155 @@
+//! Trainable parameters.
+//!
+//! `Param(k)` nodes are graph leaves read from a separate parameter vector,
+//! so data inputs and model weights can be differentiated independently.
+
+use super::multi_ad::MultiAD;
+use super::subgradient::SubgradientPolicy;
+use super::tape::FlatTape;
+use super::types::*;
+use crate::error::Result;
+
+/// Which leaves a gradient is taken with respect to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Wrt {
+    /// Data inputs only, one entry per input
+    #[default]
+    Inputs,
+    /// Parameters only, one entry per parameter
+    Params,
+    /// Inputs followed by parameters
+    Both,
+}
+
+impl MultiAD {
+    /// Compute forward pass with a parameter vector and return a gradient
+    /// function selecting inputs, parameters or both.
+    ///
+    /// `Param(k)` nodes read `params[k]`. The gradient function returns
+    /// `∂f/∂inputs`, `∂f/∂params`, or both concatenated (inputs first),
+    /// depending on `wrt`. Non-smooth ops use [`SubgradientPolicy::default`];
+    /// [`MultiAD::compute_grad_with_params_and_policy`] takes another policy.
+    ///
+    /// # Arguments
+    ///
+    /// * `exprs` - Computational graph as (operation, indices) pairs
+    /// * `inputs` - Input values to evaluate at
+    /// * `params` - Parameter values read by `Param` nodes
+    /// * `wrt` - Which gradients the returned function produces
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(AutodiffError)` if the graph is malformed (see
+    /// [`MultiAD::validate`]), and `Err(AutodiffError::MissingParams)` if
+    /// `params` is shorter than the largest `Param` index requires.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petite_ad::{GraphBuilder, MultiAD, Wrt};
+    ///
+    /// // f(x; w, b) = w * x + b
+    /// let mut builder = GraphBuilder::new(1);
+    /// builder.param("w", 0.5).param("b", 0.1).mul(1, 0).add(3, 2);
+    /// let graph = builder.build();
+    /// let params = builder.initial_params();
+    ///
+    /// let (value, grad_fn) =
+    ///     MultiAD::compute_grad_with_params(&graph, &[2.0], &params, Wrt::Params).unwrap();
+    /// assert!((value - 1.1).abs() < 1e-12);
+    /// assert_eq!(grad_fn(1.0), vec![2.0, 1.0]); // [∂f/∂w, ∂f/∂b]
+    /// ```
+    #[must_use = "gradient computation is expensive; discarding the result is likely a bug"]
+    pub fn compute_grad_with_params(
+        exprs: &[(MultiAD, Vec<usize>)],
+        inputs: &[f64],
+        params: &[f64],
+        wrt: Wrt,
+    ) -> Result<BackwardResultBox> {
+        let tape = FlatTape::record(exprs, inputs, params, SubgradientPolicy::default())?;
+        let value = tape.output();
+        let backward_fn = Box::new(move |cotangent: f64| tape.gradient_wrt(cotangent, wrt));
+        Ok((value, backward_fn))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::AutodiffError;
+    use crate::multi::builder::GraphBuilder;
+    use crate::test_utils::approx_eq_eps as approx_eq;
+
+    /// f(x, y; a, b) = a * sin(x) + b * x * y + a * b
+    fn model() -> (Vec<(MultiAD, Vec<usize>)>, Vec<f64>) {
+        let mut builder = GraphBuilder::new(2);
+        builder
+            .param("a", 0.7) // 2
+            .param("b", -1.3) // 3
+            .sin(0) // 4
+            .mul(2, 4) // 5
+            .mul(0, 1) // 6
+            .mul(3, 6) // 7
+            .add(5, 7) // 8
+            .param("a", 0.0) // 9, same parameter as index 2
+            .mul(9, 3) // 10
+            .add(8, 10); // 11
+        (builder.build(), builder.initial_params())
+    }
+
+    #[test]
+    fn test_param_gradients() {
+        let (graph, params) = model();
+        assert_eq!(params, vec![0.7, -1.3]);
+        let (x, y, a, b) = (0.4, 2.0, 0.7, -1.3);
+        let inputs = [x, y];
+
+        let (value, grad_fn) =
+            MultiAD::compute_grad_with_params(&graph, &inputs, &params, Wrt::Both).unwrap();
+        assert!(approx_eq(value, a * x.sin() + b * x * y + a * b, 1e-12));
+
+        let expected = [
+            a * x.cos() + b * y, // ∂f/∂x
+            b * x,               // ∂f/∂y
+            x.sin() + b,         // ∂f/∂a, accumulated over both Param(0) nodes
+            x * y + a,           // ∂f/∂b
+        ];
+        for (g, e) in grad_fn(1.0).iter().zip(expected) {
+            assert!(approx_eq(*g, e, 1e-12));
+        }
+
+        let inputs_only = MultiAD::compute_grad_with_params(&graph, &inputs, &params, Wrt::Inputs);
+        assert_eq!(inputs_only.unwrap().1(1.0), grad_fn(1.0)[..2]);
+        let params_only = MultiAD::compute_grad_with_params(&graph, &inputs, &params, Wrt::Params);
+        assert_eq!(params_only.unwrap().1(1.0), grad_fn(1.0)[2..]);
+    }
+
+    #[test]
+    fn test_params_in_multi_ops() {
+        // f(x; w) = w * x
+        let exprs = crate::multi_ops![(inp, 0), (param, 0), (mul, 1, 0)];
+        let (value, grad_fn) =
+            MultiAD::compute_grad_with_params(&exprs, &[3.0], &[2.0], Wrt::Both).unwrap();
+        assert_eq!(value, 6.0);
+        assert_eq!(grad_fn(1.0), vec![2.0, 3.0]);
+    }
+
+    #[test]
+    fn test_missing_params() {
+        let (graph, _) = model();
+        assert_eq!(
+            MultiAD::compute_grad_with_params(&graph, &[1.0, 2.0], &[1.0], Wrt::Params,).err(),
+            Some(AutodiffError::MissingParams {
+                expected: 2,
+                actual: 1
+            })
+        );
+        assert_eq!(
+            MultiAD::compute_grad(&graph, &[1.0, 2.0]).err(),
+            Some(AutodiffError::MissingParams {
+                expected: 2,
+                actual: 0
+            })
+        );
+    }
+}
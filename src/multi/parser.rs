@@ -0,0 +1,521 @@
+//! Infix expression parser.
+//!
+//! Turns formulas such as `"sin(x) * (x + y)"` into graphs. Precedence from
+//! lowest to highest is `+ -`, then `* /`, then unary `-`, then `^`, which is
+//! right-associative. Functions are written as calls, e.g. `sqrt(x)` or
+//! `pow(x, y)`.
+
+use std::iter::Peekable;
+use std::str::Chars;
+
+use super::multi_ad::MultiAD;
+use super::types::ParsedExpr;
+use super::var::{Tape, Var};
+use crate::error::{AutodiffError, Result};
+
+/// Functions callable by name, with the op they record.
+const FUNCTIONS: &[(&str, MultiAD)] = &[
+    ("sin", MultiAD::Sin),
+    ("cos", MultiAD::Cos),
+    ("tan", MultiAD::Tan),
+    ("exp", MultiAD::Exp),
+    ("ln", MultiAD::Ln),
+    ("sqrt", MultiAD::Sqrt),
+    ("abs", MultiAD::Abs),
+    ("neg", MultiAD::Neg),
+    ("pow", MultiAD::Pow),
+    ("tanh", MultiAD::Tanh),
+    ("sinh", MultiAD::Sinh),
+    ("cosh", MultiAD::Cosh),
+    ("asin", MultiAD::Asin),
+    ("acos", MultiAD::Acos),
+    ("atan", MultiAD::Atan),
+    ("atan2", MultiAD::Atan2),
+    ("log1p", MultiAD::Log1p),
+    ("expm1", MultiAD::Expm1),
+    ("log2", MultiAD::Log2),
+    ("log10", MultiAD::Log10),
+    ("sigmoid", MultiAD::Sigmoid),
+    ("softplus", MultiAD::Softplus),
+    ("hypot", MultiAD::Hypot),
+    ("min", MultiAD::Min),
+    ("max", MultiAD::Max),
+    ("clamp", MultiAD::Clamp),
+    ("relu", MultiAD::Relu),
+    ("floor", MultiAD::Floor),
+    ("ceil", MultiAD::Ceil),
+    ("round", MultiAD::Round),
+    ("sign", MultiAD::Sign),
+    ("fmod", MultiAD::Fmod),
+    ("erf", MultiAD::Erf),
+    ("erfc", MultiAD::Erfc),
+    ("lgamma", MultiAD::Lgamma),
+    ("digamma", MultiAD::Digamma),
+    ("trigamma", MultiAD::Trigamma),
+    ("normal_cdf", MultiAD::NormalCdf),
+    ("normal_logpdf", MultiAD::NormalLogpdf),
+    ("lambert_w", MultiAD::LambertW),
+    ("sum", MultiAD::Sum),
+    ("prod", MultiAD::Prod),
+    ("mean", MultiAD::Mean),
+    ("logsumexp", MultiAD::LogSumExp),
+    ("softmax_sum", MultiAD::SoftmaxSum),
+];
+
+/// Deepest nesting of parentheses, calls, signs and powers the parser
+/// accepts, so that deeply nested input is an error rather than a stack
+/// overflow.
+const MAX_DEPTH: usize = 256;
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Ident(String),
+    Number(f64),
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Caret,
+    LParen,
+    RParen,
+    Comma,
+    End,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    line: usize,
+    column: usize,
+}
+
+/// Splits the source into tokens, tracking 1-based line and column.
+struct Lexer<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(src: &'a str) -> Self {
+        Self {
+            chars: src.chars().peekable(),
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn take_while(&mut self, first: char, pred: impl Fn(char) -> bool) -> String {
+        let mut text = String::from(first);
+        while let Some(&c) = self.chars.peek() {
+            if !pred(c) {
+                break;
+            }
+            text.push(c);
+            self.bump();
+        }
+        text
+    }
+
+    /// Digits and dots, plus an exponent such as `e-3`.
+    fn number(&mut self, first: char) -> String {
+        let mut text = self.take_while(first, |c| c.is_ascii_alphanumeric() || c == '.');
+        if text.ends_with(['e', 'E']) {
+            if let Some(&sign @ ('+' | '-')) = self.chars.peek() {
+                self.bump();
+                text.push_str(&self.take_while(sign, |c| c.is_ascii_digit()));
+            }
+        }
+        text
+    }
+
+    fn tokenize(mut self) -> Result<Vec<Token>> {
+        let mut tokens = Vec::new();
+        loop {
+            let (line, column) = (self.line, self.column);
+            let Some(c) = self.bump() else {
+                tokens.push(Token {
+                    kind: TokenKind::End,
+                    line,
+                    column,
+                });
+                return Ok(tokens);
+            };
+            let kind = match c {
+                c if c.is_whitespace() => continue,
+                '+' => TokenKind::Plus,
+                '-' => TokenKind::Minus,
+                '*' => TokenKind::Star,
+                '/' => TokenKind::Slash,
+                '^' => TokenKind::Caret,
+                '(' => TokenKind::LParen,
+                ')' => TokenKind::RParen,
+                ',' => TokenKind::Comma,
+                c if c.is_ascii_digit() || c == '.' => {
+                    let text = self.number(c);
+                    match text.parse() {
+                        Ok(value) => TokenKind::Number(value),
+                        Err(_) => {
+                            return Err(AutodiffError::parse(
+                                format!("invalid number '{}'", text),
+                                line,
+                                column,
+                            ))
+                        }
+                    }
+                }
+                c if c.is_alphabetic() || c == '_' => {
+                    TokenKind::Ident(self.take_while(c, |c| c.is_alphanumeric() || c == '_'))
+                }
+                c => {
+                    return Err(AutodiffError::parse(
+                        format!("unexpected character '{}'", c),
+                        line,
+                        column,
+                    ))
+                }
+            };
+            tokens.push(Token { kind, line, column });
+        }
+    }
+}
+
+/// Recursive-descent parser recording onto a [`Tape`].
+struct Parser<'t> {
+    tokens: Vec<Token>,
+    pos: usize,
+    tape: &'t Tape,
+    variables: Vec<(String, Var<'t>)>,
+    /// Current nesting depth, bounded by [`MAX_DEPTH`]
+    depth: usize,
+}
+
+impl<'t> Parser<'t> {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos]
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.pos].clone();
+        if token.kind != TokenKind::End {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn error<T>(token: &Token, message: impl Into<String>) -> Result<T> {
+        Err(AutodiffError::parse(message, token.line, token.column))
+    }
+
+    fn expect(&mut self, kind: TokenKind, what: &str) -> Result<()> {
+        let token = self.next();
+        if token.kind == kind {
+            Ok(())
+        } else {
+            Self::error(&token, format!("expected {}", what))
+        }
+    }
+
+    /// expr := term (('+' | '-') term)*
+    fn expr(&mut self) -> Result<Var<'t>> {
+        let mut lhs = self.term()?;
+        loop {
+            lhs = match self.peek().kind {
+                TokenKind::Plus => {
+                    self.next();
+                    lhs + self.term()?
+                }
+                TokenKind::Minus => {
+                    self.next();
+                    lhs - self.term()?
+                }
+                _ => return Ok(lhs),
+            };
+        }
+    }
+
+    /// term := unary (('*' | '/') unary)*
+    fn term(&mut self) -> Result<Var<'t>> {
+        let mut lhs = self.unary()?;
+        loop {
+            lhs = match self.peek().kind {
+                TokenKind::Star => {
+                    self.next();
+                    lhs * self.unary()?
+                }
+                TokenKind::Slash => {
+                    self.next();
+                    lhs / self.unary()?
+                }
+                _ => return Ok(lhs),
+            };
+        }
+    }
+
+    /// unary := '-' unary | power
+    ///
+    /// Every level of nesting passes through here, so this is where the
+    /// depth is bounded.
+    fn unary(&mut self) -> Result<Var<'t>> {
+        if self.depth == MAX_DEPTH {
+            return Self::error(self.peek(), "expression nested too deeply");
+        }
+        self.depth += 1;
+        let result = if self.peek().kind == TokenKind::Minus {
+            self.next();
+            self.unary().map(|v| -v)
+        } else {
+            self.power()
+        };
+        self.depth -= 1;
+        result
+    }
+
+    /// power := primary ('^' unary)?
+    fn power(&mut self) -> Result<Var<'t>> {
+        let base = self.primary()?;
+        if self.peek().kind == TokenKind::Caret {
+            self.next();
+            return Ok(base.powf(self.unary()?));
+        }
+        Ok(base)
+    }
+
+    /// primary := number | ident '(' args ')' | ident | '(' expr ')'
+    fn primary(&mut self) -> Result<Var<'t>> {
+        let token = self.next();
+        match token.kind {
+            TokenKind::LParen => {
+                let inner = self.expr()?;
+                self.expect(TokenKind::RParen, "')'")?;
+                Ok(inner)
+            }
+            TokenKind::Ident(ref name) if self.peek().kind == TokenKind::LParen => {
+                self.call(&token, name)
+            }
+            TokenKind::Ident(name) => Ok(self.variable(name)),
+            TokenKind::Number(value) => Ok(self.tape.constant(value)),
+            TokenKind::End => Self::error(&token, "unexpected end of input"),
+            _ => Self::error(&token, "expected expression"),
+        }
+    }
+
+    fn call(&mut self, token: &Token, name: &str) -> Result<Var<'t>> {
+        let Some(&(_, op)) = FUNCTIONS.iter().find(|(f, _)| *f == name) else {
+            return Self::error(token, format!("unknown function '{}'", name));
+        };
+        self.expect(TokenKind::LParen, "'('")?;
+        let mut args = vec![self.expr()?];
+        while self.peek().kind == TokenKind::Comma {
+            self.next();
+            args.push(self.expr()?);
+        }
+        self.expect(TokenKind::RParen, "')'")?;
+
+        if !op.expected_arity().accepts(args.len()) {
+            return Self::error(
+                token,
+                format!(
+                    "{} expects {} argument(s), got {}",
+                    name,
+                    op.expected_arity(),
+                    args.len()
+                ),
+            );
+        }
+        Ok(self.tape.apply(op, &args))
+    }
+
+    fn variable(&mut self, name: String) -> Var<'t> {
+        if let Some((_, var)) = self.variables.iter().find(|(n, _)| *n == name) {
+            return *var;
+        }
+        let var = self.tape.var(0.0);
+        self.variables.push((name, var));
+        var
+    }
+}
+
+impl MultiAD {
+    /// Parse an infix formula into a graph.
+    ///
+    /// Returns the graph and the variable names in order of first appearance,
+    /// which is also the order of the graph inputs.
+    ///
+    /// Supported syntax: numbers such as `2`, `0.5` or `1e-3`, `+ - * / ^`,
+    /// unary `-`, parentheses, and the
+    /// functions `sin`, `cos`, `tan`, `exp`, `ln`, `sqrt`, `abs`, `neg`,
+    /// `tanh`, `sinh`, `cosh`, `asin`, `acos`, `atan`, `log1p`, `expm1`,
+    /// `log2`, `log10`, `sigmoid`, `softplus`, `relu`, `floor`, `ceil`,
+    /// `round`, `sign`, `erf`, `erfc`, `lgamma`, `digamma`, `trigamma`,
+    /// `normal_cdf`, `normal_logpdf`, `lambert_w`, `pow(base, exponent)`, `atan2(y, x)`, `hypot(a, b)`,
+    /// `min(a, b)`, `max(a, b)`, `fmod(a, b)`, `clamp(x, lo, hi)`, and the
+    /// n-ary `sum`, `prod`, `mean`, `logsumexp` and `softmax_sum`, which take
+    /// one or more arguments.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(AutodiffError::ParseError)` with the line and column of the
+    /// offending token if the formula is malformed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petite_ad::MultiAD;
+    ///
+    /// let (graph, vars) = MultiAD::parse("sin(x) * (x + y)").unwrap();
+    /// assert_eq!(vars, ["x", "y"]);
+    ///
+    /// let (value, grad_fn) = MultiAD::compute_grad(&graph, &[0.6, 1.4]).unwrap();
+    /// assert!((value - 0.6_f64.sin() * 2.0).abs() < 1e-12);
+    /// ```
+    pub fn parse(src: &str) -> Result<ParsedExpr> {
+        let tape = Tape::new();
+        let mut parser = Parser {
+            tokens: Lexer::new(src).tokenize()?,
+            pos: 0,
+            tape: &tape,
+            variables: Vec::new(),
+            depth: 0,
+        };
+        let output = parser.expr()?;
+        let trailing = parser.next();
+        if trailing.kind != TokenKind::End {
+            return Parser::error(&trailing, "unexpected token after expression");
+        }
+
+        let graph = tape.graph(output);
+        let names = parser.variables.into_iter().map(|(name, _)| name).collect();
+        Ok((graph, names))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::multi_ops;
+    use crate::test_utils::approx_eq_eps as approx_eq;
+
+    fn parse_err(src: &str) -> (String, usize, usize) {
+        match MultiAD::parse(src) {
+            Err(AutodiffError::ParseError {
+                message,
+                line,
+                column,
+            }) => (message, line, column),
+            other => panic!("expected parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_matches_multi_ops() {
+        let (graph, vars) = MultiAD::parse("sin(x) * (x + y)").unwrap();
+        assert_eq!(vars, ["x", "y"]);
+        assert_eq!(
+            graph,
+            multi_ops![(inp, 0), (inp, 1), (sin, 0), (add, 0, 1), (mul, 2, 3)]
+        );
+    }
+
+    #[test]
+    fn test_precedence_and_associativity() {
+        let (graph, vars) = MultiAD::parse("a - b - c * d / e ^ b ^ c + -a^b").unwrap();
+        assert_eq!(vars, ["a", "b", "c", "d", "e"]);
+
+        let (a, b, c, d, e): (f64, f64, f64, f64, f64) = (1.5, 0.5, 2.0, 3.0, 1.2);
+        let expected = a - b - c * d / e.powf(b.powf(c)) + -(a.powf(b));
+        let value = MultiAD::compute(&graph, &[a, b, c, d, e]).unwrap();
+        assert!(approx_eq(value, expected, 1e-12));
+    }
+
+    #[test]
+    fn test_all_functions() {
+        let src = "sin(x) + cos(x) + tan(x) + exp(x) + ln(x) + sqrt(x) + abs(neg(x)) + pow(x, y)";
+        let (graph, _) = MultiAD::parse(src).unwrap();
+        let (x, y): (f64, f64) = (0.7, 1.9);
+        let expected = x.sin() + x.cos() + x.tan() + x.exp() + x.ln() + x.sqrt() + x + x.powf(y);
+        let value = MultiAD::compute(&graph, &[x, y]).unwrap();
+        assert!(approx_eq(value, expected, 1e-12));
+    }
+
+    #[test]
+    fn test_gradients_and_whitespace() {
+        let (graph, vars) = MultiAD::parse("  x_1 *\n\t exp(y2)\n").unwrap();
+        assert_eq!(vars, ["x_1", "y2"]);
+        let (_, grad_fn) = MultiAD::compute_grad(&graph, &[2.0, 0.0]).unwrap();
+        assert_eq!(grad_fn(1.0), vec![1.0, 2.0]);
+    }
+
+    #[test]
+    fn test_numeric_literals() {
+        let (graph, vars) = MultiAD::parse("2 * x^3 - .5 / x + 1e-3 + 2.5E+1").unwrap();
+        assert_eq!(vars, ["x"]);
+        let x: f64 = 1.5;
+        let (value, grad_fn) = MultiAD::compute_grad(&graph, &[x]).unwrap();
+        assert!(approx_eq(
+            value,
+            2.0 * x.powi(3) - 0.5 / x + 1e-3 + 25.0,
+            1e-12
+        ));
+        assert!(approx_eq(
+            grad_fn(1.0)[0],
+            6.0 * x * x + 0.5 / (x * x),
+            1e-10
+        ));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse_err("x + $"),
+            ("unexpected character '$'".to_string(), 1, 5)
+        );
+        assert_eq!(
+            parse_err("x *\n  (y + )"),
+            ("expected expression".to_string(), 2, 8)
+        );
+        assert_eq!(parse_err("sin(x"), ("expected ')'".to_string(), 1, 6));
+        assert_eq!(
+            parse_err("foo(x)"),
+            ("unknown function 'foo'".to_string(), 1, 1)
+        );
+        assert_eq!(
+            parse_err("pow(x)"),
+            ("pow expects 2 argument(s), got 1".to_string(), 1, 1)
+        );
+        assert_eq!(
+            parse_err("x y"),
+            ("unexpected token after expression".to_string(), 1, 3)
+        );
+        assert_eq!(parse_err(""), ("unexpected end of input".to_string(), 1, 1));
+        assert_eq!(
+            parse_err("2.5.1 * x"),
+            ("invalid number '2.5.1'".to_string(), 1, 1)
+        );
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        let nested = |depth| format!("{}x{}", "(".repeat(depth), ")".repeat(depth));
+        assert!(MultiAD::parse(&nested(MAX_DEPTH - 1)).is_ok());
+        assert_eq!(
+            parse_err(&nested(200_000)),
+            ("expression nested too deeply".to_string(), 1, MAX_DEPTH + 1)
+        );
+        assert_eq!(
+            parse_err(&"-".repeat(200_000)).0,
+            "expression nested too deeply"
+        );
+    }
+}
@@ -0,0 +1,238 @@
+//! Numeric kernels for the n-ary reduction ops.
+//!
+//! `Sum`, `Prod`, `Mean`, `LogSumExp` and `SoftmaxSum` take any number of
+//! arguments. Their derivatives are computed here in one pass over the
+//! arguments rather than by differentiating a chain of binary nodes: products
+//! leave one factor out through prefix and suffix products, so zero factors
+//! need no division, and the softmax-based ops subtract the largest argument
+//! before exponentiating so they neither overflow nor underflow to `NaN`.
+
+/// Largest argument, or `NaN` if any argument is `NaN`.
+fn max(x: &[f64]) -> f64 {
+    x.iter().fold(f64::NEG_INFINITY, |m, &v| {
+        if v.is_nan() || m.is_nan() {
+            f64::NAN
+        } else {
+            m.max(v)
+        }
+    })
+}
+
+/// ln(Σ exp(xᵢ)), shifted by the largest argument.
+pub(crate) fn log_sum_exp(x: &[f64]) -> f64 {
+    let m = max(x);
+    if !m.is_finite() {
+        return m;
+    }
+    m + x.iter().map(|&v| (v - m).exp()).sum::<f64>().ln()
+}
+
+/// softmax(x), the gradient of [`log_sum_exp`], written into `out`.
+///
+/// Where the largest argument is infinite the weight is split evenly between
+/// the arguments equal to it.
+pub(crate) fn softmax(x: &[f64], out: &mut [f64]) {
+    let m = max(x);
+    if m.is_finite() {
+        for (o, &v) in out.iter_mut().zip(x) {
+            *o = (v - m).exp();
+        }
+    } else {
+        for (o, &v) in out.iter_mut().zip(x) {
+            *o = if v == m { 1.0 } else { 0.0 };
+        }
+    }
+    let total: f64 = out.iter().sum();
+    for o in out.iter_mut() {
+        *o /= total;
+    }
+}
+
+/// Softmax-weighted sum Σ softmax(x)ᵢ xᵢ, a smooth maximum of the arguments.
+///
+/// Arguments with zero weight, such as `-∞`, are left out of the sum.
+pub(crate) fn softmax_sum(x: &[f64]) -> f64 {
+    let m = max(x);
+    if !m.is_finite() {
+        return m;
+    }
+    let (weighted, total) = x.iter().fold((0.0, 0.0), |(weighted, total), &v| {
+        let w = (v - m).exp();
+        if w == 0.0 {
+            return (weighted, total);
+        }
+        (weighted + w * v, total + w)
+    });
+    weighted / total
+}
+
+/// Gradient of [`softmax_sum`]: pₖ (1 + xₖ - f) with p = softmax(x).
+pub(crate) fn softmax_sum_partials(x: &[f64], out: &mut [f64]) {
+    softmax(x, out);
+    let f = softmax_sum(x);
+    for (o, &v) in out.iter_mut().zip(x) {
+        if *o != 0.0 {
+            *o *= 1.0 + v - f;
+        }
+    }
+}
+
+/// Products of all arguments but one: `out[i] = Π_{j≠i} x[j]`.
+pub(crate) fn product_partials(x: &[f64], out: &mut [f64]) {
+    let mut prefix = 1.0;
+    for (o, &v) in out.iter_mut().zip(x) {
+        *o = prefix;
+        prefix *= v;
+    }
+    let mut suffix = 1.0;
+    for (o, &v) in out.iter_mut().zip(x).rev() {
+        *o *= suffix;
+        suffix *= v;
+    }
+}
+
+/// Row-major Hessian of the product: the products of all arguments but two
+/// off the diagonal, zero on it.
+pub(crate) fn product_second_partials(x: &[f64], out: &mut [f64]) {
+    let n = x.len();
+    let mut others = x.to_vec();
+    for (i, row) in out.chunks_mut(n).enumerate() {
+        // Leaving out i by making it a unit factor
+        others[i] = 1.0;
+        product_partials(&others, row);
+        row[i] = 0.0;
+        others[i] = x[i];
+    }
+}
+
+/// Row-major Hessian of [`log_sum_exp`]: diag(p) - p pᵀ with p = softmax(x).
+pub(crate) fn log_sum_exp_second_partials(x: &[f64], out: &mut [f64]) {
+    let n = x.len();
+    let mut p = vec![0.0; n];
+    softmax(x, &mut p);
+    for (i, row) in out.chunks_mut(n).enumerate() {
+        for (j, h) in row.iter_mut().enumerate() {
+            *h = -p[i] * p[j];
+        }
+        row[i] += p[i];
+    }
+}
+
+/// Row-major Hessian of [`softmax_sum`]:
+/// pₖ δₖⱼ (2 + xₖ - f) - pₖ pⱼ (2 + xₖ + xⱼ - 2f).
+pub(crate) fn softmax_sum_second_partials(x: &[f64], out: &mut [f64]) {
+    let n = x.len();
+    let mut p = vec![0.0; n];
+    softmax(x, &mut p);
+    let f = softmax_sum(x);
+    for (k, row) in out.chunks_mut(n).enumerate() {
+        row.fill(0.0);
+        if p[k] == 0.0 {
+            continue;
+        }
+        for (j, h) in row.iter_mut().enumerate() {
+            if p[j] != 0.0 {
+                *h = -p[k] * p[j] * (2.0 + x[k] + x[j] - 2.0 * f);
+            }
+        }
+        row[k] += p[k] * (2.0 + x[k] - f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::approx_eq_eps as approx_eq;
+
+    #[test]
+    fn test_log_sum_exp_is_stable() {
+        assert!(approx_eq(
+            log_sum_exp(&[1000.0, 1000.0]),
+            1000.0 + std::f64::consts::LN_2,
+            1e-12
+        ));
+        assert!(approx_eq(
+            log_sum_exp(&[-1000.0, -1000.0 + 2f64.ln()]),
+            -1000.0 + 3f64.ln(),
+            1e-12
+        ));
+        assert!(approx_eq(log_sum_exp(&[0.5]), 0.5, 1e-15));
+        assert_eq!(
+            log_sum_exp(&[f64::NEG_INFINITY, f64::NEG_INFINITY]),
+            f64::NEG_INFINITY
+        );
+        assert_eq!(log_sum_exp(&[1.0, f64::INFINITY]), f64::INFINITY);
+        assert!(log_sum_exp(&[1.0, f64::NAN, f64::INFINITY]).is_nan());
+
+        let mut p = [0.0; 3];
+        softmax(&[800.0, 800.0, -800.0], &mut p);
+        assert_eq!(p, [0.5, 0.5, 0.0]);
+        softmax(&[f64::INFINITY, 0.0, f64::INFINITY], &mut p);
+        assert_eq!(p, [0.5, 0.0, 0.5]);
+    }
+
+    #[test]
+    fn test_softmax_sum_ignores_negative_infinity() {
+        let x = [1.0, f64::NEG_INFINITY];
+        assert_eq!(softmax_sum(&x), 1.0);
+        let mut grad = [0.0; 2];
+        softmax_sum_partials(&x, &mut grad);
+        assert_eq!(grad, [1.0, 0.0]);
+        let mut hessian = [0.0; 4];
+        softmax_sum_second_partials(&x, &mut hessian);
+        assert_eq!(hessian, [0.0; 4]);
+    }
+
+    #[test]
+    fn test_softmax_sum_is_a_smooth_max() {
+        assert!(approx_eq(softmax_sum(&[3.0, 3.0]), 3.0, 1e-15));
+        assert!(approx_eq(softmax_sum(&[900.0, 0.0]), 900.0, 1e-12));
+        let expected = (1.0 * 1f64.exp() + 2.0 * 2f64.exp()) / (1f64.exp() + 2f64.exp());
+        assert!(approx_eq(softmax_sum(&[1.0, 2.0]), expected, 1e-15));
+    }
+
+    #[test]
+    fn test_product_partials_with_zeros() {
+        let mut out = [0.0; 3];
+        product_partials(&[2.0, 3.0, 4.0], &mut out);
+        assert_eq!(out, [12.0, 8.0, 6.0]);
+        product_partials(&[0.0, 3.0, 4.0], &mut out);
+        assert_eq!(out, [12.0, 0.0, 0.0]);
+        product_partials(&[0.0, 3.0, 0.0], &mut out);
+        assert_eq!(out, [0.0, 0.0, 0.0]);
+
+        let mut hessian = [0.0; 9];
+        product_second_partials(&[0.0, 3.0, 4.0], &mut hessian);
+        assert_eq!(hessian, [0.0, 4.0, 3.0, 4.0, 0.0, 0.0, 3.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_second_partials_against_finite_differences() {
+        type Gradient = fn(&[f64], &mut [f64]);
+        type Hessian = fn(&[f64], &mut [f64]);
+        let cases: [(Gradient, Hessian); 3] = [
+            (softmax, log_sum_exp_second_partials),
+            (softmax_sum_partials, softmax_sum_second_partials),
+            (product_partials, product_second_partials),
+        ];
+        let x = [0.3, -1.2, 2.0, 0.7];
+        let n = x.len();
+        let h = 1e-6;
+        for (gradient, hessian) in cases {
+            let mut expected = vec![0.0; n * n];
+            hessian(&x, &mut expected);
+            for j in 0..n {
+                let (mut plus, mut minus) = (x, x);
+                plus[j] += h;
+                minus[j] -= h;
+                let (mut g_plus, mut g_minus) = ([0.0; 4], [0.0; 4]);
+                gradient(&plus, &mut g_plus);
+                gradient(&minus, &mut g_minus);
+                for i in 0..n {
+                    let numeric = (g_plus[i] - g_minus[i]) / (2.0 * h);
+                    assert!(approx_eq(expected[i * n + j], numeric, 1e-6));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,554 @@
+//! Human-readable rendering of graphs as infix formulas or LaTeX.
+//!
+//! Inputs are named `x0, x1, …` and parameters `p0, p1, …`. Leaves are
+//! always inlined; an operation used more than once is bound to a temporary
+//! `t0, t1, …` with a `let` line, so shared subexpressions are written once.
+//! Parentheses are only emitted where precedence requires them.
+
+use super::multi_ad::MultiAD;
+use crate::error::Result;
+
+// Binding strength of rendered expressions, loosest first
+const SUM: u8 = 1;
+const PRODUCT: u8 = 2;
+const PREFIX: u8 = 3;
+const POWER: u8 = 4;
+const ATOM: u8 = 5;
+
+/// Output syntax of a [`Renderer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Notation {
+    /// Plain infix. Without `let` lines the formula parses back with
+    /// [`MultiAD::parse`], which numbers inputs by first appearance
+    Infix,
+    /// LaTeX math mode
+    Latex,
+}
+
+/// A rendered expression and how tightly it binds.
+struct Rendered {
+    text: String,
+    precedence: u8,
+}
+
+impl Rendered {
+    fn new(text: String, precedence: u8) -> Self {
+        Self { text, precedence }
+    }
+}
+
+/// Renders validated graphs whose inputs are declared with `Inp` nodes.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Renderer {
+    notation: Notation,
+    /// Name a lone input `x` instead of `x0`
+    single_input: bool,
+}
+
+impl Renderer {
+    pub(crate) fn new(notation: Notation) -> Self {
+        Self {
+            notation,
+            single_input: false,
+        }
+    }
+
+    /// Name input 0 `x`, for single-variable functions.
+    pub(crate) fn single_input(mut self) -> Self {
+        self.single_input = true;
+        self
+    }
+
+    fn name(&self, prefix: &str, index: usize) -> String {
+        match self.notation {
+            Notation::Infix => format!("{prefix}{index}"),
+            Notation::Latex => format!("{prefix}_{{{index}}}"),
+        }
+    }
+
+    fn input(&self, k: usize) -> String {
+        if self.single_input {
+            "x".to_string()
+        } else {
+            self.name("x", k)
+        }
+    }
+
+    fn param(&self, k: usize) -> String {
+        match self.notation {
+            Notation::Infix => self.name("p", k),
+            Notation::Latex => self.name("\\theta", k),
+        }
+    }
+
+    /// `e` as text, parenthesised if it binds looser than `min`.
+    fn operand(&self, e: &Rendered, min: u8) -> String {
+        if e.precedence >= min {
+            e.text.clone()
+        } else {
+            match self.notation {
+                Notation::Infix => format!("({})", e.text),
+                Notation::Latex => format!("\\left({}\\right)", e.text),
+            }
+        }
+    }
+
+    /// A function call such as `sin(a)` or `\sin\left(a\right)`.
+    fn call(&self, name: &str, a: &[&Rendered]) -> Rendered {
+        self.call_as(name, &format!("\\{name}"), a)
+    }
+
+    /// A function call written `latex` in LaTeX, e.g. `\arctan` for `atan`.
+    fn call_as(&self, name: &str, latex: &str, a: &[&Rendered]) -> Rendered {
+        let args: Vec<&str> = a.iter().map(|r| r.text.as_str()).collect();
+        let args = args.join(", ");
+        let text = match self.notation {
+            Notation::Infix => format!("{name}({args})"),
+            Notation::Latex => format!("{latex}\\left({args}\\right)"),
+        };
+        Rendered::new(text, ATOM)
+    }
+
+    /// Render a leaf node.
+    fn leaf(&self, op: MultiAD, args: &[usize]) -> Rendered {
+        match op {
+            MultiAD::Inp => Rendered::new(self.input(args[0]), ATOM),
+            MultiAD::Param(k) => Rendered::new(self.param(k), ATOM),
+            MultiAD::Const(c) if c.is_finite() => {
+                let precedence = if c.is_sign_negative() { PREFIX } else { ATOM };
+                Rendered::new(c.to_string(), precedence)
+            }
+            // Infix output divides by zero so the parser reads the same value back
+            MultiAD::Const(c) => match (self.notation, c.is_nan(), c > 0.0) {
+                (Notation::Infix, true, _) => Rendered::new("(0 / 0)".to_string(), ATOM),
+                (Notation::Infix, false, true) => Rendered::new("(1 / 0)".to_string(), ATOM),
+                (Notation::Infix, false, false) => Rendered::new("(-1 / 0)".to_string(), ATOM),
+                (Notation::Latex, true, _) => Rendered::new("\\mathrm{NaN}".to_string(), ATOM),
+                (Notation::Latex, false, true) => Rendered::new("\\infty".to_string(), ATOM),
+                (Notation::Latex, false, false) => Rendered::new("-\\infty".to_string(), PREFIX),
+            },
+            _ => unreachable!("{} is not a leaf", op.op_name()),
+        }
+    }
+
+    /// Render an operation applied to rendered arguments.
+    fn op(&self, op: MultiAD, a: &[&Rendered]) -> Rendered {
+        let latex = self.notation == Notation::Latex;
+        let binary = |symbol: &str, precedence: u8, lhs: u8, rhs: u8| {
+            let text = format!(
+                "{} {symbol} {}",
+                self.operand(a[0], lhs),
+                self.operand(a[1], rhs)
+            );
+            Rendered::new(text, precedence)
+        };
+        match op {
+            MultiAD::Add => binary("+", SUM, SUM, SUM),
+            MultiAD::Sub => binary("-", SUM, SUM, PRODUCT),
+            MultiAD::Mul if latex => binary("\\cdot", PRODUCT, PRODUCT, PREFIX),
+            MultiAD::Mul => binary("*", PRODUCT, PRODUCT, PREFIX),
+            MultiAD::Div if latex => {
+                Rendered::new(format!("\\frac{{{}}}{{{}}}", a[0].text, a[1].text), ATOM)
+            }
+            MultiAD::Div => binary("/", PRODUCT, PRODUCT, PREFIX),
+            MultiAD::Pow if latex => {
+                let text = format!("{}^{{{}}}", self.operand(a[0], ATOM), a[1].text);
+                Rendered::new(text, POWER)
+            }
+            MultiAD::Pow => {
+                let text = format!(
+                    "{}^{}",
+                    self.operand(a[0], ATOM),
+                    self.operand(a[1], PREFIX)
+                );
+                Rendered::new(text, POWER)
+            }
+            // Nested negation is parenthesised rather than written `--`
+            MultiAD::Neg => Rendered::new(format!("-{}", self.operand(a[0], POWER)), PREFIX),
+            MultiAD::Sqrt if latex => Rendered::new(format!("\\sqrt{{{}}}", a[0].text), ATOM),
+            MultiAD::Abs if latex => Rendered::new(format!("\\left|{}\\right|", a[0].text), ATOM),
+            MultiAD::Exp if latex => Rendered::new(format!("e^{{{}}}", a[0].text), POWER),
+            MultiAD::Sin => self.call("sin", a),
+            MultiAD::Cos => self.call("cos", a),
+            MultiAD::Tan => self.call("tan", a),
+            MultiAD::Exp => self.call("exp", a),
+            MultiAD::Ln => self.call("ln", a),
+            MultiAD::Sqrt => self.call("sqrt", a),
+            MultiAD::Abs => self.call("abs", a),
+            MultiAD::Tanh => self.call("tanh", a),
+            MultiAD::Sinh => self.call("sinh", a),
+            MultiAD::Cosh => self.call("cosh", a),
+            MultiAD::Asin => self.call_as("asin", "\\arcsin", a),
+            MultiAD::Acos => self.call_as("acos", "\\arccos", a),
+            MultiAD::Atan => self.call_as("atan", "\\arctan", a),
+            MultiAD::Atan2 => self.call_as("atan2", "\\operatorname{atan2}", a),
+            MultiAD::Log1p => self.call_as("log1p", "\\operatorname{log1p}", a),
+            MultiAD::Expm1 => self.call_as("expm1", "\\operatorname{expm1}", a),
+            MultiAD::Log2 => self.call_as("log2", "\\log_{2}", a),
+            MultiAD::Log10 => self.call_as("log10", "\\log_{10}", a),
+            MultiAD::Sigmoid => self.call_as("sigmoid", "\\sigma", a),
+            MultiAD::Softplus => self.call_as("softplus", "\\operatorname{softplus}", a),
+            MultiAD::Hypot => self.call_as("hypot", "\\operatorname{hypot}", a),
+            MultiAD::Min => self.call("min", a),
+            MultiAD::Max => self.call("max", a),
+            MultiAD::Clamp => self.call_as("clamp", "\\operatorname{clamp}", a),
+            MultiAD::Relu => self.call_as("relu", "\\operatorname{relu}", a),
+            MultiAD::Floor if latex => Rendered::new(
+                format!("\\left\\lfloor {} \\right\\rfloor", a[0].text),
+                ATOM,
+            ),
+            MultiAD::Ceil if latex => {
+                Rendered::new(format!("\\left\\lceil {} \\right\\rceil", a[0].text), ATOM)
+            }
+            MultiAD::Floor => self.call("floor", a),
+            MultiAD::Ceil => self.call("ceil", a),
+            MultiAD::Round => self.call_as("round", "\\operatorname{round}", a),
+            MultiAD::Sign => self.call_as("sign", "\\operatorname{sgn}", a),
+            MultiAD::Fmod => self.call_as("fmod", "\\operatorname{fmod}", a),
+            MultiAD::Erf => self.call_as("erf", "\\operatorname{erf}", a),
+            MultiAD::Erfc => self.call_as("erfc", "\\operatorname{erfc}", a),
+            MultiAD::Lgamma => self.call_as("lgamma", "\\ln\\Gamma", a),
+            MultiAD::Digamma => self.call_as("digamma", "\\psi", a),
+            MultiAD::Trigamma => self.call_as("trigamma", "\\psi_1", a),
+            MultiAD::NormalCdf => self.call_as("normal_cdf", "\\Phi", a),
+            MultiAD::NormalLogpdf => self.call_as("normal_logpdf", "\\ln\\phi", a),
+            MultiAD::LambertW => self.call_as("lambert_w", "W", a),
+            MultiAD::Sum => self.call_as("sum", "\\operatorname{sum}", a),
+            MultiAD::Prod => self.call_as("prod", "\\operatorname{prod}", a),
+            MultiAD::Mean => self.call_as("mean", "\\operatorname{mean}", a),
+            MultiAD::LogSumExp => self.call_as("logsumexp", "\\operatorname{logsumexp}", a),
+            MultiAD::SoftmaxSum => self.call_as("softmax_sum", "\\operatorname{softmax\\_sum}", a),
+            MultiAD::Inp | MultiAD::Const(_) | MultiAD::Param(_) => {
+                unreachable!("leaves are rendered by Renderer::leaf")
+            }
+        }
+    }
+
+    /// Render the output of a valid graph, binding shared operations first.
+    pub(crate) fn render(&self, exprs: &[(MultiAD, Vec<usize>)]) -> String {
+        let output = exprs.len() - 1;
+
+        // Count uses by the nodes the output depends on
+        let mut live = vec![false; exprs.len()];
+        let mut uses = vec![0usize; exprs.len()];
+        live[output] = true;
+        for (index, (op, args)) in exprs.iter().enumerate().rev() {
+            if live[index] && *op != MultiAD::Inp {
+                for &arg in args {
+                    live[arg] = true;
+                    uses[arg] += 1;
+                }
+            }
+        }
+
+        let mut rendered: Vec<Option<Rendered>> = Vec::with_capacity(exprs.len());
+        let mut bindings = Vec::new();
+        for (index, (op, args)) in exprs.iter().enumerate() {
+            if !live[index] {
+                rendered.push(None);
+                continue;
+            }
+            let is_leaf = *op == MultiAD::Inp || args.is_empty();
+            let mut node = if is_leaf {
+                self.leaf(*op, args)
+            } else {
+                // Live nodes only refer to live nodes
+                let a: Vec<&Rendered> = args.iter().filter_map(|&i| rendered[i].as_ref()).collect();
+                self.op(*op, &a)
+            };
+            if !is_leaf && uses[index] > 1 && index != output {
+                let name = self.name("t", bindings.len());
+                bindings.push((name.clone(), node.text));
+                node = Rendered::new(name, ATOM);
+            }
+            rendered.push(Some(node));
+        }
+
+        let result = rendered[output].take().map(|r| r.text).unwrap_or_default();
+        if bindings.is_empty() {
+            return result;
+        }
+        match self.notation {
+            Notation::Infix => {
+                let mut text = String::new();
+                for (name, value) in bindings {
+                    text.push_str(&format!("let {name} = {value};\n"));
+                }
+                text + &result
+            }
+            Notation::Latex => {
+                let mut text = String::from("\\begin{aligned}\n");
+                for (name, value) in bindings {
+                    text.push_str(&format!("{name} &= {value} \\\\\n"));
+                }
+                text + "f &= " + &result + "\n\\end{aligned}"
+            }
+        }
+    }
+}
+
+impl MultiAD {
+    /// Render a graph as an infix formula.
+    ///
+    /// Inputs are written `x0, x1, …` and parameters `p0, p1, …`. Operations
+    /// used more than once are bound to temporaries `t0, t1, …` on `let`
+    /// lines before the final expression. Nodes the output does not depend
+    /// on are left out. The graph takes `num_inputs` inputs, laid out as for
+    /// [`MultiAD::validate`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(AutodiffError)` if the graph is malformed; see
+    /// [`MultiAD::validate`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petite_ad::{MultiAD, multi_ops};
+    ///
+    /// let exprs = multi_ops![(inp, 0), (inp, 1), (add, 0, 1), (sin, 0), (mul, 2, 3)];
+    /// assert_eq!(MultiAD::to_infix(&exprs, 2).unwrap(), "(x0 + x1) * sin(x0)");
+    ///
+    /// // sin(x0) is shared
+    /// let exprs = multi_ops![(inp, 0), (sin, 0), (mul, 1, 1), (add, 2, 1)];
+    /// assert_eq!(MultiAD::to_infix(&exprs, 1).unwrap(), "let t0 = sin(x0);\nt0 * t0 + t0");
+    /// ```
+    pub fn to_infix(exprs: &[(MultiAD, Vec<usize>)], num_inputs: usize) -> Result<String> {
+        Self::validate(exprs, num_inputs)?;
+        Ok(Renderer::new(Notation::Infix).render(&Self::with_input_nodes(exprs, num_inputs)))
+    }
+
+    /// Render a graph as a LaTeX formula for math mode.
+    ///
+    /// Inputs are written `x_{0}, …`, parameters `\theta_{0}, …` and shared
+    /// temporaries `t_{0}, …`. With shared subexpressions the result is an
+    /// `aligned` block whose last line is `f &= …`. The graph takes
+    /// `num_inputs` inputs, as in [`MultiAD::to_infix`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(AutodiffError)` if the graph is malformed; see
+    /// [`MultiAD::validate`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petite_ad::{MultiAD, multi_ops};
+    ///
+    /// let exprs = multi_ops![(inp, 0), (inp, 1), (div, 0, 1), (sqrt, 2)];
+    /// assert_eq!(MultiAD::to_latex(&exprs, 2).unwrap(), "\\sqrt{\\frac{x_{0}}{x_{1}}}");
+    /// ```
+    pub fn to_latex(exprs: &[(MultiAD, Vec<usize>)], num_inputs: usize) -> Result<String> {
+        Self::validate(exprs, num_inputs)?;
+        Ok(Renderer::new(Notation::Latex).render(&Self::with_input_nodes(exprs, num_inputs)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::AutodiffError;
+    use crate::multi_ops;
+    use crate::test_utils::approx_eq_eps as approx_eq;
+
+    fn infix(src: &str) -> String {
+        let (graph, names) = MultiAD::parse(src).unwrap();
+        MultiAD::to_infix(&graph, names.len()).unwrap()
+    }
+
+    fn latex(src: &str) -> String {
+        let (graph, names) = MultiAD::parse(src).unwrap();
+        MultiAD::to_latex(&graph, names.len()).unwrap()
+    }
+
+    #[test]
+    fn test_infix_precedence() {
+        assert_eq!(infix("a - (b - c)"), "x0 - (x1 - x2)");
+        assert_eq!(infix("(a - b) - c"), "x0 - x1 - x2");
+        assert_eq!(infix("a / (b * c)"), "x0 / (x1 * x2)");
+        assert_eq!(infix("-(a + b) * c"), "-(x0 + x1) * x2");
+        assert_eq!(infix("(a ^ b) ^ c"), "(x0^x1)^x2");
+        assert_eq!(infix("a ^ b ^ c"), "x0^x1^x2");
+        assert_eq!(infix("-a ^ 2"), "-x0^2");
+        assert_eq!(infix("2.5 * exp(-a)"), "2.5 * exp(-x0)");
+    }
+
+    #[test]
+    fn test_infix_round_trips_through_parser() {
+        let (graph, _) =
+            MultiAD::parse("x^y / tan(x) + ln(y) * sqrt(x) - abs(x - y) + cos(-exp(y))").unwrap();
+        let text = MultiAD::to_infix(&graph, 2).unwrap();
+        let (reparsed, names) = MultiAD::parse(&text).unwrap();
+        assert_eq!(names, ["x0", "x1"]);
+        let inputs = [1.3, 0.7];
+        assert!(approx_eq(
+            MultiAD::compute(&graph, &inputs).unwrap(),
+            MultiAD::compute(&reparsed, &inputs).unwrap(),
+            1e-12
+        ));
+    }
+
+    #[test]
+    fn test_extended_ops_round_trip_through_parser() {
+        let src = "tanh(x) * sinh(y) / cosh(x) + acos(y) - atan(x) + atan2(y, x) * hypot(x, y) \
+                   + log1p(x) + expm1(y) + log2(x) - sigmoid(y) * softplus(x)";
+        let (graph, _) = MultiAD::parse(src).unwrap();
+        let text = MultiAD::to_infix(&graph, 2).unwrap();
+        let (reparsed, _) = MultiAD::parse(&text).unwrap();
+        let inputs = [1.3, 0.7];
+        assert!(approx_eq(
+            MultiAD::compute(&graph, &inputs).unwrap(),
+            MultiAD::compute(&reparsed, &inputs).unwrap(),
+            1e-12
+        ));
+    }
+
+    #[test]
+    fn test_special_ops_render() {
+        let src = "erf(x) - erfc(y) + lgamma(x) * digamma(y) + trigamma(x) + normal_cdf(x) \
+                   + normal_logpdf(y) + lambert_w(x)";
+        let (graph, _) = MultiAD::parse(src).unwrap();
+        let text = MultiAD::to_infix(&graph, 2).unwrap();
+        let (reparsed, _) = MultiAD::parse(&text).unwrap();
+        let inputs = [1.3, 0.7];
+        assert!(approx_eq(
+            MultiAD::compute(&graph, &inputs).unwrap(),
+            MultiAD::compute(&reparsed, &inputs).unwrap(),
+            1e-12
+        ));
+        assert_eq!(
+            latex("lgamma(x) + normal_cdf(x)"),
+            "\\ln\\Gamma\\left(x_{0}\\right) + \\Phi\\left(x_{0}\\right)"
+        );
+    }
+
+    #[test]
+    fn test_nary_ops_render() {
+        let (graph, _) = MultiAD::parse(
+            "sum(x, y, 2) * prod(x) + mean(x, y) - logsumexp(x, y) / softmax_sum(y, x)",
+        )
+        .unwrap();
+        let text = MultiAD::to_infix(&graph, 2).unwrap();
+        assert_eq!(
+            text,
+            "sum(x0, x1, 2) * prod(x0) + mean(x0, x1) - logsumexp(x0, x1) / softmax_sum(x1, x0)"
+        );
+        let (reparsed, _) = MultiAD::parse(&text).unwrap();
+        assert_eq!(reparsed, graph);
+    }
+
+    #[test]
+    fn test_shared_subexpressions() {
+        // Dead nodes are skipped and leaves are never bound
+        let exprs = multi_ops![
+            (inp, 0),
+            (inp, 1),
+            (cos, 1),
+            (add, 0, 1),
+            (exp, 3),
+            (mul, 4, 3),
+            (mul, 5, 0)
+        ];
+        assert_eq!(
+            MultiAD::to_infix(&exprs, 2).unwrap(),
+            "let t0 = x0 + x1;\nexp(t0) * t0 * x0"
+        );
+        assert_eq!(
+            MultiAD::to_latex(&exprs, 2).unwrap(),
+            "\\begin{aligned}\nt_{0} &= x_{0} + x_{1} \\\\\nf &= e^{t_{0}} \\cdot t_{0} \\cdot x_{0}\n\\end{aligned}"
+        );
+    }
+
+    #[test]
+    fn test_latex() {
+        assert_eq!(
+            latex("sin(x) * (x + y)"),
+            "\\sin\\left(x_{0}\\right) \\cdot \\left(x_{0} + x_{1}\\right)"
+        );
+        assert_eq!(
+            latex("(x + 1) ^ abs(y)"),
+            "\\left(x_{0} + 1\\right)^{\\left|x_{1}\\right|}"
+        );
+        assert_eq!(latex("-(x / y)"), "-\\frac{x_{0}}{x_{1}}");
+        assert_eq!(
+            latex("atan2(y, x) + log10(asin(x))"),
+            "\\operatorname{atan2}\\left(x_{0}, x_{1}\\right) + \\log_{10}\\left(\\arcsin\\left(x_{1}\\right)\\right)"
+        );
+
+        let exprs = multi_ops![(inp, 0), (param, 0), (mul, 1, 0)];
+        assert_eq!(
+            MultiAD::to_latex(&exprs, 1).unwrap(),
+            "\\theta_{0} \\cdot x_{0}"
+        );
+        assert_eq!(MultiAD::to_infix(&exprs, 1).unwrap(), "p0 * x0");
+    }
+
+    #[test]
+    fn test_implicit_inputs() {
+        // x0 * (x1 - x0) with the inputs at indices 0 and 1
+        let exprs = multi_ops![(sub, 1, 0), (mul, 0, 2)];
+        assert_eq!(MultiAD::to_infix(&exprs, 2).unwrap(), "x0 * (x1 - x0)");
+        assert_eq!(
+            MultiAD::to_latex(&exprs, 2).unwrap(),
+            "x_{0} \\cdot \\left(x_{1} - x_{0}\\right)"
+        );
+        assert_eq!(
+            MultiAD::to_infix(&multi_ops![(const, 2.0)], 0).unwrap(),
+            "2"
+        );
+
+        // x * z reads input 2 past a dead sin(x) node
+        let exprs = multi_ops![(sin, 0), (mul, 0, 2)];
+        assert_eq!(MultiAD::to_infix(&exprs, 3).unwrap(), "x0 * x2");
+        assert_eq!(MultiAD::to_latex(&exprs, 3).unwrap(), "x_{0} \\cdot x_{2}");
+    }
+
+    #[test]
+    fn test_nested_negation() {
+        let exprs = multi_ops![(inp, 0), (neg, 0), (neg, 1), (const, -2.0), (neg, 3), (mul, 2, 4)];
+        let text = MultiAD::to_infix(&exprs, 1).unwrap();
+        assert_eq!(text, "-(-x0) * -(-2)");
+        let (reparsed, _) = MultiAD::parse(&text).unwrap();
+        assert_eq!(MultiAD::compute(&reparsed, &[3.0]).unwrap(), 6.0);
+    }
+
+    #[test]
+    fn test_non_finite_constants() {
+        let exprs = multi_ops![
+            (inp, 0),
+            (const, f64::INFINITY),
+            (const, f64::NEG_INFINITY),
+            (const, f64::NAN),
+            (mul, 0, 1),
+            (add, 4, 2),
+            (sub, 5, 3)
+        ];
+        let text = MultiAD::to_infix(&exprs, 1).unwrap();
+        assert_eq!(text, "x0 * (1 / 0) + (-1 / 0) - (0 / 0)");
+        let (reparsed, names) = MultiAD::parse(&text).unwrap();
+        assert_eq!(names, ["x0"]);
+        assert!(MultiAD::compute(&reparsed, &[2.0]).unwrap().is_nan());
+
+        let round_trip = |c| {
+            let text = MultiAD::to_infix(&multi_ops![(const, c)], 0).unwrap();
+            MultiAD::compute(&MultiAD::parse(&text).unwrap().0, &[]).unwrap()
+        };
+        assert_eq!(round_trip(f64::INFINITY), f64::INFINITY);
+        assert_eq!(round_trip(f64::NEG_INFINITY), f64::NEG_INFINITY);
+        assert!(round_trip(f64::NAN).is_nan());
+
+        assert_eq!(
+            MultiAD::to_latex(&exprs, 1).unwrap(),
+            "x_{0} \\cdot \\infty + -\\infty - \\mathrm{NaN}"
+        );
+    }
+
+    #[test]
+    fn test_render_errors() {
+        assert_eq!(MultiAD::to_infix(&[], 0), Err(AutodiffError::EmptyGraph));
+        assert_eq!(
+            MultiAD::to_latex(&multi_ops![(inp, 0), (sin, 1)], 1),
+            Err(AutodiffError::ForwardReference { node: 1, index: 1 })
+        );
+    }
+}
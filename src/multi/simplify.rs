@@ -0,0 +1,500 @@
+//! Rule-based algebraic simplification.
+//!
+//! A [`Simplifier`] sweeps a graph front to back and offers every node to its
+//! [`RewriteRule`]s, which may replace it with an existing node, a constant,
+//! or a cheaper operation. Between sweeps the graph is passed through
+//! [`MultiAD::optimize`], so rewrites that expose constants, duplicates or
+//! dead nodes are cleaned up, and sweeping stops once no rule fires.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use super::multi_ad::MultiAD;
+use crate::error::{AutodiffError, Result};
+
+/// Upper bound on rewrites applied to a single node within one sweep.
+const MAX_REWRITES_PER_NODE: usize = 32;
+
+/// Upper bound on sweeps over the whole graph.
+const MAX_SWEEPS: usize = 16;
+
+/// Replacement for a node proposed by a [`RewriteRule`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rewrite {
+    /// Use an existing node instead
+    Node(usize),
+    /// Use a constant instead
+    Const(f64),
+    /// Use another operation on existing nodes instead
+    Op(MultiAD, Vec<usize>),
+}
+
+/// Read-only view of the nodes already emitted, handed to every rule.
+///
+/// Indices are node indices of the graph being built; all arguments of the
+/// node being rewritten are valid indices.
+#[derive(Debug, Clone, Copy)]
+pub struct RewriteContext<'g> {
+    nodes: &'g [(MultiAD, Vec<usize>)],
+}
+
+impl<'g> RewriteContext<'g> {
+    /// Operation of node `index`.
+    pub fn op(&self, index: usize) -> MultiAD {
+        self.nodes[index].0
+    }
+
+    /// Arguments of node `index`; for an `Inp` node this is its input number.
+    pub fn args(&self, index: usize) -> &'g [usize] {
+        &self.nodes[index].1
+    }
+
+    /// Value of node `index` if it is a `Const` node.
+    pub fn constant(&self, index: usize) -> Option<f64> {
+        match self.op(index) {
+            MultiAD::Const(c) => Some(c),
+            _ => None,
+        }
+    }
+
+    /// Whether node `index` is the constant `value`.
+    pub fn is_constant(&self, index: usize, value: f64) -> bool {
+        self.constant(index) == Some(value)
+    }
+
+    /// Argument of node `index` if it is the unary operation `op`.
+    pub fn unary_arg(&self, index: usize, op: MultiAD) -> Option<usize> {
+        (self.op(index) == op).then(|| self.args(index)[0])
+    }
+}
+
+/// An algebraic identity applied by a [`Simplifier`].
+///
+/// `rewrite` sees an operation and its argument indices and returns a
+/// replacement, or `None` to leave the node alone. It is only called for
+/// nodes with arguments, never for `Inp`, `Const` or `Param` nodes.
+/// Any `Fn(&RewriteContext, MultiAD, &[usize]) -> Option<Rewrite>` closure
+/// is a rule.
+///
+/// # Examples
+///
+/// ```
+/// use petite_ad::{MultiAD, Rewrite, RewriteContext, Simplifier};
+///
+/// // abs(abs(x)) → abs(x)
+/// let abs_abs = |cx: &RewriteContext<'_>, op: MultiAD, args: &[usize]| {
+///     let inner = cx.unary_arg(args[0], MultiAD::Abs)?;
+///     (op == MultiAD::Abs).then(|| Rewrite::Op(MultiAD::Abs, vec![inner]))
+/// };
+/// let mut simplifier = Simplifier::new();
+/// simplifier.add_rule(abs_abs);
+///
+/// let (graph, _) = MultiAD::parse("abs(abs(x))").unwrap();
+/// assert_eq!(simplifier.simplify(&graph, 1).unwrap().len(), 2);
+/// ```
+pub trait RewriteRule {
+    /// Propose a replacement for `op(args)`.
+    fn rewrite(&self, cx: &RewriteContext<'_>, op: MultiAD, args: &[usize]) -> Option<Rewrite>;
+}
+
+impl<F> RewriteRule for F
+where
+    F: Fn(&RewriteContext<'_>, MultiAD, &[usize]) -> Option<Rewrite>,
+{
+    fn rewrite(&self, cx: &RewriteContext<'_>, op: MultiAD, args: &[usize]) -> Option<Rewrite> {
+        self(cx, op, args)
+    }
+}
+
+/// Applies a list of [`RewriteRule`]s to graphs.
+///
+/// [`Simplifier::new`] starts with the default rules, tried in this order:
+/// - identities: `x + 0`, `x - 0`, `x * 1`, `x / 1`, `x ^ 1` → `x`;
+///   `0 - x`, `x * -1` → `-x`; `--x` → `x`; `x ^ 0` → `1`
+/// - inverses: `ln(exp(x))` → `x`, and `exp(ln(x))` → `x`, which is only
+///   equal for `x > 0`; elsewhere the original is NaN
+/// - cancellation: `x - x` → `0` and `x / x` → `1`, which differ from the
+///   original when `x` is infinite or NaN, or is zero for `x / x`
+/// - trigonometry: `sin²(x) + cos²(x)` → `1`
+/// - strength reduction: `x ^ 2` → `x * x`
+///
+/// Identities such as `x * 0` → `0` are deliberately absent, since
+/// `inf * 0` is NaN.
+pub struct Simplifier {
+    rules: Vec<Box<dyn RewriteRule>>,
+}
+
+impl fmt::Debug for Simplifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Simplifier")
+            .field("rules", &self.rules.len())
+            .finish()
+    }
+}
+
+impl Default for Simplifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Simplifier {
+    /// A simplifier with the default rules.
+    pub fn new() -> Self {
+        let mut simplifier = Self::without_rules();
+        simplifier
+            .add_rule(identities)
+            .add_rule(inverses)
+            .add_rule(cancellation)
+            .add_rule(pythagorean)
+            .add_rule(strength_reduction);
+        simplifier
+    }
+
+    /// A simplifier with no rules, which only runs [`MultiAD::optimize`].
+    pub fn without_rules() -> Self {
+        Self { rules: Vec::new() }
+    }
+
+    /// Append a rule; rules are tried in the order they were added.
+    pub fn add_rule(&mut self, rule: impl RewriteRule + 'static) -> &mut Self {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    /// Simplify a graph, returning the rewritten graph.
+    ///
+    /// The graph takes `num_inputs` inputs, laid out as for
+    /// [`MultiAD::validate`]. The result starts with one `Inp` node per
+    /// input, like the output of [`MultiAD::optimize`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(AutodiffError)` if the graph is malformed (see
+    /// [`MultiAD::validate`]), or if a rule refers to a node that does not
+    /// exist yet or builds an operation with the wrong arity.
+    pub fn simplify(
+        &self,
+        exprs: &[(MultiAD, Vec<usize>)],
+        num_inputs: usize,
+    ) -> Result<Vec<(MultiAD, Vec<usize>)>> {
+        let mut graph = exprs.to_vec();
+        MultiAD::optimize(&mut graph, num_inputs)?;
+        for _ in 0..MAX_SWEEPS {
+            let changed = self.sweep(&mut graph, num_inputs)?;
+            MultiAD::optimize(&mut graph, num_inputs)?;
+            if !changed {
+                break;
+            }
+        }
+        Ok(graph)
+    }
+
+    /// Rewrite every node once, merging nodes that become identical.
+    /// Returns whether any rule fired.
+    fn sweep(&self, exprs: &mut Vec<(MultiAD, Vec<usize>)>, num_inputs: usize) -> Result<bool> {
+        let mut nodes: Vec<(MultiAD, Vec<usize>)> = Vec::with_capacity(exprs.len());
+        let mut canonical = Vec::with_capacity(exprs.len());
+        let mut seen: HashMap<(MultiAD, Vec<usize>), usize> = HashMap::new();
+        let mut changed = false;
+
+        for (op, args) in exprs.iter() {
+            let mut node = match op {
+                MultiAD::Inp => (*op, args.clone()),
+                _ => (*op, args.iter().map(|&arg| canonical[arg]).collect()),
+            };
+            let mut target = None;
+
+            for _ in 0..MAX_REWRITES_PER_NODE {
+                if node.0 == MultiAD::Inp || node.1.is_empty() {
+                    break;
+                }
+                let cx = RewriteContext { nodes: &nodes };
+                let Some(rewrite) = self
+                    .rules
+                    .iter()
+                    .find_map(|r| r.rewrite(&cx, node.0, &node.1))
+                else {
+                    break;
+                };
+                changed = true;
+                match rewrite {
+                    Rewrite::Node(index) => {
+                        check_index(index, nodes.len())?;
+                        target = Some(index);
+                        break;
+                    }
+                    Rewrite::Const(value) => node = (MultiAD::Const(value), Vec::new()),
+                    Rewrite::Op(op, args) => {
+                        AutodiffError::check_arity(op.op_name(), op.expected_arity(), args.len())?;
+                        for &arg in &args {
+                            check_index(arg, nodes.len())?;
+                        }
+                        node = (op, args);
+                    }
+                }
+            }
+
+            let index = match target {
+                Some(index) => index,
+                None => *seen.entry(node).or_insert_with_key(|node| {
+                    nodes.push(node.clone());
+                    nodes.len() - 1
+                }),
+            };
+            canonical.push(index);
+        }
+
+        let output = canonical[canonical.len() - 1];
+        *exprs = MultiAD::prune(&nodes, output, num_inputs);
+        Ok(changed)
+    }
+}
+
+/// Rule results may only refer to nodes emitted before the one being rewritten.
+fn check_index(index: usize, num_nodes: usize) -> Result<()> {
+    if index >= num_nodes {
+        return Err(AutodiffError::IndexOutOfBounds {
+            index,
+            max_index: num_nodes.saturating_sub(1),
+        });
+    }
+    Ok(())
+}
+
+/// Neutral elements and double negation.
+fn identities(cx: &RewriteContext<'_>, op: MultiAD, args: &[usize]) -> Option<Rewrite> {
+    match op {
+        MultiAD::Add if cx.is_constant(args[0], 0.0) => Some(Rewrite::Node(args[1])),
+        MultiAD::Add | MultiAD::Sub if cx.is_constant(args[1], 0.0) => Some(Rewrite::Node(args[0])),
+        MultiAD::Sub if cx.is_constant(args[0], 0.0) => {
+            Some(Rewrite::Op(MultiAD::Neg, vec![args[1]]))
+        }
+        MultiAD::Mul => {
+            let (a, b) = (args[0], args[1]);
+            for (c, x) in [(a, b), (b, a)] {
+                if cx.is_constant(c, 1.0) {
+                    return Some(Rewrite::Node(x));
+                }
+                if cx.is_constant(c, -1.0) {
+                    return Some(Rewrite::Op(MultiAD::Neg, vec![x]));
+                }
+            }
+            None
+        }
+        MultiAD::Div | MultiAD::Pow if cx.is_constant(args[1], 1.0) => Some(Rewrite::Node(args[0])),
+        MultiAD::Pow if cx.is_constant(args[1], 0.0) => Some(Rewrite::Const(1.0)),
+        MultiAD::Neg => cx.unary_arg(args[0], MultiAD::Neg).map(Rewrite::Node),
+        _ => None,
+    }
+}
+
+/// `ln(exp(x))` and `exp(ln(x))`.
+fn inverses(cx: &RewriteContext<'_>, op: MultiAD, args: &[usize]) -> Option<Rewrite> {
+    match op {
+        MultiAD::Ln => cx.unary_arg(args[0], MultiAD::Exp).map(Rewrite::Node),
+        MultiAD::Exp => cx.unary_arg(args[0], MultiAD::Ln).map(Rewrite::Node),
+        _ => None,
+    }
+}
+
+/// `x - x` and `x / x`.
+fn cancellation(_cx: &RewriteContext<'_>, op: MultiAD, args: &[usize]) -> Option<Rewrite> {
+    match op {
+        MultiAD::Sub if args[0] == args[1] => Some(Rewrite::Const(0.0)),
+        MultiAD::Div if args[0] == args[1] => Some(Rewrite::Const(1.0)),
+        _ => None,
+    }
+}
+
+/// `sin²(x) + cos²(x)` in either order, with squares written as `s * s` or `s ^ 2`.
+fn pythagorean(cx: &RewriteContext<'_>, op: MultiAD, args: &[usize]) -> Option<Rewrite> {
+    if op != MultiAD::Add {
+        return None;
+    }
+    let squared = |index: usize| match (cx.op(index), cx.args(index)) {
+        (MultiAD::Mul, &[a, b]) if a == b => Some(a),
+        (MultiAD::Pow, &[a, b]) if cx.is_constant(b, 2.0) => Some(a),
+        _ => None,
+    };
+    let (a, b) = (squared(args[0])?, squared(args[1])?);
+    let sin_cos = |s: usize, c: usize| {
+        matches!(
+            (cx.unary_arg(s, MultiAD::Sin), cx.unary_arg(c, MultiAD::Cos)),
+            (Some(x), Some(y)) if x == y
+        )
+    };
+    (sin_cos(a, b) || sin_cos(b, a)).then_some(Rewrite::Const(1.0))
+}
+
+/// `x ^ 2` → `x * x`.
+fn strength_reduction(cx: &RewriteContext<'_>, op: MultiAD, args: &[usize]) -> Option<Rewrite> {
+    (op == MultiAD::Pow && cx.is_constant(args[1], 2.0))
+        .then(|| Rewrite::Op(MultiAD::Mul, vec![args[0], args[0]]))
+}
+
+impl MultiAD {
+    /// Simplify a graph with the default rules of [`Simplifier`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(AutodiffError)` if the graph is malformed; see
+    /// [`MultiAD::validate`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petite_ad::{MultiAD, multi_ops};
+    ///
+    /// // (x * 1 + 0) ^ 2  →  x * x
+    /// let (graph, _) = MultiAD::parse("(x * 1 + 0) ^ 2").unwrap();
+    /// let simplified = MultiAD::simplify(&graph, 1).unwrap();
+    /// assert_eq!(simplified, multi_ops![(inp, 0), (mul, 0, 0)]);
+    /// ```
+    pub fn simplify(
+        exprs: &[(MultiAD, Vec<usize>)],
+        num_inputs: usize,
+    ) -> Result<Vec<(MultiAD, Vec<usize>)>> {
+        Simplifier::new().simplify(exprs, num_inputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::multi_ops;
+    use crate::test_utils::approx_eq_eps as approx_eq;
+
+    fn simplified(src: &str) -> Vec<(MultiAD, Vec<usize>)> {
+        let (graph, names) = MultiAD::parse(src).unwrap();
+        MultiAD::simplify(&graph, names.len()).unwrap()
+    }
+
+    #[test]
+    fn test_default_rules() {
+        let x = multi_ops![(inp, 0)];
+        for src in [
+            "x * 1", "1 * x", "x + 0", "0 + x", "x - 0", "x / 1", "x ^ 1", "--x",
+        ] {
+            assert_eq!(simplified(src), x, "{src}");
+        }
+        assert_eq!(simplified("exp(ln(x))"), x);
+        assert_eq!(simplified("ln(exp(x))"), x);
+        assert_eq!(simplified("0 - x"), multi_ops![(inp, 0), (neg, 0)]);
+        assert_eq!(simplified("x * -1"), multi_ops![(inp, 0), (neg, 0)]);
+        assert_eq!(simplified("x ^ 2"), multi_ops![(inp, 0), (mul, 0, 0)]);
+        assert_eq!(simplified("x ^ 0"), multi_ops![(inp, 0), (const, 1.0)]);
+    }
+
+    #[test]
+    fn test_cancellation_and_pythagorean() {
+        assert_eq!(
+            simplified("sin(x) - sin(x)"),
+            multi_ops![(inp, 0), (const, 0.0)]
+        );
+        assert_eq!(simplified("y / y"), multi_ops![(inp, 0), (const, 1.0)]);
+        for src in [
+            "sin(x)^2 + cos(x)^2",
+            "cos(x) * cos(x) + sin(x) ^ 2",
+            "sin(x + y)^2 + cos(y + x)^2",
+        ] {
+            let graph = simplified(src);
+            assert_eq!(graph.last(), Some(&(MultiAD::Const(1.0), vec![])), "{src}");
+        }
+        // Different arguments do not cancel
+        assert!(simplified("sin(x)^2 + cos(y)^2").len() > 3);
+    }
+
+    #[test]
+    fn test_implicit_inputs() {
+        // x * 1 + (y - y) with x and y at indices 0 and 1
+        let exprs = multi_ops![(const, 1.0), (mul, 0, 2), (sub, 1, 1), (add, 3, 4)];
+        assert_eq!(
+            MultiAD::simplify(&exprs, 2).unwrap(),
+            multi_ops![(inp, 0), (inp, 1), (inp, 0)]
+        );
+
+        // x * z reads input 2 past a dead sin(x) node
+        let exprs = multi_ops![(sin, 0), (mul, 0, 2)];
+        assert_eq!(
+            MultiAD::simplify(&exprs, 3).unwrap(),
+            multi_ops![(inp, 0), (inp, 1), (inp, 2), (mul, 0, 2)]
+        );
+    }
+
+    #[test]
+    fn test_simplify_derivative_graphs() {
+        let (graph, _) = MultiAD::parse("x * y * exp(x) + sin(x)^2 + cos(x)^2").unwrap();
+        for wrt in 0..2 {
+            let derivative = MultiAD::derivative_graph(&graph, 2, wrt).unwrap();
+            let simple = MultiAD::simplify(&derivative, 2).unwrap();
+            assert!(simple.len() < derivative.len());
+            for inputs in [[0.3, 1.7], [1.1, -0.6]] {
+                let (v0, g0) = MultiAD::compute_grad(&derivative, &inputs).unwrap();
+                let (v1, g1) = MultiAD::compute_grad(&simple, &inputs).unwrap();
+                assert!(approx_eq(v0, v1, 1e-12));
+                for (a, b) in g0(1.0).iter().zip(g1(1.0)) {
+                    assert!(approx_eq(*a, b, 1e-12));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_custom_rules() {
+        // sqrt(x * x) → abs(x)
+        let sqrt_square = |cx: &RewriteContext<'_>, op: MultiAD, args: &[usize]| match (
+            op,
+            cx.op(args[0]),
+            cx.args(args[0]),
+        ) {
+            (MultiAD::Sqrt, MultiAD::Mul, &[a, b]) if a == b => {
+                Some(Rewrite::Op(MultiAD::Abs, vec![a]))
+            }
+            _ => None,
+        };
+        let (graph, _) = MultiAD::parse("sqrt(x ^ 2)").unwrap();
+        assert_eq!(MultiAD::simplify(&graph, 1).unwrap().len(), 3);
+
+        let mut simplifier = Simplifier::new();
+        simplifier.add_rule(sqrt_square);
+        assert_eq!(
+            simplifier.simplify(&graph, 1).unwrap(),
+            multi_ops![(inp, 0), (abs, 0)]
+        );
+
+        // Without rules only the optimisation passes run
+        let (graph, _) = MultiAD::parse("x * 1").unwrap();
+        assert_eq!(
+            Simplifier::without_rules()
+                .simplify(&graph, 1)
+                .unwrap()
+                .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        let mut simplifier = Simplifier::without_rules();
+        simplifier
+            .add_rule(|_: &RewriteContext<'_>, _: MultiAD, _: &[usize]| Some(Rewrite::Node(7)));
+        let (graph, _) = MultiAD::parse("sin(x)").unwrap();
+        assert_eq!(
+            simplifier.simplify(&graph, 1),
+            Err(AutodiffError::IndexOutOfBounds {
+                index: 7,
+                max_index: 0
+            })
+        );
+
+        let mut simplifier = Simplifier::without_rules();
+        simplifier.add_rule(|_: &RewriteContext<'_>, _: MultiAD, args: &[usize]| {
+            Some(Rewrite::Op(MultiAD::Add, args.to_vec()))
+        });
+        assert_eq!(
+            simplifier.simplify(&graph, 1),
+            Err(AutodiffError::arity("Add", 2, 1))
+        );
+    }
+}
@@ -0,0 +1,331 @@
+//! Derivatives of non-smooth ops where they are not differentiable.
+//!
+//! `Abs`, `Relu`, `Min`, `Max`, `Clamp` and `Fmod` have kinks or jumps where
+//! the derivative from the left differs from the derivative from the right,
+//! and `Floor`, `Ceil`, `Round` and `Sign` are flat wherever they are
+//! differentiable. A [`SubgradientPolicy`] decides which value the reverse
+//! pass uses in those places, the same way for every op.
+
+use super::multi_ad::MultiAD;
+use super::params::Wrt;
+use super::tape::FlatTape;
+use super::types::*;
+use crate::error::Result;
+
+/// Which derivative non-smooth ops report where they are not differentiable.
+///
+/// At a kink each argument has a left derivative (moving the argument down)
+/// and a right derivative (moving it up); the policy picks one of them, their
+/// average, or zero. Away from kinks every policy gives the ordinary
+/// derivative, except that [`SubgradientPolicy::StraightThrough`] also
+/// replaces the zero derivative of step functions.
+///
+/// # Examples
+///
+/// ```
+/// use petite_ad::{CompiledGraph, SubgradientPolicy, multi_ops};
+///
+/// let exprs = multi_ops![(inp, 0), (abs, 0)];
+/// let mut grad = [0.0];
+/// for (policy, expected) in [
+///     (SubgradientPolicy::Zero, 0.0),
+///     (SubgradientPolicy::Left, -1.0),
+///     (SubgradientPolicy::Right, 1.0),
+///     (SubgradientPolicy::Average, 0.0),
+/// ] {
+///     let graph = CompiledGraph::new(&exprs, 1).unwrap().with_subgradient_policy(policy);
+///     graph.value_and_grad(&[0.0], &mut graph.workspace(), &mut grad).unwrap();
+///     assert_eq!(grad[0], expected);
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SubgradientPolicy {
+    /// Zero at kinks, so `abs'(0) = relu'(0) = 0`
+    #[default]
+    Zero,
+    /// The derivative from the left
+    Left,
+    /// The derivative from the right
+    Right,
+    /// The mean of the left and right derivatives
+    Average,
+    /// Treat the op as the identity on its first argument: step functions
+    /// (`Floor`, `Ceil`, `Round`, `Sign`) get derivative 1 everywhere, and at
+    /// a kink the first argument gets 1 and the others 0
+    StraightThrough,
+}
+
+impl SubgradientPolicy {
+    /// Derivative at a kink with one-sided derivatives `left` and `right`;
+    /// `through` is the straight-through value for this argument.
+    pub(crate) fn kink(self, left: f64, right: f64, through: f64) -> f64 {
+        match self {
+            SubgradientPolicy::Zero => 0.0,
+            SubgradientPolicy::Left => left,
+            SubgradientPolicy::Right => right,
+            SubgradientPolicy::Average => 0.5 * (left + right),
+            SubgradientPolicy::StraightThrough => through,
+        }
+    }
+
+    /// Derivative of a step function, which is zero wherever it exists.
+    pub(crate) fn step(self) -> f64 {
+        match self {
+            SubgradientPolicy::StraightThrough => 1.0,
+            _ => 0.0,
+        }
+    }
+}
+
+impl MultiAD {
+    /// Like [`MultiAD::compute_grad`], with the derivatives of non-smooth ops
+    /// at their kinks chosen by `policy`.
+    ///
+    /// [`MultiAD::compute_grad`] and the other entry points use
+    /// [`SubgradientPolicy::default`];
+    /// [`MultiAD::compute_grad_with_params_and_policy`] takes a policy for
+    /// graphs with parameters and [`CompiledGraph`](crate::CompiledGraph)
+    /// through `with_subgradient_policy`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(AutodiffError)` if the graph is malformed; see
+    /// [`MultiAD::validate`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petite_ad::{MultiAD, SubgradientPolicy, multi_ops};
+    ///
+    /// // round has zero derivative; straight-through passes gradients on
+    /// let exprs = multi_ops![(inp, 0), (round, 0), (mul, 1, 0)];
+    /// let (_, grad_fn) = MultiAD::compute_grad(&exprs, &[1.2]).unwrap();
+    /// assert_eq!(grad_fn(1.0), vec![1.0]);
+    /// let (_, grad_fn) =
+    ///     MultiAD::compute_grad_with_policy(&exprs, &[1.2], SubgradientPolicy::StraightThrough)
+    ///         .unwrap();
+    /// assert_eq!(grad_fn(1.0), vec![2.2]);
+    /// ```
+    #[must_use = "gradient computation is expensive; discarding the result is likely a bug"]
+    pub fn compute_grad_with_policy(
+        exprs: &[(MultiAD, Vec<usize>)],
+        inputs: &[f64],
+        policy: SubgradientPolicy,
+    ) -> Result<BackwardResultBox> {
+        let tape = FlatTape::record(exprs, inputs, &[], policy)?;
+        let value = tape.output();
+        Ok((value, Box::new(move |cotangent| tape.gradient(cotangent))))
+    }
+
+    /// Like [`MultiAD::compute_grad_with_params`], with the derivatives of
+    /// non-smooth ops at their kinks chosen by `policy`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(AutodiffError)` if the graph is malformed (see
+    /// [`MultiAD::validate`]), and `Err(AutodiffError::MissingParams)` if
+    /// `params` is shorter than the largest `Param` index requires.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petite_ad::{MultiAD, SubgradientPolicy, Wrt, multi_ops};
+    ///
+    /// // f(x; w) = round(w * x)
+    /// let exprs = multi_ops![(inp, 0), (param, 0), (mul, 1, 0), (round, 2)];
+    /// let policy = SubgradientPolicy::StraightThrough;
+    /// let (_, grad_fn) =
+    ///     MultiAD::compute_grad_with_params_and_policy(&exprs, &[1.2], &[2.0], Wrt::Params, policy)
+    ///         .unwrap();
+    /// assert_eq!(grad_fn(1.0), vec![1.2]);
+    /// ```
+    #[must_use = "gradient computation is expensive; discarding the result is likely a bug"]
+    pub fn compute_grad_with_params_and_policy(
+        exprs: &[(MultiAD, Vec<usize>)],
+        inputs: &[f64],
+        params: &[f64],
+        wrt: Wrt,
+        policy: SubgradientPolicy,
+    ) -> Result<BackwardResultBox> {
+        let tape = FlatTape::record(exprs, inputs, params, policy)?;
+        let value = tape.output();
+        let backward_fn = Box::new(move |cotangent: f64| tape.gradient_wrt(cotangent, wrt));
+        Ok((value, backward_fn))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::multi_ops;
+    use crate::test_utils::approx_eq_eps as approx_eq;
+
+    const POLICIES: [SubgradientPolicy; 5] = [
+        SubgradientPolicy::Zero,
+        SubgradientPolicy::Left,
+        SubgradientPolicy::Right,
+        SubgradientPolicy::Average,
+        SubgradientPolicy::StraightThrough,
+    ];
+
+    fn grad(
+        exprs: &[(MultiAD, Vec<usize>)],
+        inputs: &[f64],
+        policy: SubgradientPolicy,
+    ) -> Vec<f64> {
+        MultiAD::compute_grad_with_policy(exprs, inputs, policy)
+            .unwrap()
+            .1(1.0)
+    }
+
+    /// Slopes of `op` just left and just right of `args` in each argument,
+    /// measured between two points on the same side so jumps do not count.
+    fn one_sided(op: MultiAD, args: &[f64]) -> Vec<(f64, f64)> {
+        let h = 1e-7;
+        let f = |i: usize, offset: f64| {
+            let mut args = args.to_vec();
+            args[i] += offset;
+            op.forward(&args).unwrap()
+        };
+        (0..args.len())
+            .map(|i| {
+                let left = (f(i, -h) - f(i, -2.0 * h)) / h;
+                let right = (f(i, 2.0 * h) - f(i, h)) / h;
+                (left, right)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_forward_values() {
+        let value = |op: MultiAD, args: &[f64]| op.forward(args).unwrap();
+        assert_eq!(value(MultiAD::Min, &[2.0, -1.0]), -1.0);
+        assert_eq!(value(MultiAD::Max, &[2.0, -1.0]), 2.0);
+        assert_eq!(value(MultiAD::Clamp, &[5.0, 0.0, 1.0]), 1.0);
+        assert_eq!(value(MultiAD::Clamp, &[0.5, 2.0, 1.0]), 1.0);
+        assert_eq!(value(MultiAD::Relu, &[-3.0]), 0.0);
+        assert!(value(MultiAD::Relu, &[f64::NAN]).is_nan());
+        assert_eq!(value(MultiAD::Floor, &[-1.5]), -2.0);
+        assert_eq!(value(MultiAD::Ceil, &[-1.5]), -1.0);
+        assert_eq!(value(MultiAD::Round, &[2.5]), 3.0);
+        assert_eq!(value(MultiAD::Sign, &[-0.0]), 0.0);
+        assert_eq!(value(MultiAD::Sign, &[-7.0]), -1.0);
+        assert_eq!(value(MultiAD::Fmod, &[-7.5, 2.0]), -1.5);
+    }
+
+    #[test]
+    fn test_abs_at_zero_defaults_to_zero() {
+        let exprs = multi_ops![(inp, 0), (abs, 0)];
+        assert_eq!(
+            MultiAD::compute_grad(&exprs, &[0.0]).unwrap().1(1.0),
+            vec![0.0]
+        );
+        assert_eq!(
+            MultiAD::compute_grad(&exprs, &[-2.0]).unwrap().1(1.0),
+            vec![-1.0]
+        );
+    }
+
+    #[test]
+    fn test_policies_pick_one_sided_derivatives() {
+        // (op, kink point) for every op with a kink
+        let cases = [
+            (MultiAD::Abs, vec![0.0]),
+            (MultiAD::Relu, vec![0.0]),
+            (MultiAD::Max, vec![1.5, 1.5]),
+            (MultiAD::Min, vec![1.5, 1.5]),
+            (MultiAD::Clamp, vec![0.0, 0.0, 2.0]),
+            (MultiAD::Clamp, vec![2.0, 0.0, 2.0]),
+            (MultiAD::Fmod, vec![6.0, 2.0]),
+            (MultiAD::Fmod, vec![-6.0, 2.0]),
+            (MultiAD::Fmod, vec![6.0, -3.0]),
+        ];
+        for (op, point) in cases {
+            let n = point.len();
+            let exprs: Vec<_> = (0..n)
+                .map(|k| (MultiAD::Inp, vec![k]))
+                .chain([(op, (0..n).collect())])
+                .collect();
+            let sides = one_sided(op, &point);
+            for policy in &POLICIES[..4] {
+                let grads = grad(&exprs, &point, *policy);
+                for (g, &(left, right)) in grads.iter().zip(&sides) {
+                    // Where both sides agree the policy is not consulted
+                    let expected = match policy {
+                        _ if approx_eq(left, right, 1e-6) => left,
+                        SubgradientPolicy::Zero => 0.0,
+                        SubgradientPolicy::Left => left,
+                        SubgradientPolicy::Right => right,
+                        _ => 0.5 * (left + right),
+                    };
+                    assert!(
+                        approx_eq(*g, expected, 1e-6),
+                        "{op} at {point:?} with {policy:?}: {g} vs {expected}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_straight_through() {
+        let policy = SubgradientPolicy::StraightThrough;
+        for op in ["floor", "ceil", "round", "sign"] {
+            let exprs = [(MultiAD::Inp, vec![0]), (op.parse().unwrap(), vec![0])];
+            assert_eq!(grad(&exprs, &[0.3], policy), vec![1.0]);
+            assert_eq!(grad(&exprs, &[0.3], SubgradientPolicy::Right), vec![0.0]);
+        }
+        let exprs = multi_ops![(inp, 0), (inp, 1), (max, 0, 1)];
+        assert_eq!(grad(&exprs, &[1.0, 1.0], policy), vec![1.0, 0.0]);
+        assert_eq!(grad(&exprs, &[1.0, 3.0], policy), vec![0.0, 1.0]);
+        let exprs = multi_ops![(inp, 0), (relu, 0)];
+        assert_eq!(grad(&exprs, &[0.0], policy), vec![1.0]);
+    }
+
+    #[test]
+    fn test_smooth_points_ignore_policy() {
+        let exprs = multi_ops![
+            (inp, 0),
+            (inp, 1),
+            (inp, 2),
+            (clamp, 0, 1, 2),
+            (fmod, 2, 0),
+            (min, 3, 2),
+            (relu, 5),
+            (mul, 6, 0),
+            (add, 7, 4)
+        ];
+        let point = [0.7, -1.0, 2.5];
+        let expected = grad(&exprs, &point, SubgradientPolicy::Zero);
+        for policy in POLICIES {
+            assert_eq!(grad(&exprs, &point, policy), expected);
+        }
+        // f = x² + (z mod x) with the clamp inactive, and trunc(2.5 / 0.7) = 3
+        assert!(approx_eq(expected[0], 1.4 - 3.0, 1e-12));
+        assert_eq!(expected[1..], [0.0, 1.0]);
+
+        // 1.0 / 0.1 rounds to exactly 10, but fmod(1.0, 0.1) ≈ 0.1 is not at
+        // a jump: trunc is 9
+        let exprs = multi_ops![(inp, 0), (inp, 1), (fmod, 0, 1)];
+        for policy in POLICIES {
+            assert_eq!(grad(&exprs, &[1.0, 0.1], policy), [1.0, -9.0]);
+        }
+    }
+
+    #[test]
+    fn test_subgradient_policy_with_params() {
+        // f(x; w) = round(w * x) * w
+        let exprs = multi_ops![(inp, 0), (param, 0), (mul, 1, 0), (round, 2), (mul, 3, 1)];
+        let grads = |policy| {
+            MultiAD::compute_grad_with_params_and_policy(&exprs, &[1.2], &[2.0], Wrt::Both, policy)
+                .unwrap()
+                .1(1.0)
+        };
+        assert_eq!(grads(SubgradientPolicy::Zero), vec![0.0, 2.0]);
+        // Straight-through treats round as the identity: f ≈ w² x
+        assert_eq!(
+            grads(SubgradientPolicy::StraightThrough),
+            vec![4.0, 2.0 + 2.4]
+        );
+    }
+}
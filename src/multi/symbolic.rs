@@ -0,0 +1,807 @@
+//! Symbolic differentiation into new graphs.
+//!
+//! [`MultiAD::derivative_graph`] runs forward mode symbolically: instead of
+//! pushing numeric tangents through the graph it emits the nodes that compute
+//! them. Tangents known to be zero or one are tracked without emitting nodes,
+//! so untouched branches and unit factors do not bloat the result.
+
+use super::multi_ad::MultiAD;
+use super::special;
+use crate::error::{AutodiffError, Result};
+
+/// Tangent of a node: a known constant or the index of a node computing it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Tangent {
+    Zero,
+    One,
+    Node(usize),
+}
+
+/// Appends nodes to a graph while tracking their value indices.
+struct Emitter {
+    graph: Vec<(MultiAD, Vec<usize>)>,
+    next_index: usize,
+    constants: Vec<(f64, usize)>,
+}
+
+impl Emitter {
+    fn push(&mut self, op: MultiAD, args: Vec<usize>) -> usize {
+        self.graph.push((op, args));
+        self.next_index += 1;
+        self.next_index - 1
+    }
+
+    /// Index of a node holding `value`, emitted once per distinct constant.
+    fn constant(&mut self, value: f64) -> usize {
+        if let Some(&(_, index)) = self
+            .constants
+            .iter()
+            .find(|(c, _)| c.to_bits() == value.to_bits())
+        {
+            return index;
+        }
+        let index = self.push(MultiAD::Const(value), vec![]);
+        self.constants.push((value, index));
+        index
+    }
+
+    fn node(&mut self, t: Tangent) -> usize {
+        match t {
+            Tangent::Zero => self.constant(0.0),
+            Tangent::One => self.constant(1.0),
+            Tangent::Node(index) => index,
+        }
+    }
+
+    fn add(&mut self, a: Tangent, b: Tangent) -> Tangent {
+        match (a, b) {
+            (Tangent::Zero, t) | (t, Tangent::Zero) => t,
+            _ => {
+                let (a, b) = (self.node(a), self.node(b));
+                Tangent::Node(self.push(MultiAD::Add, vec![a, b]))
+            }
+        }
+    }
+
+    fn sub(&mut self, a: Tangent, b: Tangent) -> Tangent {
+        match (a, b) {
+            (t, Tangent::Zero) => t,
+            (Tangent::Zero, t) => self.neg(t),
+            _ => {
+                let (a, b) = (self.node(a), self.node(b));
+                Tangent::Node(self.push(MultiAD::Sub, vec![a, b]))
+            }
+        }
+    }
+
+    fn neg(&mut self, t: Tangent) -> Tangent {
+        match t {
+            Tangent::Zero => Tangent::Zero,
+            _ => {
+                let t = self.node(t);
+                Tangent::Node(self.push(MultiAD::Neg, vec![t]))
+            }
+        }
+    }
+
+    /// `t * value`, where `value` is the index of an existing node.
+    fn scale(&mut self, t: Tangent, value: usize) -> Tangent {
+        match t {
+            Tangent::Zero => Tangent::Zero,
+            Tangent::One => Tangent::Node(value),
+            Tangent::Node(t) => Tangent::Node(self.push(MultiAD::Mul, vec![t, value])),
+        }
+    }
+
+    /// `t / value`, where `value` is the index of an existing node.
+    fn divide(&mut self, t: Tangent, value: usize) -> Tangent {
+        match t {
+            Tangent::Zero => Tangent::Zero,
+            _ => {
+                let t = self.node(t);
+                Tangent::Node(self.push(MultiAD::Div, vec![t, value]))
+            }
+        }
+    }
+
+    /// Emit the tangent of `op(args) = out` given the tangents of its arguments.
+    fn rule(&mut self, op: MultiAD, args: &[usize], out: usize, dargs: &[Tangent]) -> Tangent {
+        match op {
+            MultiAD::Inp
+            | MultiAD::Const(_)
+            | MultiAD::Param(_)
+            | MultiAD::Floor
+            | MultiAD::Ceil
+            | MultiAD::Round
+            | MultiAD::Sign => Tangent::Zero,
+            MultiAD::Add => self.add(dargs[0], dargs[1]),
+            MultiAD::Sub => self.sub(dargs[0], dargs[1]),
+            MultiAD::Neg => self.neg(dargs[0]),
+            // (a b)' = a' b + a b'
+            MultiAD::Mul => {
+                let da = self.scale(dargs[0], args[1]);
+                let db = self.scale(dargs[1], args[0]);
+                self.add(da, db)
+            }
+            // (a / b)' = (a' - (a / b) b') / b
+            MultiAD::Div => {
+                let db = self.scale(dargs[1], out);
+                let numerator = self.sub(dargs[0], db);
+                self.divide(numerator, args[1])
+            }
+            // (a^b)' = a' b a^(b-1) + b' a^b ln(a)
+            MultiAD::Pow => {
+                let da = if dargs[0] == Tangent::Zero {
+                    Tangent::Zero
+                } else {
+                    let one = self.constant(1.0);
+                    let exp_m1 = self.push(MultiAD::Sub, vec![args[1], one]);
+                    let pow_m1 = self.push(MultiAD::Pow, vec![args[0], exp_m1]);
+                    let factor = self.push(MultiAD::Mul, vec![args[1], pow_m1]);
+                    self.scale(dargs[0], factor)
+                };
+                let db = if dargs[1] == Tangent::Zero {
+                    Tangent::Zero
+                } else {
+                    let ln = self.push(MultiAD::Ln, vec![args[0]]);
+                    let factor = self.push(MultiAD::Mul, vec![out, ln]);
+                    self.scale(dargs[1], factor)
+                };
+                self.add(da, db)
+            }
+            // atan2(y, x)' = (x y' - y x') / (x² + y²)
+            MultiAD::Atan2 => {
+                let (y, x) = (args[0], args[1]);
+                let dy = self.scale(dargs[0], x);
+                let dx = self.scale(dargs[1], y);
+                let numerator = self.sub(dy, dx);
+                if numerator == Tangent::Zero {
+                    return Tangent::Zero;
+                }
+                let x2 = self.push(MultiAD::Mul, vec![x, x]);
+                let y2 = self.push(MultiAD::Mul, vec![y, y]);
+                let r2 = self.push(MultiAD::Add, vec![x2, y2]);
+                self.divide(numerator, r2)
+            }
+            // hypot(a, b)' = (a a' + b b') / hypot(a, b)
+            MultiAD::Hypot => {
+                let da = self.scale(dargs[0], args[0]);
+                let db = self.scale(dargs[1], args[1]);
+                let numerator = self.add(da, db);
+                self.divide(numerator, out)
+            }
+            // max' = a' [a > b] + b' [b > a], min' = a' [b > a] + b' [a > b]
+            MultiAD::Max | MultiAD::Min => {
+                let (a, b) = if op == MultiAD::Max {
+                    (args[0], args[1])
+                } else {
+                    (args[1], args[0])
+                };
+                let da = if dargs[0] == Tangent::Zero {
+                    Tangent::Zero
+                } else {
+                    let wins = self.greater(a, b);
+                    self.scale(dargs[0], wins)
+                };
+                let db = if dargs[1] == Tangent::Zero {
+                    Tangent::Zero
+                } else {
+                    let wins = self.greater(b, a);
+                    self.scale(dargs[1], wins)
+                };
+                self.add(da, db)
+            }
+            // x' [lo < x < hi] + lo' [x < lo <= hi] + hi' [x > hi or hi < lo]
+            MultiAD::Clamp => {
+                let (x, lo, hi) = (args[0], args[1], args[2]);
+                let one = self.constant(1.0);
+                let inverted = self.greater(lo, hi);
+                let ordered = self.push(MultiAD::Sub, vec![one, inverted]);
+                let dx = if dargs[0] == Tangent::Zero {
+                    Tangent::Zero
+                } else {
+                    let above = self.greater(x, lo);
+                    let below = self.greater(hi, x);
+                    let inside = self.push(MultiAD::Mul, vec![above, below]);
+                    self.scale(dargs[0], inside)
+                };
+                let dlo = if dargs[1] == Tangent::Zero {
+                    Tangent::Zero
+                } else {
+                    let under = self.greater(lo, x);
+                    let factor = self.push(MultiAD::Mul, vec![under, ordered]);
+                    self.scale(dargs[1], factor)
+                };
+                let dhi = if dargs[2] == Tangent::Zero {
+                    Tangent::Zero
+                } else {
+                    let over = self.greater(x, hi);
+                    let over = self.push(MultiAD::Mul, vec![over, ordered]);
+                    let factor = self.push(MultiAD::Add, vec![over, inverted]);
+                    self.scale(dargs[2], factor)
+                };
+                let sum = self.add(dx, dlo);
+                self.add(sum, dhi)
+            }
+            // fmod(a, b)' = a' - b' trunc(a / b), with b' dropped where the
+            // remainder is zero and the value jumps; trunc(a / b) is read off
+            // the remainder as round((a - fmod(a, b)) / b)
+            MultiAD::Fmod => {
+                let db = if dargs[1] == Tangent::Zero {
+                    Tangent::Zero
+                } else {
+                    let multiple = self.push(MultiAD::Sub, vec![args[0], out]);
+                    let q = self.push(MultiAD::Div, vec![multiple, args[1]]);
+                    let trunc = self.push(MultiAD::Round, vec![q]);
+                    let remainder_sign = self.push(MultiAD::Sign, vec![out]);
+                    let off_jump = self.push(MultiAD::Abs, vec![remainder_sign]);
+                    let factor = self.push(MultiAD::Mul, vec![trunc, off_jump]);
+                    self.scale(dargs[1], factor)
+                };
+                self.sub(dargs[0], db)
+            }
+            MultiAD::Sum => self.sum(dargs.to_vec()),
+            MultiAD::Mean => {
+                let total = self.sum(dargs.to_vec());
+                let factor = self.constant(1.0 / args.len() as f64);
+                self.scale(total, factor)
+            }
+            // (Π aⱼ)' = Σ aᵢ' Π_{j≠i} aⱼ
+            MultiAD::Prod => {
+                let terms = (0..args.len())
+                    .map(|i| {
+                        if dargs[i] == Tangent::Zero || args.len() == 1 {
+                            return dargs[i];
+                        }
+                        let others: Vec<usize> = (0..args.len())
+                            .filter(|&j| j != i)
+                            .map(|j| args[j])
+                            .collect();
+                        let others = self.push(MultiAD::Prod, others);
+                        self.scale(dargs[i], others)
+                    })
+                    .collect();
+                self.sum(terms)
+            }
+            // logsumexp' = Σ aᵢ' exp(aᵢ - out)
+            MultiAD::LogSumExp => {
+                let terms = (0..args.len())
+                    .map(|i| {
+                        if dargs[i] == Tangent::Zero {
+                            return Tangent::Zero;
+                        }
+                        let shifted = self.push(MultiAD::Sub, vec![args[i], out]);
+                        let weight = self.push(MultiAD::Exp, vec![shifted]);
+                        self.scale(dargs[i], weight)
+                    })
+                    .collect();
+                self.sum(terms)
+            }
+            // softmax_sum' = Σ aᵢ' pᵢ (1 + aᵢ - out) with pᵢ = exp(aᵢ - logsumexp(a))
+            MultiAD::SoftmaxSum => {
+                let mut log_total = None;
+                let terms = (0..args.len())
+                    .map(|i| {
+                        if dargs[i] == Tangent::Zero {
+                            return Tangent::Zero;
+                        }
+                        let log_total = *log_total
+                            .get_or_insert_with(|| self.push(MultiAD::LogSumExp, args.to_vec()));
+                        let shifted = self.push(MultiAD::Sub, vec![args[i], log_total]);
+                        let weight = self.push(MultiAD::Exp, vec![shifted]);
+                        let one = self.constant(1.0);
+                        let gap = self.push(MultiAD::Sub, vec![args[i], out]);
+                        let lift = self.push(MultiAD::Add, vec![one, gap]);
+                        let factor = self.push(MultiAD::Mul, vec![weight, lift]);
+                        self.scale(dargs[i], factor)
+                    })
+                    .collect();
+                self.sum(terms)
+            }
+            _ if dargs[0] == Tangent::Zero => Tangent::Zero,
+            MultiAD::Sin => {
+                let cos = self.push(MultiAD::Cos, vec![args[0]]);
+                self.scale(dargs[0], cos)
+            }
+            MultiAD::Cos => {
+                let sin = self.push(MultiAD::Sin, vec![args[0]]);
+                let neg_sin = self.push(MultiAD::Neg, vec![sin]);
+                self.scale(dargs[0], neg_sin)
+            }
+            // tan' = 1 / cos²
+            MultiAD::Tan => {
+                let cos = self.push(MultiAD::Cos, vec![args[0]]);
+                let cos2 = self.push(MultiAD::Mul, vec![cos, cos]);
+                self.divide(dargs[0], cos2)
+            }
+            MultiAD::Exp => self.scale(dargs[0], out),
+            MultiAD::Ln => self.divide(dargs[0], args[0]),
+            // sqrt' = 1 / (2 sqrt)
+            MultiAD::Sqrt => {
+                let twice = self.push(MultiAD::Add, vec![out, out]);
+                self.divide(dargs[0], twice)
+            }
+            // |a|' = sign(a), zero at zero
+            MultiAD::Abs => {
+                let sign = self.push(MultiAD::Sign, vec![args[0]]);
+                self.scale(dargs[0], sign)
+            }
+            // relu' = [a > 0]
+            MultiAD::Relu => {
+                let sign = self.push(MultiAD::Sign, vec![args[0]]);
+                let step = self.push(MultiAD::Relu, vec![sign]);
+                self.scale(dargs[0], step)
+            }
+            MultiAD::Sinh => {
+                let cosh = self.push(MultiAD::Cosh, vec![args[0]]);
+                self.scale(dargs[0], cosh)
+            }
+            MultiAD::Cosh => {
+                let sinh = self.push(MultiAD::Sinh, vec![args[0]]);
+                self.scale(dargs[0], sinh)
+            }
+            // tanh' = 1 - tanh²
+            MultiAD::Tanh => {
+                let factor = self.one_minus_square(out);
+                self.scale(dargs[0], factor)
+            }
+            // asin' = 1 / sqrt(1 - a²), acos' = -asin'
+            MultiAD::Asin | MultiAD::Acos => {
+                let one_minus = self.one_minus_square(args[0]);
+                let root = self.push(MultiAD::Sqrt, vec![one_minus]);
+                let d = self.divide(dargs[0], root);
+                if op == MultiAD::Acos {
+                    self.neg(d)
+                } else {
+                    d
+                }
+            }
+            // atan' = 1 / (1 + a²)
+            MultiAD::Atan => {
+                let one = self.constant(1.0);
+                let square = self.push(MultiAD::Mul, vec![args[0], args[0]]);
+                let denominator = self.push(MultiAD::Add, vec![one, square]);
+                self.divide(dargs[0], denominator)
+            }
+            MultiAD::Log1p => {
+                let one = self.constant(1.0);
+                let denominator = self.push(MultiAD::Add, vec![one, args[0]]);
+                self.divide(dargs[0], denominator)
+            }
+            MultiAD::Expm1 => {
+                let exp = self.push(MultiAD::Exp, vec![args[0]]);
+                self.scale(dargs[0], exp)
+            }
+            // log_b' = 1 / (a ln b)
+            MultiAD::Log2 | MultiAD::Log10 => {
+                let base = if op == MultiAD::Log2 {
+                    std::f64::consts::LN_2
+                } else {
+                    std::f64::consts::LN_10
+                };
+                let ln_base = self.constant(base);
+                let denominator = self.push(MultiAD::Mul, vec![args[0], ln_base]);
+                self.divide(dargs[0], denominator)
+            }
+            // σ' = σ (1 - σ), written σ(a) σ(-a) so it does not round to zero
+            MultiAD::Sigmoid => {
+                let negated = self.push(MultiAD::Neg, vec![args[0]]);
+                let complement = self.push(MultiAD::Sigmoid, vec![negated]);
+                let factor = self.push(MultiAD::Mul, vec![out, complement]);
+                self.scale(dargs[0], factor)
+            }
+            // softplus' = σ
+            MultiAD::Softplus => {
+                let sigmoid = self.push(MultiAD::Sigmoid, vec![args[0]]);
+                self.scale(dargs[0], sigmoid)
+            }
+            // erf' = 2/√π exp(-a²), erfc' = -erf'
+            MultiAD::Erf | MultiAD::Erfc => {
+                let height = if op == MultiAD::Erf {
+                    special::FRAC_2_SQRT_PI
+                } else {
+                    -special::FRAC_2_SQRT_PI
+                };
+                let factor = self.gaussian(args[0], 1.0, height);
+                self.scale(dargs[0], factor)
+            }
+            // lgamma' = ψ
+            MultiAD::Lgamma => {
+                let digamma = self.push(MultiAD::Digamma, vec![args[0]]);
+                self.scale(dargs[0], digamma)
+            }
+            // ψ' = ψ₁
+            MultiAD::Digamma => {
+                let trigamma = self.push(MultiAD::Trigamma, vec![args[0]]);
+                self.scale(dargs[0], trigamma)
+            }
+            // Only reached with a zero tangent; see `derivative_graph`
+            MultiAD::Trigamma => Tangent::Zero,
+            // Φ' = φ = exp(-a²/2) / √(2π)
+            MultiAD::NormalCdf => {
+                let factor = self.gaussian(args[0], 0.5, special::FRAC_1_SQRT_2PI);
+                self.scale(dargs[0], factor)
+            }
+            MultiAD::NormalLogpdf => {
+                let neg = self.push(MultiAD::Neg, vec![args[0]]);
+                self.scale(dargs[0], neg)
+            }
+            // W' = 1 / (a + e^W)
+            MultiAD::LambertW => {
+                let exp = self.push(MultiAD::Exp, vec![out]);
+                let denominator = self.push(MultiAD::Add, vec![args[0], exp]);
+                self.divide(dargs[0], denominator)
+            }
+        }
+    }
+
+    /// Sum of tangents, as one `Sum` node when more than one is non-zero.
+    fn sum(&mut self, terms: Vec<Tangent>) -> Tangent {
+        let terms: Vec<Tangent> = terms.into_iter().filter(|&t| t != Tangent::Zero).collect();
+        match terms[..] {
+            [] => Tangent::Zero,
+            [t] => t,
+            _ => {
+                let nodes = terms.into_iter().map(|t| self.node(t)).collect();
+                Tangent::Node(self.push(MultiAD::Sum, nodes))
+            }
+        }
+    }
+
+    /// Index of a new node computing `height · exp(-curvature · value²)`.
+    fn gaussian(&mut self, value: usize, curvature: f64, height: f64) -> usize {
+        let square = self.push(MultiAD::Mul, vec![value, value]);
+        let curvature = self.constant(-curvature);
+        let exponent = self.push(MultiAD::Mul, vec![curvature, square]);
+        let exp = self.push(MultiAD::Exp, vec![exponent]);
+        let height = self.constant(height);
+        self.push(MultiAD::Mul, vec![height, exp])
+    }
+
+    /// Index of a new node computing 1 if `a > b` and 0 otherwise.
+    fn greater(&mut self, a: usize, b: usize) -> usize {
+        let difference = self.push(MultiAD::Sub, vec![a, b]);
+        let sign = self.push(MultiAD::Sign, vec![difference]);
+        self.push(MultiAD::Relu, vec![sign])
+    }
+
+    /// Index of a new node computing `1 - value²`.
+    fn one_minus_square(&mut self, value: usize) -> usize {
+        let one = self.constant(1.0);
+        let square = self.push(MultiAD::Mul, vec![value, value]);
+        self.push(MultiAD::Sub, vec![one, square])
+    }
+}
+
+impl MultiAD {
+    /// Build a graph computing `∂f/∂x_wrt` using the existing op set.
+    ///
+    /// The result evaluates with the same inputs as `exprs`: it contains the
+    /// original nodes followed by the derivative nodes, and its last node is
+    /// the derivative. Because it is an ordinary graph it can be inspected,
+    /// evaluated, or passed back in for higher-order derivatives.
+    ///
+    /// The graph takes `num_inputs` inputs, laid out as for
+    /// [`MultiAD::validate`]; a graph without `Inp` nodes gets one in front
+    /// for each of them. Derivatives of non-smooth ops at their kinks follow
+    /// [`SubgradientPolicy::Zero`].
+    ///
+    /// [`SubgradientPolicy::Zero`]: crate::SubgradientPolicy::Zero
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(AutodiffError)` if the graph is malformed (see
+    /// [`MultiAD::validate`]) or `wrt` is not an input index, and
+    /// `Err(AutodiffError::NoSymbolicDerivative)` if any `Trigamma` node
+    /// depends on `x_wrt`, since its derivative is not an op.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petite_ad::{MultiAD, multi_ops};
+    ///
+    /// // f(x, y) = x * y  →  ∂f/∂x = y
+    /// let exprs = multi_ops![(inp, 0), (inp, 1), (mul, 0, 1)];
+    /// let dfdx = MultiAD::derivative_graph(&exprs, 2, 0).unwrap();
+    /// assert_eq!(MultiAD::compute(&dfdx, &[3.0, 4.0]).unwrap(), 4.0);
+    ///
+    /// // Differentiate again: ∂²f/∂x∂y = 1
+    /// let dfdxdy = MultiAD::derivative_graph(&dfdx, 2, 1).unwrap();
+    /// assert_eq!(MultiAD::compute(&dfdxdy, &[3.0, 4.0]).unwrap(), 1.0);
+    /// ```
+    pub fn derivative_graph(
+        exprs: &[(MultiAD, Vec<usize>)],
+        num_inputs: usize,
+        wrt: usize,
+    ) -> Result<Vec<(MultiAD, Vec<usize>)>> {
+        if wrt >= num_inputs {
+            return Err(AutodiffError::IndexOutOfBounds {
+                index: wrt,
+                max_index: num_inputs.saturating_sub(1),
+            });
+        }
+
+        Self::validate(exprs, num_inputs)?;
+        let exprs = &Self::with_input_nodes(exprs, num_inputs);
+
+        let mut tangents: Vec<Tangent> = Vec::with_capacity(exprs.len());
+        let mut emitter = Emitter {
+            graph: exprs.to_vec(),
+            next_index: exprs.len(),
+            constants: Vec::new(),
+        };
+
+        for (index, (op, args)) in exprs.iter().enumerate() {
+            let tangent = match op {
+                MultiAD::Inp if args[0] == wrt => Tangent::One,
+                MultiAD::Inp => Tangent::Zero,
+                _ => {
+                    let dargs: Vec<Tangent> = args.iter().map(|&arg| tangents[arg]).collect();
+                    if *op == MultiAD::Trigamma && dargs[0] != Tangent::Zero {
+                        return Err(AutodiffError::NoSymbolicDerivative {
+                            operation: op.op_name(),
+                        });
+                    }
+                    emitter.rule(*op, args, index, &dargs)
+                }
+            };
+            tangents.push(tangent);
+        }
+
+        // The derivative must be the last node of the result
+        let output = tangents.last().copied().unwrap_or(Tangent::Zero);
+        let last = emitter.next_index - 1;
+        match output {
+            Tangent::Node(t) if t == last => {}
+            Tangent::Node(t) => {
+                let (op, args) = emitter.graph[t].clone();
+                emitter.push(op, args);
+            }
+            constant => {
+                let value = if constant == Tangent::One { 1.0 } else { 0.0 };
+                emitter.push(MultiAD::Const(value), vec![]);
+            }
+        }
+        Ok(emitter.graph)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::multi_ops;
+    use crate::test_utils::approx_eq_eps as approx_eq;
+
+    fn all_ops_graph() -> Vec<(MultiAD, Vec<usize>)> {
+        // f(x, y) = x^y / tan(x) + ln(y) * sqrt(x) - abs(x - y) + cos(-exp(y))
+        let (graph, _) =
+            MultiAD::parse("x^y / tan(x) + ln(y) * sqrt(x) - abs(x - y) + cos(-exp(y))").unwrap();
+        graph
+    }
+
+    #[test]
+    fn test_derivative_matches_gradient() {
+        let exprs = all_ops_graph();
+        let dfdx = MultiAD::derivative_graph(&exprs, 2, 0).unwrap();
+        let dfdy = MultiAD::derivative_graph(&exprs, 2, 1).unwrap();
+        for inputs in [[1.2, 2.5], [0.7, 0.4]] {
+            let grads = MultiAD::compute_grad(&exprs, &inputs).unwrap().1(1.0);
+            let dx = MultiAD::compute(&dfdx, &inputs).unwrap();
+            let dy = MultiAD::compute(&dfdy, &inputs).unwrap();
+            assert!(approx_eq(dx, grads[0], 1e-10));
+            assert!(approx_eq(dy, grads[1], 1e-10));
+        }
+    }
+
+    #[test]
+    fn test_second_derivatives_match_hessian() {
+        let exprs = all_ops_graph();
+        let inputs = [1.2, 2.5];
+        let hessian = MultiAD::hessian(&exprs, &inputs).unwrap();
+        for (i, row) in hessian.iter().enumerate() {
+            let first = MultiAD::derivative_graph(&exprs, 2, i).unwrap();
+            for (j, expected) in row.iter().enumerate() {
+                let second = MultiAD::derivative_graph(&first, 2, j).unwrap();
+                let value = MultiAD::compute(&second, &inputs).unwrap();
+                assert!(approx_eq(value, *expected, 1e-8));
+            }
+        }
+    }
+
+    #[test]
+    fn test_extended_ops_derivatives() {
+        let (exprs, _) = MultiAD::parse(
+            "tanh(x * y) + sinh(x) * cosh(y) + asin(x) * acos(x / y) + atan(y) + atan2(y, x) \
+             + log1p(x) * expm1(y) + log2(y) - log10(x) + sigmoid(x - y) * softplus(x * y) \
+             + hypot(x, y)",
+        )
+        .unwrap();
+        let inputs = [0.4, 1.5];
+        let grads = MultiAD::compute_grad(&exprs, &inputs).unwrap().1(1.0);
+        let hessian = MultiAD::hessian(&exprs, &inputs).unwrap();
+        for (i, (grad, row)) in grads.iter().zip(&hessian).enumerate() {
+            let first = MultiAD::derivative_graph(&exprs, 2, i).unwrap();
+            let value = MultiAD::compute(&first, &inputs).unwrap();
+            assert!(approx_eq(value, *grad, 1e-10));
+            for (j, expected) in row.iter().enumerate() {
+                let second = MultiAD::derivative_graph(&first, 2, j).unwrap();
+                let value = MultiAD::compute(&second, &inputs).unwrap();
+                assert!(approx_eq(value, *expected, 1e-8));
+            }
+        }
+    }
+
+    #[test]
+    fn test_special_ops_derivatives() {
+        let (exprs, _) = MultiAD::parse(
+            "erf(x * y) + erfc(y) * normal_cdf(x - y) + normal_logpdf(x * y) + lambert_w(x / y)",
+        )
+        .unwrap();
+        let inputs = [-0.7, 2.0];
+        let grads = MultiAD::compute_grad(&exprs, &inputs).unwrap().1(1.0);
+        let hessian = MultiAD::hessian(&exprs, &inputs).unwrap();
+        for (i, (grad, row)) in grads.iter().zip(&hessian).enumerate() {
+            let first = MultiAD::derivative_graph(&exprs, 2, i).unwrap();
+            let value = MultiAD::compute(&first, &inputs).unwrap();
+            assert!(approx_eq(value, *grad, 1e-12));
+            for (j, expected) in row.iter().enumerate() {
+                let second = MultiAD::derivative_graph(&first, 2, j).unwrap();
+                let value = MultiAD::compute(&second, &inputs).unwrap();
+                assert!(approx_eq(value, *expected, 1e-10));
+            }
+        }
+
+        // lgamma' is digamma and lgamma'' is trigamma
+        let (exprs, _) = MultiAD::parse("lgamma(x) * y").unwrap();
+        let inputs = [0.5, 2.0];
+        let dfdx = MultiAD::derivative_graph(&exprs, 2, 0).unwrap();
+        let value = MultiAD::compute(&dfdx, &inputs).unwrap();
+        assert!(approx_eq(value, 2.0 * -1.963_510_026_021_423_5, 1e-12));
+        let hessian = MultiAD::hessian(&exprs, &inputs).unwrap();
+        for (j, expected) in hessian[0].iter().enumerate() {
+            let second = MultiAD::derivative_graph(&dfdx, 2, j).unwrap();
+            let value = MultiAD::compute(&second, &inputs).unwrap();
+            assert!(approx_eq(value, *expected, 1e-12));
+        }
+
+        // trigamma's own derivative is not an op
+        let d2fdx2 = MultiAD::derivative_graph(&dfdx, 2, 0).unwrap();
+        assert_eq!(
+            MultiAD::derivative_graph(&d2fdx2, 2, 0),
+            Err(AutodiffError::NoSymbolicDerivative {
+                operation: "Trigamma"
+            })
+        );
+        // Trigamma nodes that do not depend on the variable are fine
+        assert!(MultiAD::derivative_graph(&d2fdx2, 2, 1).is_ok());
+    }
+
+    #[test]
+    fn test_nary_ops_derivatives() {
+        let (exprs, _) = MultiAD::parse(
+            "sum(x, y, x * y) * prod(x, y, z) + mean(x, z) ^ 2 + logsumexp(x, y, z) \
+             - softmax_sum(x * z, y, z)",
+        )
+        .unwrap();
+        for inputs in [[0.4, 1.5, -0.3], [0.0, 2.0, 0.5]] {
+            let grads = MultiAD::compute_grad(&exprs, &inputs).unwrap().1(1.0);
+            let hessian = MultiAD::hessian(&exprs, &inputs).unwrap();
+            for (i, (grad, row)) in grads.iter().zip(&hessian).enumerate() {
+                let first = MultiAD::derivative_graph(&exprs, 3, i).unwrap();
+                let value = MultiAD::compute(&first, &inputs).unwrap();
+                assert!(approx_eq(value, *grad, 1e-12));
+                for (j, expected) in row.iter().enumerate() {
+                    let second = MultiAD::derivative_graph(&first, 3, j).unwrap();
+                    let value = MultiAD::compute(&second, &inputs).unwrap();
+                    assert!(approx_eq(value, *expected, 1e-10));
+                }
+            }
+        }
+
+        // The tangents of a sum are added by one n-ary node
+        let exprs = multi_ops![(inp, 0), (sin, 0), (cos, 0), (exp, 0), (sum, 1, 2, 3)];
+        let dfdx = MultiAD::derivative_graph(&exprs, 1, 0).unwrap();
+        assert_eq!(dfdx.last().unwrap().0, MultiAD::Sum);
+    }
+
+    #[test]
+    fn test_nonsmooth_derivatives_match_gradient() {
+        let (exprs, _) = MultiAD::parse(
+            "max(x, y) * min(x, y) + clamp(x, y, z) + relu(x - z) + abs(y) + fmod(z, x) \
+             + floor(x) + ceil(y) + round(z) + sign(x)",
+        )
+        .unwrap();
+        let derivatives: Vec<_> = (0..3)
+            .map(|wrt| MultiAD::derivative_graph(&exprs, 3, wrt).unwrap())
+            .collect();
+        // Smooth points, including fmod(1.0, 0.1) where 1.0 / 0.1 rounds to
+        // an integer, then kinks of max/min, clamp, relu, abs and fmod
+        for inputs in [
+            [0.7, -1.2, 2.5],
+            [0.1, -1.0, 1.0],
+            [1.5, 1.5, 3.0],
+            [0.5, 0.5, 2.0],
+            [2.0, 0.0, 2.0],
+            [1.0, -1.0, 3.0],
+        ] {
+            let grads = MultiAD::compute_grad(&exprs, &inputs).unwrap().1(1.0);
+            for (derivative, grad) in derivatives.iter().zip(&grads) {
+                let value = MultiAD::compute(derivative, &inputs).unwrap();
+                assert!(
+                    approx_eq(value, *grad, 1e-12),
+                    "{inputs:?}: {value} vs {grad}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_derivative_graph_layout() {
+        // d(x * y)/dx = y: y is declared again so it is the last node
+        let exprs = multi_ops![(inp, 0), (inp, 1), (mul, 0, 1)];
+        let dfdx = MultiAD::derivative_graph(&exprs, 2, 0).unwrap();
+        assert_eq!(dfdx[..3], exprs[..]);
+        assert_eq!(dfdx[3..], multi_ops![(inp, 1)][..]);
+
+        // d(sin(x) * y)/dy = sin(x) is an earlier op, so it is computed again
+        let exprs = multi_ops![(inp, 0), (sin, 0), (inp, 1), (mul, 1, 2)];
+        let dfdy = MultiAD::derivative_graph(&exprs, 2, 1).unwrap();
+        assert_eq!(dfdy[4..], multi_ops![(sin, 0)][..]);
+
+        // d(sin(x))/dx = cos(x) * 1 needs no scaling node
+        let exprs = multi_ops![(inp, 0), (sin, 0)];
+        let dfdx = MultiAD::derivative_graph(&exprs, 1, 0).unwrap();
+        assert_eq!(dfdx[2..], multi_ops![(cos, 0)][..]);
+    }
+
+    #[test]
+    fn test_implicit_inputs() {
+        // f(x, y) = x * y with inputs at indices 0 and 1
+        let exprs = multi_ops![(mul, 0, 1), (sin, 2)];
+        let dfdy = MultiAD::derivative_graph(&exprs, 2, 1).unwrap();
+        assert_eq!(dfdy[..2], multi_ops![(inp, 0), (inp, 1)][..]);
+        let expected = 3.0 * (3.0_f64 * 4.0).cos();
+        assert!(approx_eq(
+            MultiAD::compute(&dfdy, &[3.0, 4.0]).unwrap(),
+            expected,
+            1e-12
+        ));
+
+        // f(x, y, z) = x * z: the unused sin(x) and y do not hide input z
+        let exprs = multi_ops![(sin, 0), (mul, 0, 2)];
+        let dfdx = MultiAD::derivative_graph(&exprs, 3, 0).unwrap();
+        let dfdz = MultiAD::derivative_graph(&exprs, 3, 2).unwrap();
+        assert_eq!(MultiAD::compute(&dfdx, &[2.0, 5.0, 7.0]).unwrap(), 7.0);
+        assert_eq!(MultiAD::compute(&dfdz, &[2.0, 5.0, 7.0]).unwrap(), 2.0);
+    }
+
+    #[test]
+    fn test_constant_derivatives() {
+        let exprs = multi_ops![(inp, 0), (inp, 1), (sin, 1)];
+        let dfdx = MultiAD::derivative_graph(&exprs, 2, 0).unwrap();
+        assert_eq!(dfdx.last(), Some(&(MultiAD::Const(0.0), vec![])));
+        assert_eq!(MultiAD::compute(&dfdx, &[1.0, 2.0]).unwrap(), 0.0);
+
+        let exprs = multi_ops![(inp, 0), (inp, 1), (add, 0, 1)];
+        let dfdx = MultiAD::derivative_graph(&exprs, 2, 0).unwrap();
+        assert_eq!(MultiAD::compute(&dfdx, &[1.0, 2.0]).unwrap(), 1.0);
+    }
+
+    #[test]
+    fn test_derivative_graph_errors() {
+        let exprs = multi_ops![(inp, 0), (inp, 1), (mul, 0, 1)];
+        assert_eq!(
+            MultiAD::derivative_graph(&exprs, 2, 2),
+            Err(AutodiffError::IndexOutOfBounds {
+                index: 2,
+                max_index: 1
+            })
+        );
+
+        let exprs = vec![(MultiAD::Inp, vec![0]), (MultiAD::Sin, vec![0, 0])];
+        assert_eq!(
+            MultiAD::derivative_graph(&exprs, 1, 0),
+            Err(AutodiffError::arity("Sin", 1, 2))
+        );
+    }
+}
@@ -0,0 +1,191 @@
+//! Flat tape used by the reverse pass.
+//!
+//! A forward pass records every node into contiguous arrays: the op code, the
+//! argument indices, the saved primal values and the local partials evaluated
+//! at those values. Sweeping the tape backward is then a single loop of
+//! multiply-adds with no boxing and no per-node allocation.
+//!
+//! The tape is a [`CompiledGraph`] (op codes and argument indices) paired with
+//! the [`Workspace`] holding the values and partials of one evaluation.
+
+use super::compiled::{CompiledGraph, Workspace};
+use super::multi_ad::MultiAD;
+use super::params::Wrt;
+use super::subgradient::SubgradientPolicy;
+use crate::error::Result;
+
+/// A graph recorded at one evaluation point.
+#[derive(Debug, Clone)]
+pub(crate) struct FlatTape {
+    graph: CompiledGraph,
+    ws: Workspace,
+    num_params: usize,
+}
+
+impl FlatTape {
+    /// Run the forward pass and record the tape.
+    ///
+    /// `Param(k)` nodes read `params[k]`, and the local partials of
+    /// non-smooth ops at their kinks follow `policy`.
+    pub(crate) fn record(
+        exprs: &[(MultiAD, Vec<usize>)],
+        inputs: &[f64],
+        params: &[f64],
+        policy: SubgradientPolicy,
+    ) -> Result<Self> {
+        let graph = CompiledGraph::new(exprs, inputs.len())?.with_subgradient_policy(policy);
+        let mut ws = graph.workspace();
+        graph.forward(inputs, params, &mut ws, true)?;
+        Ok(Self {
+            graph,
+            ws,
+            num_params: params.len(),
+        })
+    }
+
+    /// Number of inputs the tape was recorded with.
+    pub(crate) fn num_inputs(&self) -> usize {
+        self.graph.num_inputs()
+    }
+
+    /// Saved primal values of every node, inputs first.
+    pub(crate) fn values(&self) -> &[f64] {
+        self.ws.values()
+    }
+
+    /// Value of the last node, which is the graph output.
+    pub(crate) fn output(&self) -> f64 {
+        self.values()[self.output_slot()]
+    }
+
+    /// Value slot of the graph output.
+    pub(crate) fn output_slot(&self) -> usize {
+        self.graph.output()
+    }
+
+    /// Value slot of node `node` of the source graph, if it exists.
+    pub(crate) fn slot(&self, node: usize) -> Option<usize> {
+        self.graph.slot(node)
+    }
+
+    /// Number of addressable nodes of the source graph.
+    pub(crate) fn num_nodes(&self) -> usize {
+        self.graph.num_nodes()
+    }
+
+    /// Number of recorded (non-input) nodes.
+    pub(crate) fn len(&self) -> usize {
+        self.graph.len()
+    }
+
+    /// Op code of recorded node `i`.
+    pub(crate) fn op(&self, i: usize) -> MultiAD {
+        self.graph.op(i)
+    }
+
+    /// Argument indices of recorded node `i`.
+    pub(crate) fn args(&self, i: usize) -> &[usize] {
+        self.graph.args(i)
+    }
+
+    /// Local partials of recorded node `i`, aligned with [`FlatTape::args`].
+    pub(crate) fn partials(&self, i: usize) -> &[f64] {
+        &self.ws.partials()[self.graph.arg_range(i)]
+    }
+
+    /// Propagate input tangents forward into `tangents`, one entry per node.
+    ///
+    /// A term whose tangent or partial is exactly zero is skipped rather
+    /// than multiplied, here and in every other sweep, so `0 · ∞` and
+    /// `0 · NaN` count as zero. An infinite or `NaN` partial that is unused,
+    /// such as `ln(a)` for a constant exponent or `sqrt` at zero scaled by
+    /// zero, then stays out of the result and forward and reverse mode agree.
+    pub(crate) fn push_forward(&self, input_tangent: &[f64], tangents: &mut Vec<f64>) {
+        tangents.clear();
+        tangents.extend_from_slice(input_tangent);
+        for i in 0..self.len() {
+            let tangent = self
+                .args(i)
+                .iter()
+                .zip(self.partials(i))
+                .filter(|&(&arg, &d)| tangents[arg] != 0.0 && d != 0.0)
+                .map(|(&arg, d)| tangents[arg] * d)
+                .sum();
+            tangents.push(tangent);
+        }
+    }
+
+    /// Propagate node cotangents backward in place.
+    ///
+    /// `cotangents` holds one seed per node on entry; on return its first
+    /// `num_inputs` entries hold the accumulated input cotangents.
+    pub(crate) fn pull_back(&self, cotangents: &mut [f64]) {
+        self.graph.pull_back(self.ws.partials(), cotangents);
+    }
+
+    /// Gradient of the output node scaled by `cotangent`.
+    pub(crate) fn gradient(&self, cotangent: f64) -> Vec<f64> {
+        self.gradient_wrt(cotangent, Wrt::Inputs)
+    }
+
+    /// Gradient of the output node with respect to inputs, parameters or
+    /// both (inputs first), scaled by `cotangent`.
+    pub(crate) fn gradient_wrt(&self, cotangent: f64, wrt: Wrt) -> Vec<f64> {
+        let mut cotangents = vec![0.0; self.values().len()];
+        cotangents[self.output_slot()] = cotangent;
+        self.pull_back(&mut cotangents);
+
+        let mut param_grads = vec![0.0; self.num_params];
+        for &(node, k) in self.graph.param_nodes() {
+            param_grads[k] += cotangents[self.num_inputs() + node];
+        }
+        cotangents.truncate(self.num_inputs());
+        match wrt {
+            Wrt::Inputs => cotangents,
+            Wrt::Params => param_grads,
+            Wrt::Both => {
+                cotangents.extend(param_grads);
+                cotangents
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::multi_ops;
+
+    #[test]
+    fn test_tape_layout() {
+        let exprs = multi_ops![(inp, 0), (inp, 1), (add, 0, 1), (sin, 0), (mul, 2, 3)];
+        let tape =
+            FlatTape::record(&exprs, &[0.6, 1.4], &[], SubgradientPolicy::default()).unwrap();
+
+        assert_eq!(tape.num_inputs(), 2);
+        assert_eq!(tape.len(), 3);
+        assert_eq!(tape.op(2), MultiAD::Mul);
+        assert_eq!(tape.args(0), &[0, 1]);
+        assert_eq!(tape.args(1), &[0]);
+        assert_eq!(tape.partials(1), &[0.6_f64.cos()]);
+        assert_eq!(tape.values().len(), 5);
+        assert_eq!(tape.output(), 0.6_f64.sin() * 2.0);
+    }
+
+    #[test]
+    fn test_tape_gradient_is_reusable() {
+        let exprs = multi_ops![(inp, 0), (inp, 1), (mul, 0, 1)];
+        let tape =
+            FlatTape::record(&exprs, &[3.0, 4.0], &[], SubgradientPolicy::default()).unwrap();
+        assert_eq!(tape.gradient(1.0), vec![4.0, 3.0]);
+        assert_eq!(tape.gradient(2.0), vec![8.0, 6.0]);
+    }
+
+    #[test]
+    fn test_tape_empty_graph() {
+        assert_eq!(
+            FlatTape::record(&[], &[], &[], SubgradientPolicy::default()).unwrap_err(),
+            crate::error::AutodiffError::EmptyGraph
+        );
+    }
+}
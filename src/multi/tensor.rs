@@ -0,0 +1,24 @@
+//! Graphs whose nodes hold vectors and small dense matrices.
+//!
+//! [`TensorAD`] graphs have the same layout as [`MultiAD`](crate::MultiAD)
+//! graphs, but each node holds a [`Tensor`]: a scalar, a vector or a
+//! row-major matrix. Shapes are inferred and checked before anything is
+//! evaluated, scalar ops apply elementwise with NumPy-style broadcasting, and
+//! the reverse pass returns each input's gradient with that input's shape,
+//! summing over any dimensions it was broadcast along.
+//!
+//! Matrix products, linear solves, determinants, inverses and Cholesky
+//! factors are ops of their own, with reverse-mode rules written in terms of
+//! the same small dense kernels as their forward passes.
+
+mod builder;
+mod graph;
+mod linalg;
+mod shape;
+mod tensor_ad;
+mod value;
+
+pub use builder::TensorGraphBuilder;
+pub use shape::Shape;
+pub use tensor_ad::TensorAD;
+pub use value::Tensor;
@@ -0,0 +1,327 @@
+//! Builder for tensor graphs that checks shapes as nodes are added.
+
+use super::shape::Shape;
+use super::tensor_ad::TensorAD;
+use crate::error::{AutodiffError, Result};
+use crate::multi::multi_ad::MultiAD;
+
+/// Builder for [`TensorAD`] graphs.
+///
+/// Unlike [`GraphBuilder`](crate::GraphBuilder), every method that adds an
+/// operation infers its shape on the spot and returns the new node's index,
+/// so a shape error points at the call that caused it rather than surfacing
+/// when the graph is first evaluated.
+///
+/// # Examples
+///
+/// ```
+/// use petite_ad::{Shape, Tensor, TensorAD, TensorGraphBuilder};
+///
+/// // Mean squared error of X ⊙ w summed per row, against targets y
+/// let mut b = TensorGraphBuilder::new(&[Shape::Matrix(3, 2), Shape::Vector(3), Shape::Vector(2)]);
+/// let (x, y, w) = (0, 1, 2);
+/// let scaled = b.mul(x, w).unwrap();
+/// let predicted = b.sum_axis(scaled, 1).unwrap();
+/// let residual = b.sub(predicted, y).unwrap();
+/// let squared = b.mul(residual, residual).unwrap();
+/// b.mean(squared).unwrap();
+///
+/// // Shapes are checked when the node is added
+/// assert!(b.add(x, y).is_err());
+///
+/// let inputs = [
+///     Tensor::from_rows(&[[1.0, 0.0], [0.0, 1.0], [1.0, 1.0]]).unwrap(),
+///     Tensor::vector([1.0, 2.0, 3.0]),
+///     Tensor::vector([1.0, 2.0]),
+/// ];
+/// let (loss, grad_fn) = TensorAD::compute_grad(&b.build(), &inputs).unwrap();
+/// assert_eq!(loss.as_scalar(), Some(0.0));
+/// assert_eq!(grad_fn(&Tensor::scalar(1.0))[2], Tensor::vector([0.0, 0.0]));
+/// ```
+#[derive(Debug, Clone)]
+pub struct TensorGraphBuilder {
+    /// Number of input tensors
+    num_inputs: usize,
+    /// Operations in the graph, starting with one `Inp` node per input
+    operations: Vec<(TensorAD, Vec<usize>)>,
+    /// Shape of each node
+    shapes: Vec<Shape>,
+}
+
+impl TensorGraphBuilder {
+    /// Creates a builder whose inputs have the given shapes.
+    ///
+    /// Input `k` is node `k`.
+    pub fn new(input_shapes: &[Shape]) -> Self {
+        Self {
+            num_inputs: input_shapes.len(),
+            operations: (0..input_shapes.len())
+                .map(|k| (TensorAD::Inp, vec![k]))
+                .collect(),
+            shapes: input_shapes.to_vec(),
+        }
+    }
+
+    /// Shape of a node, or `None` if there is no such node.
+    pub fn shape(&self, node: usize) -> Option<Shape> {
+        self.shapes.get(node).copied()
+    }
+
+    /// Adds any operation, checking its arguments and shape.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ArityError`, `InputCountMismatch`, `IndexOutOfBounds` or
+    /// `ShapeMismatch` if the node cannot be added; the builder is left
+    /// unchanged.
+    pub fn push(&mut self, op: TensorAD, args: &[usize]) -> Result<usize> {
+        AutodiffError::check_arity(op.op_name(), op.expected_arity(), args.len())?;
+        let num_inputs = self.num_inputs;
+        let shape = if op == TensorAD::Inp {
+            if args[0] >= num_inputs {
+                return Err(AutodiffError::InputCountMismatch {
+                    expected: args[0] + 1,
+                    actual: num_inputs,
+                });
+            }
+            self.shapes[args[0]]
+        } else {
+            let arg_shapes = args
+                .iter()
+                .map(|&index| {
+                    self.shape(index).ok_or(AutodiffError::IndexOutOfBounds {
+                        index,
+                        max_index: self.shapes.len().saturating_sub(1),
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            op.output_shape(&arg_shapes)?
+        };
+        self.operations.push((op, args.to_vec()));
+        self.shapes.push(shape);
+        Ok(self.shapes.len() - 1)
+    }
+
+    /// Adds a scalar constant, which broadcasts against any shape.
+    pub fn constant(&mut self, value: f64) -> usize {
+        self.operations.push((TensorAD::Const(value), vec![]));
+        self.shapes.push(Shape::Scalar);
+        self.shapes.len() - 1
+    }
+
+    /// Applies a scalar op elementwise, broadcasting its arguments.
+    ///
+    /// # Errors
+    ///
+    /// See [`TensorGraphBuilder::push`].
+    pub fn elementwise(&mut self, op: MultiAD, args: &[usize]) -> Result<usize> {
+        self.push(TensorAD::Elementwise(op), args)
+    }
+
+    /// Applies a unary scalar op to each element.
+    ///
+    /// # Errors
+    ///
+    /// See [`TensorGraphBuilder::push`].
+    pub fn map(&mut self, op: MultiAD, arg: usize) -> Result<usize> {
+        self.elementwise(op, &[arg])
+    }
+
+    /// Adds `a + b`, broadcasting.
+    ///
+    /// # Errors
+    ///
+    /// See [`TensorGraphBuilder::push`].
+    pub fn add(&mut self, a: usize, b: usize) -> Result<usize> {
+        self.elementwise(MultiAD::Add, &[a, b])
+    }
+
+    /// Adds `a - b`, broadcasting.
+    ///
+    /// # Errors
+    ///
+    /// See [`TensorGraphBuilder::push`].
+    pub fn sub(&mut self, a: usize, b: usize) -> Result<usize> {
+        self.elementwise(MultiAD::Sub, &[a, b])
+    }
+
+    /// Adds the elementwise product `a ⊙ b`, broadcasting.
+    ///
+    /// # Errors
+    ///
+    /// See [`TensorGraphBuilder::push`].
+    pub fn mul(&mut self, a: usize, b: usize) -> Result<usize> {
+        self.elementwise(MultiAD::Mul, &[a, b])
+    }
+
+    /// Adds the elementwise quotient `a / b`, broadcasting.
+    ///
+    /// # Errors
+    ///
+    /// See [`TensorGraphBuilder::push`].
+    pub fn div(&mut self, a: usize, b: usize) -> Result<usize> {
+        self.elementwise(MultiAD::Div, &[a, b])
+    }
+
+    /// Adds the sum of all elements of `arg`.
+    ///
+    /// # Errors
+    ///
+    /// See [`TensorGraphBuilder::push`].
+    pub fn sum(&mut self, arg: usize) -> Result<usize> {
+        self.push(TensorAD::Sum, &[arg])
+    }
+
+    /// Adds the mean of all elements of `arg`.
+    ///
+    /// # Errors
+    ///
+    /// See [`TensorGraphBuilder::push`].
+    pub fn mean(&mut self, arg: usize) -> Result<usize> {
+        self.push(TensorAD::Mean, &[arg])
+    }
+
+    /// Adds the sum of `arg` along `axis`.
+    ///
+    /// # Errors
+    ///
+    /// See [`TensorGraphBuilder::push`]; `arg` must have an axis `axis`.
+    pub fn sum_axis(&mut self, arg: usize, axis: usize) -> Result<usize> {
+        self.push(TensorAD::SumAxis(axis), &[arg])
+    }
+
+    /// Adds the matrix product `a · b`.
+    ///
+    /// # Errors
+    ///
+    /// See [`TensorGraphBuilder::push`]; `a` and `b` must be a matrix and a
+    /// matrix or vector whose inner dimensions agree, or a vector and a
+    /// matrix.
+    pub fn matmul(&mut self, a: usize, b: usize) -> Result<usize> {
+        self.push(TensorAD::MatMul, &[a, b])
+    }
+
+    /// Adds the inner product of two vectors.
+    ///
+    /// # Errors
+    ///
+    /// See [`TensorGraphBuilder::push`]; `a` and `b` must be vectors of the
+    /// same length.
+    pub fn dot(&mut self, a: usize, b: usize) -> Result<usize> {
+        self.push(TensorAD::Dot, &[a, b])
+    }
+
+    /// Adds the transpose of a matrix.
+    ///
+    /// # Errors
+    ///
+    /// See [`TensorGraphBuilder::push`]; `arg` must be a matrix.
+    pub fn transpose(&mut self, arg: usize) -> Result<usize> {
+        self.push(TensorAD::Transpose, &[arg])
+    }
+
+    /// Adds the solution `x` of `a x = b`.
+    ///
+    /// # Errors
+    ///
+    /// See [`TensorGraphBuilder::push`]; `a` must be square and `b` a vector
+    /// or matrix with as many rows.
+    pub fn solve(&mut self, a: usize, b: usize) -> Result<usize> {
+        self.push(TensorAD::Solve, &[a, b])
+    }
+
+    /// Adds the determinant of a square matrix.
+    ///
+    /// # Errors
+    ///
+    /// See [`TensorGraphBuilder::push`]; `arg` must be a square matrix.
+    pub fn det(&mut self, arg: usize) -> Result<usize> {
+        self.push(TensorAD::Det, &[arg])
+    }
+
+    /// Adds ln|det| of a square matrix.
+    ///
+    /// # Errors
+    ///
+    /// See [`TensorGraphBuilder::push`]; `arg` must be a square matrix.
+    pub fn logdet(&mut self, arg: usize) -> Result<usize> {
+        self.push(TensorAD::LogDet, &[arg])
+    }
+
+    /// Adds the inverse of a square matrix.
+    ///
+    /// # Errors
+    ///
+    /// See [`TensorGraphBuilder::push`]; `arg` must be a square matrix.
+    pub fn inverse(&mut self, arg: usize) -> Result<usize> {
+        self.push(TensorAD::Inverse, &[arg])
+    }
+
+    /// Adds the lower-triangular Cholesky factor of a symmetric positive
+    /// definite matrix.
+    ///
+    /// # Errors
+    ///
+    /// See [`TensorGraphBuilder::push`]; `arg` must be a square matrix.
+    pub fn cholesky(&mut self, arg: usize) -> Result<usize> {
+        self.push(TensorAD::Cholesky, &[arg])
+    }
+
+    /// Returns the graph built so far; its output is the last node added.
+    pub fn build(&self) -> Vec<(TensorAD, Vec<usize>)> {
+        self.operations.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shapes_are_tracked() {
+        let mut b = TensorGraphBuilder::new(&[Shape::Matrix(2, 3), Shape::Vector(3)]);
+        let sum = b.add(0, 1).unwrap();
+        assert_eq!(b.shape(sum), Some(Shape::Matrix(2, 3)));
+        let rows = b.sum_axis(sum, 1).unwrap();
+        assert_eq!(b.shape(rows), Some(Shape::Vector(2)));
+        let half = b.constant(0.5);
+        let scaled = b.mul(rows, half).unwrap();
+        let out = b.map(MultiAD::Exp, scaled).unwrap();
+        assert_eq!(b.shape(out), Some(Shape::Vector(2)));
+        assert_eq!(
+            TensorAD::shapes(&b.build(), &[Shape::Matrix(2, 3), Shape::Vector(3)])
+                .unwrap()
+                .last(),
+            Some(&Shape::Vector(2))
+        );
+    }
+
+    #[test]
+    fn test_errors_leave_builder_unchanged() {
+        let mut b = TensorGraphBuilder::new(&[Shape::Vector(2), Shape::Vector(3)]);
+        let err = b.mul(0, 1).unwrap_err();
+        assert_eq!(
+            err,
+            AutodiffError::ShapeMismatch {
+                operation: "Mul",
+                shapes: vec![Shape::Vector(2), Shape::Vector(3)]
+            }
+        );
+        assert_eq!(err.to_string(), "Shape mismatch in Mul: got [2], [3]");
+        assert_eq!(
+            b.add(0, 5),
+            Err(AutodiffError::IndexOutOfBounds {
+                index: 5,
+                max_index: 1
+            })
+        );
+        assert_eq!(
+            b.elementwise(MultiAD::Add, &[0]),
+            Err(AutodiffError::arity("Add", 2, 1))
+        );
+        assert!(b.sum_axis(0, 1).is_err());
+        assert_eq!(b.build().len(), 2);
+        assert_eq!(b.push(TensorAD::Inp, &[1]), Ok(2));
+        assert_eq!(b.shape(2), Some(Shape::Vector(3)));
+    }
+}
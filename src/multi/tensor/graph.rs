@@ -0,0 +1,575 @@
+//! Shape checking, evaluation and reverse-mode gradients of tensor graphs.
+
+use super::shape::Shape;
+use super::tensor_ad::TensorAD;
+use super::value::Tensor;
+use crate::error::Result;
+use crate::multi::types::TensorResultBox;
+use crate::multi::validate;
+
+type Graph = [(TensorAD, Vec<usize>)];
+
+impl TensorAD {
+    /// Check a graph and infer the shape of every node.
+    ///
+    /// Performs the same structural checks as
+    /// [`MultiAD::validate`](crate::MultiAD::validate), in the same order,
+    /// then reports `ShapeMismatch` for the first node whose argument shapes
+    /// the op cannot combine. The returned shapes are indexed by node, so
+    /// the last one is the shape of the output.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first problem found as `Err(AutodiffError)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petite_ad::{AutodiffError, MultiAD, Shape, TensorAD};
+    ///
+    /// let exprs = vec![
+    ///     (TensorAD::Inp, vec![0]),
+    ///     (TensorAD::Inp, vec![1]),
+    ///     (TensorAD::Elementwise(MultiAD::Mul), vec![0, 1]),
+    /// ];
+    /// let shapes = TensorAD::shapes(&exprs, &[Shape::Matrix(4, 3), Shape::Vector(3)]).unwrap();
+    /// assert_eq!(shapes[2], Shape::Matrix(4, 3));
+    ///
+    /// assert_eq!(
+    ///     TensorAD::shapes(&exprs, &[Shape::Matrix(4, 3), Shape::Vector(4)]),
+    ///     Err(AutodiffError::ShapeMismatch {
+    ///         operation: "Mul",
+    ///         shapes: vec![Shape::Matrix(4, 3), Shape::Vector(4)],
+    ///     })
+    /// );
+    /// ```
+    pub fn shapes(exprs: &Graph, input_shapes: &[Shape]) -> Result<Vec<Shape>> {
+        // Graphs without Inp nodes take their inputs at the first indices
+        let mut shapes = if exprs.iter().any(|(op, _)| *op == TensorAD::Inp) {
+            Vec::with_capacity(exprs.len())
+        } else {
+            input_shapes.to_vec()
+        };
+        validate::walk(exprs, input_shapes.len(), |op, args| {
+            let shape = match op {
+                TensorAD::Inp => input_shapes[args[0]],
+                _ => {
+                    let arg_shapes: Vec<Shape> = args.iter().map(|&i| shapes[i]).collect();
+                    op.output_shape(&arg_shapes)?
+                }
+            };
+            shapes.push(shape);
+            Ok(())
+        })?;
+        Ok(shapes)
+    }
+
+    /// Evaluate a graph.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(AutodiffError)` if the graph fails [`TensorAD::shapes`]
+    /// for the shapes of `inputs`.
+    pub fn compute(exprs: &Graph, inputs: &[Tensor]) -> Result<Tensor> {
+        let mut values = evaluate(exprs, inputs)?;
+        Ok(values.pop().expect("checked graphs are not empty"))
+    }
+
+    /// Evaluate a graph and return a closure computing its gradient.
+    ///
+    /// The closure takes the cotangent of the output, which must have the
+    /// output's shape (usually `Tensor::scalar(1.0)` for a scalar loss), and
+    /// returns one gradient per input with that input's shape. Gradients of
+    /// broadcast arguments are summed over the broadcast dimensions.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(AutodiffError)` if the graph fails [`TensorAD::shapes`]
+    /// for the shapes of `inputs`.
+    ///
+    /// # Panics
+    ///
+    /// The closure panics if the cotangent does not have the output's shape.
+    pub fn compute_grad(exprs: &Graph, inputs: &[Tensor]) -> Result<TensorResultBox> {
+        let values = evaluate(exprs, inputs)?;
+        let output = values.last().expect("checked graphs are not empty").clone();
+        let exprs = exprs.to_vec();
+        let input_shapes: Vec<Shape> = inputs.iter().map(Tensor::shape).collect();
+
+        let grad_fn = move |cotangent: &Tensor| {
+            assert_eq!(
+                cotangent.shape(),
+                values[values.len() - 1].shape(),
+                "cotangent must have the output's shape"
+            );
+            backward(&exprs, &values, &input_shapes, cotangent)
+        };
+        Ok((output, Box::new(grad_fn)))
+    }
+}
+
+/// Values of every node, indexed like the shapes from [`TensorAD::shapes`].
+fn evaluate(exprs: &Graph, inputs: &[Tensor]) -> Result<Vec<Tensor>> {
+    let input_shapes: Vec<Shape> = inputs.iter().map(Tensor::shape).collect();
+    let shapes = TensorAD::shapes(exprs, &input_shapes)?;
+    let first_node = shapes.len() - exprs.len();
+    let mut values: Vec<Tensor> = inputs[..first_node].to_vec();
+    values.reserve(exprs.len());
+    for (op, args) in exprs {
+        let value = match op {
+            TensorAD::Inp => inputs[args[0]].clone(),
+            _ => {
+                let arg_values: Vec<&Tensor> = args.iter().map(|&i| &values[i]).collect();
+                op.forward(&arg_values)?
+            }
+        };
+        values.push(value);
+    }
+    Ok(values)
+}
+
+/// Reverse sweep from `cotangent` at the output to the inputs.
+fn backward(
+    exprs: &Graph,
+    values: &[Tensor],
+    input_shapes: &[Shape],
+    cotangent: &Tensor,
+) -> Vec<Tensor> {
+    let first_node = values.len() - exprs.len();
+    let mut adjoints: Vec<Tensor> = values.iter().map(|v| Tensor::zeros(v.shape())).collect();
+    let mut grads: Vec<Tensor> = input_shapes.iter().map(|&s| Tensor::zeros(s)).collect();
+    adjoints[values.len() - 1] = cotangent.clone();
+
+    for (node, (op, args)) in exprs.iter().enumerate().rev() {
+        let node = first_node + node;
+        match op {
+            TensorAD::Inp => grads[args[0]].accumulate(&adjoints[node]),
+            TensorAD::Const(_) => {}
+            _ => {
+                let arg_values: Vec<&Tensor> = args.iter().map(|&i| &values[i]).collect();
+                let mut contributions: Vec<Tensor> = arg_values
+                    .iter()
+                    .map(|v| Tensor::zeros(v.shape()))
+                    .collect();
+                op.backward(
+                    &arg_values,
+                    &values[node],
+                    &adjoints[node],
+                    &mut contributions,
+                );
+                for (&i, contribution) in args.iter().zip(&contributions) {
+                    adjoints[i].accumulate(contribution);
+                }
+            }
+        }
+    }
+
+    for (grad, adjoint) in grads.iter_mut().zip(&adjoints[..first_node]) {
+        grad.accumulate(adjoint);
+    }
+    grads
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::AutodiffError;
+    use crate::multi::multi_ad::MultiAD;
+    use crate::multi::tensor::TensorGraphBuilder;
+    use crate::test_utils::approx_eq_eps as approx_eq;
+
+    /// Central-difference gradient of a scalar-valued graph.
+    fn numeric_gradient(exprs: &Graph, inputs: &[Tensor]) -> Vec<Tensor> {
+        let h = 1e-6;
+        let f = |inputs: &[Tensor]| {
+            TensorAD::compute(exprs, inputs)
+                .unwrap()
+                .as_scalar()
+                .unwrap()
+        };
+        inputs
+            .iter()
+            .enumerate()
+            .map(|(i, input)| {
+                let data = (0..input.data().len())
+                    .map(|k| {
+                        let (mut plus, mut minus) = (inputs.to_vec(), inputs.to_vec());
+                        plus[i].data_mut()[k] += h;
+                        minus[i].data_mut()[k] -= h;
+                        (f(&plus) - f(&minus)) / (2.0 * h)
+                    })
+                    .collect::<Vec<_>>();
+                Tensor::from_shape(input.shape(), data).unwrap()
+            })
+            .collect()
+    }
+
+    fn assert_gradient_matches(exprs: &Graph, inputs: &[Tensor]) {
+        let (_, grad_fn) = TensorAD::compute_grad(exprs, inputs).unwrap();
+        let grads = grad_fn(&Tensor::scalar(1.0));
+        let expected = numeric_gradient(exprs, inputs);
+        for (grad, expected) in grads.iter().zip(&expected) {
+            assert_eq!(grad.shape(), expected.shape());
+            for (&g, &e) in grad.data().iter().zip(expected.data()) {
+                let tolerance = 1e-6 * e.abs().max(1.0);
+                assert!(approx_eq(g, e, tolerance), "{grad:?} vs {expected:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_broadcast_gradients_against_finite_differences() {
+        // Σ tanh(X · w + b) · c + mean(X)
+        let exprs = vec![
+            (TensorAD::Inp, vec![0]),
+            (TensorAD::Inp, vec![1]),
+            (TensorAD::Inp, vec![2]),
+            (TensorAD::Inp, vec![3]),
+            (TensorAD::Elementwise(MultiAD::Mul), vec![0, 1]),
+            (TensorAD::Elementwise(MultiAD::Add), vec![4, 2]),
+            (TensorAD::Elementwise(MultiAD::Tanh), vec![5]),
+            (TensorAD::Elementwise(MultiAD::Mul), vec![6, 3]),
+            (TensorAD::Sum, vec![7]),
+            (TensorAD::Mean, vec![0]),
+            (TensorAD::Elementwise(MultiAD::Add), vec![8, 9]),
+        ];
+        let inputs = [
+            Tensor::from_rows(&[[0.5, -1.0, 0.3], [0.2, 0.8, -0.4]]).unwrap(),
+            Tensor::vector([0.7, -0.2, 1.1]),
+            Tensor::matrix(2, 1, vec![0.1, -0.3]).unwrap(),
+            Tensor::scalar(1.5),
+        ];
+        assert_gradient_matches(&exprs, &inputs);
+    }
+
+    #[test]
+    fn test_sum_axis_and_clamp_gradients() {
+        // Σ clamp(sum_axis(X, 1), lo, 2) ⊙ v with a per-row lower bound
+        let exprs = vec![
+            (TensorAD::Inp, vec![0]),
+            (TensorAD::Inp, vec![1]),
+            (TensorAD::Inp, vec![2]),
+            (TensorAD::SumAxis(1), vec![0]),
+            (TensorAD::Const(2.0), vec![]),
+            (TensorAD::Elementwise(MultiAD::Clamp), vec![3, 1, 4]),
+            (TensorAD::Elementwise(MultiAD::Mul), vec![5, 2]),
+            (TensorAD::SumAxis(0), vec![6]),
+        ];
+        let inputs = [
+            Tensor::from_rows(&[[0.5, 0.4], [0.2, -0.8], [1.5, 1.0]]).unwrap(),
+            Tensor::vector([0.0, -0.1, 0.0]),
+            Tensor::vector([1.0, 2.0, 3.0]),
+        ];
+        assert_gradient_matches(&exprs, &inputs);
+        let (_, grad_fn) = TensorAD::compute_grad(&exprs, &inputs).unwrap();
+        let grads = grad_fn(&Tensor::scalar(1.0));
+        // Row 0 passes through, row 1 is clamped below, row 2 above
+        assert_eq!(grads[0].data(), &[1.0, 1.0, 0.0, 0.0, 0.0, 0.0]);
+        assert_eq!(grads[1].data(), &[0.0, 2.0, 0.0]);
+    }
+
+    #[test]
+    fn test_implicit_inputs_and_reused_nodes() {
+        // Without Inp nodes the inputs sit at 0 and 1; x is used twice
+        let exprs = vec![
+            (TensorAD::Elementwise(MultiAD::Mul), vec![0, 0]),
+            (TensorAD::Elementwise(MultiAD::Mul), vec![2, 1]),
+            (TensorAD::Sum, vec![3]),
+        ];
+        let inputs = [Tensor::vector([1.0, 2.0, 3.0]), Tensor::scalar(0.5)];
+        let (value, grad_fn) = TensorAD::compute_grad(&exprs, &inputs).unwrap();
+        assert_eq!(value.as_scalar(), Some(7.0));
+        let grads = grad_fn(&Tensor::scalar(2.0));
+        assert_eq!(grads[0], Tensor::vector([2.0, 4.0, 6.0]));
+        assert_eq!(grads[1].as_scalar(), Some(28.0));
+    }
+
+    #[test]
+    fn test_vector_output_cotangent() {
+        let exprs = vec![
+            (TensorAD::Inp, vec![0]),
+            (TensorAD::Elementwise(MultiAD::Exp), vec![0]),
+        ];
+        let inputs = [Tensor::vector([0.0, 1.0])];
+        let (value, grad_fn) = TensorAD::compute_grad(&exprs, &inputs).unwrap();
+        assert_eq!(value, Tensor::vector([1.0, 1f64.exp()]));
+        let grads = grad_fn(&Tensor::vector([1.0, -1.0]));
+        assert_eq!(grads[0], Tensor::vector([1.0, -(1f64.exp())]));
+    }
+
+    #[test]
+    #[should_panic(expected = "cotangent must have the output's shape")]
+    fn test_cotangent_shape_is_checked() {
+        let exprs = vec![(TensorAD::Inp, vec![0])];
+        let (_, grad_fn) = TensorAD::compute_grad(&exprs, &[Tensor::vector([1.0, 2.0])]).unwrap();
+        grad_fn(&Tensor::scalar(1.0));
+    }
+
+    #[test]
+    fn test_structural_errors() {
+        let inputs = [Shape::Vector(2)];
+        assert_eq!(
+            TensorAD::shapes(&[], &inputs),
+            Err(AutodiffError::EmptyGraph)
+        );
+        assert_eq!(
+            TensorAD::shapes(&[(TensorAD::Inp, vec![1])], &inputs),
+            Err(AutodiffError::InputCountMismatch {
+                expected: 2,
+                actual: 1
+            })
+        );
+        assert_eq!(
+            TensorAD::shapes(
+                &[(TensorAD::Inp, vec![0]), (TensorAD::Sum, vec![1])],
+                &inputs
+            ),
+            Err(AutodiffError::ForwardReference { node: 1, index: 1 })
+        );
+        assert_eq!(
+            TensorAD::shapes(
+                &[(TensorAD::Inp, vec![0]), (TensorAD::Sum, vec![0, 0])],
+                &inputs
+            ),
+            Err(AutodiffError::arity("Sum", 1, 2))
+        );
+        assert_eq!(
+            TensorAD::compute(
+                &[(TensorAD::Inp, vec![0]), (TensorAD::Const(1.0), vec![])],
+                &[Tensor::scalar(1.0), Tensor::scalar(2.0)]
+            ),
+            Err(AutodiffError::MissingInput { input: 1 })
+        );
+    }
+
+    #[test]
+    fn test_linear_regression_converges() {
+        // y = 2·x₀ - 3·x₁ + 0.5, fitted by gradient descent on the mean
+        // squared error of sum_axis(X ⊙ w, 1) + b
+        let exprs = vec![
+            (TensorAD::Inp, vec![0]),
+            (TensorAD::Inp, vec![1]),
+            (TensorAD::Inp, vec![2]),
+            (TensorAD::Inp, vec![3]),
+            (TensorAD::Elementwise(MultiAD::Mul), vec![0, 2]),
+            (TensorAD::SumAxis(1), vec![4]),
+            (TensorAD::Elementwise(MultiAD::Add), vec![5, 3]),
+            (TensorAD::Elementwise(MultiAD::Sub), vec![6, 1]),
+            (TensorAD::Const(2.0), vec![]),
+            (TensorAD::Elementwise(MultiAD::Pow), vec![7, 8]),
+            (TensorAD::Mean, vec![9]),
+        ];
+        let rows = [[1.0, 0.0], [0.0, 1.0], [1.0, 1.0], [2.0, -1.0], [-1.0, 0.5]];
+        let targets: Vec<f64> = rows.iter().map(|r| 2.0 * r[0] - 3.0 * r[1] + 0.5).collect();
+        let mut inputs = [
+            Tensor::from_rows(&rows).unwrap(),
+            Tensor::vector(targets),
+            Tensor::zeros(Shape::Vector(2)),
+            Tensor::scalar(0.0),
+        ];
+        for _ in 0..2000 {
+            let (_, grad_fn) = TensorAD::compute_grad(&exprs, &inputs).unwrap();
+            let grads = grad_fn(&Tensor::scalar(1.0));
+            for (param, grad) in inputs[2..].iter_mut().zip(&grads[2..]) {
+                for (p, g) in param.data_mut().iter_mut().zip(grad.data()) {
+                    *p -= 0.1 * g;
+                }
+            }
+        }
+        assert!(approx_eq(inputs[2].data()[0], 2.0, 1e-8));
+        assert!(approx_eq(inputs[2].data()[1], -3.0, 1e-8));
+        assert!(approx_eq(inputs[3].data()[0], 0.5, 1e-8));
+    }
+
+    /// `Σ op(args) ⊙ w`, with the weights `w` as the last input, so the
+    /// cotangent reaching `op` is `w`.
+    fn weighted(op: TensorAD, arity: usize) -> Vec<(TensorAD, Vec<usize>)> {
+        let mut exprs: Vec<_> = (0..=arity).map(|k| (TensorAD::Inp, vec![k])).collect();
+        exprs.push((op, (0..arity).collect()));
+        exprs.push((TensorAD::Elementwise(MultiAD::Mul), vec![arity + 1, arity]));
+        exprs.push((TensorAD::Sum, vec![arity + 2]));
+        exprs
+    }
+
+    fn matrix(rows: usize, cols: usize, data: &[f64]) -> Tensor {
+        Tensor::matrix(rows, cols, data.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_linalg_gradients_against_finite_differences() {
+        let a = matrix(3, 3, &[2.0, 0.5, -1.0, 0.3, 3.0, 0.4, -0.7, 0.2, 1.5]);
+        let spd = matrix(3, 3, &[4.0, 1.2, -0.6, 1.2, 3.0, 0.5, -0.6, 0.5, 2.0]);
+        let w33 = matrix(3, 3, &[0.3, -1.1, 0.8, 0.5, 0.2, -0.4, 1.3, -0.6, 0.9]);
+        let b = Tensor::vector([1.0, -2.0, 0.5]);
+        let wide = matrix(2, 3, &[0.4, -0.3, 1.2, 0.9, 0.1, -0.8]);
+        let tall = matrix(3, 2, &[0.7, -0.2, 0.5, 1.1, -1.4, 0.6]);
+        let cases = [
+            (
+                TensorAD::MatMul,
+                vec![
+                    wide.clone(),
+                    tall.clone(),
+                    matrix(2, 2, &[0.3, -1.1, 0.8, 0.5]),
+                ],
+            ),
+            (
+                TensorAD::MatMul,
+                vec![wide.clone(), b.clone(), Tensor::vector([0.5, -1.5])],
+            ),
+            (
+                TensorAD::MatMul,
+                vec![b.clone(), tall.clone(), Tensor::vector([2.0, 1.0])],
+            ),
+            (
+                TensorAD::Dot,
+                vec![
+                    b.clone(),
+                    Tensor::vector([0.3, 0.1, -0.2]),
+                    Tensor::scalar(1.5),
+                ],
+            ),
+            (TensorAD::Transpose, vec![wide.clone(), tall.clone()]),
+            (
+                TensorAD::Solve,
+                vec![a.clone(), b.clone(), Tensor::vector([0.2, -0.7, 1.0])],
+            ),
+            (
+                TensorAD::Solve,
+                vec![
+                    a.clone(),
+                    tall.clone(),
+                    matrix(3, 2, &[1.0, -0.5, 0.2, 0.8, -0.3, 0.6]),
+                ],
+            ),
+            (TensorAD::Det, vec![a.clone(), Tensor::scalar(0.7)]),
+            // Singular matrices, where det A · A⁻ᵀ would be 0 · ∞
+            (
+                TensorAD::Det,
+                vec![matrix(2, 2, &[1.0, 2.0, 2.0, 4.0]), Tensor::scalar(1.0)],
+            ),
+            (
+                TensorAD::Det,
+                vec![
+                    matrix(3, 3, &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0]),
+                    Tensor::scalar(0.7),
+                ],
+            ),
+            (TensorAD::LogDet, vec![a.clone(), Tensor::scalar(1.0)]),
+            // ln|det| with a negative determinant
+            (
+                TensorAD::LogDet,
+                vec![matrix(2, 2, &[0.5, 2.0, 1.5, -1.0]), Tensor::scalar(1.0)],
+            ),
+            (TensorAD::Inverse, vec![a.clone(), w33.clone()]),
+            (TensorAD::Cholesky, vec![spd, w33]),
+        ];
+        for (op, inputs) in cases {
+            let arity = inputs.len() - 1;
+            let exprs = weighted(op, arity);
+            assert_gradient_matches(&exprs, &inputs);
+        }
+    }
+
+    #[test]
+    fn test_linalg_values() {
+        let a = matrix(2, 2, &[4.0, 2.0, 2.0, 3.0]);
+        let value = |op: TensorAD, args: Vec<Tensor>| {
+            let mut exprs: Vec<_> = (0..args.len()).map(|k| (TensorAD::Inp, vec![k])).collect();
+            exprs.push((op, (0..args.len()).collect()));
+            TensorAD::compute(&exprs, &args).unwrap()
+        };
+        assert_eq!(value(TensorAD::Det, vec![a.clone()]).as_scalar(), Some(8.0));
+        assert!(approx_eq(
+            value(TensorAD::LogDet, vec![a.clone()])
+                .as_scalar()
+                .unwrap(),
+            8f64.ln(),
+            1e-15
+        ));
+        let x = value(TensorAD::Solve, vec![a.clone(), Tensor::vector([2.0, 1.0])]);
+        assert_eq!(x, Tensor::vector([0.5, 0.0]));
+        let inverse = value(TensorAD::Inverse, vec![a.clone()]);
+        assert_eq!(inverse, matrix(2, 2, &[0.375, -0.25, -0.25, 0.5]));
+        let l = value(TensorAD::Cholesky, vec![a.clone()]);
+        assert_eq!(l.get(0, 1), 0.0);
+        let l_t = value(TensorAD::Transpose, vec![l.clone()]);
+        let product = value(TensorAD::MatMul, vec![l, l_t]);
+        for (&p, &e) in product.data().iter().zip(a.data()) {
+            assert!(approx_eq(p, e, 1e-15));
+        }
+        assert_eq!(
+            value(
+                TensorAD::Dot,
+                vec![Tensor::vector([1.0, 2.0]), Tensor::vector([3.0, 4.0])]
+            ),
+            Tensor::scalar(11.0)
+        );
+    }
+
+    #[test]
+    fn test_linalg_shape_errors() {
+        use Shape::*;
+        let check = |op: TensorAD, shapes: &[Shape]| {
+            let mut exprs: Vec<_> = (0..shapes.len())
+                .map(|k| (TensorAD::Inp, vec![k]))
+                .collect();
+            exprs.push((op, (0..shapes.len()).collect()));
+            TensorAD::shapes(&exprs, shapes).map(|s| s[shapes.len()])
+        };
+        assert_eq!(
+            check(TensorAD::MatMul, &[Matrix(2, 3), Matrix(3, 4)]),
+            Ok(Matrix(2, 4))
+        );
+        assert_eq!(
+            check(TensorAD::MatMul, &[Matrix(2, 3), Matrix(2, 3)]),
+            Err(AutodiffError::ShapeMismatch {
+                operation: "MatMul",
+                shapes: vec![Matrix(2, 3), Matrix(2, 3)]
+            })
+        );
+        assert!(check(TensorAD::MatMul, &[Vector(3), Vector(3)]).is_err());
+        assert!(check(TensorAD::Dot, &[Matrix(1, 3), Vector(3)]).is_err());
+        assert!(check(TensorAD::Transpose, &[Vector(3)]).is_err());
+        assert_eq!(
+            check(TensorAD::Solve, &[Matrix(3, 3), Matrix(3, 2)]),
+            Ok(Matrix(3, 2))
+        );
+        assert!(check(TensorAD::Solve, &[Matrix(3, 2), Vector(3)]).is_err());
+        assert!(check(TensorAD::Solve, &[Matrix(3, 3), Vector(2)]).is_err());
+        assert!(check(TensorAD::Det, &[Matrix(2, 3)]).is_err());
+        assert_eq!(check(TensorAD::Cholesky, &[Matrix(2, 2)]), Ok(Matrix(2, 2)));
+        assert!(check(TensorAD::Inverse, &[Scalar]).is_err());
+    }
+
+    #[test]
+    fn test_ridge_solve_inside_loss() {
+        // Residual loss of the ridge fit w = (XᵀX + λI)⁻¹ Xᵀ y, differentiated
+        // through the solve with respect to the data and λ
+        let mut b = TensorGraphBuilder::new(&[
+            Shape::Matrix(4, 2),
+            Shape::Vector(4),
+            Shape::Scalar,
+            Shape::Matrix(2, 2),
+        ]);
+        let (x, y, lambda, identity) = (0, 1, 2, 3);
+        let x_t = b.transpose(x).unwrap();
+        let gram = b.matmul(x_t, x).unwrap();
+        let ridge = b.mul(lambda, identity).unwrap();
+        let system = b.add(gram, ridge).unwrap();
+        let rhs = b.matmul(x_t, y).unwrap();
+        let w = b.solve(system, rhs).unwrap();
+        let fitted = b.matmul(x, w).unwrap();
+        let residual = b.sub(fitted, y).unwrap();
+        let squared = b.mul(residual, residual).unwrap();
+        b.sum(squared).unwrap();
+        let exprs = b.build();
+
+        let inputs = [
+            matrix(4, 2, &[1.0, 0.5, -0.3, 1.2, 0.8, -0.9, 1.5, 0.1]),
+            Tensor::vector([1.1, 0.4, -0.7, 2.0]),
+            Tensor::scalar(0.3),
+            matrix(2, 2, &[1.0, 0.0, 0.0, 1.0]),
+        ];
+        assert_gradient_matches(&exprs, &inputs);
+    }
+}
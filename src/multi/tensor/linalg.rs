@@ -0,0 +1,359 @@
+//! Dense linear-algebra kernels for the matrix ops.
+//!
+//! Matrices are small and row-major, so everything here is a direct loop:
+//! products are triple loops, `Solve`, `Det`, `LogDet` and `Inverse` share an
+//! LU factorization with partial pivoting, and `Cholesky` is the textbook
+//! column-by-column factorization. Singular and indefinite matrices are not
+//! errors; as with the scalar ops, they produce infinities or `NaN`, except
+//! for the gradient of `Det`, which stays finite.
+
+/// `a · b` for an `m × k` matrix `a` and a `k × n` matrix `b`.
+pub(crate) fn matmul(a: &[f64], b: &[f64], m: usize, k: usize, n: usize) -> Vec<f64> {
+    let mut out = vec![0.0; m * n];
+    for (out_row, a_row) in out.chunks_mut(n.max(1)).zip(a.chunks(k.max(1))).take(m) {
+        for (&a_ip, b_row) in a_row.iter().zip(b.chunks(n.max(1))) {
+            for (o, &b_pj) in out_row.iter_mut().zip(b_row) {
+                *o += a_ip * b_pj;
+            }
+        }
+    }
+    out
+}
+
+/// Transpose of a `rows × cols` matrix.
+pub(crate) fn transpose(a: &[f64], rows: usize, cols: usize) -> Vec<f64> {
+    let mut out = vec![0.0; rows * cols];
+    for (i, a_row) in a.chunks(cols.max(1)).enumerate().take(rows) {
+        for (j, &v) in a_row.iter().enumerate() {
+            out[j * rows + i] = v;
+        }
+    }
+    out
+}
+
+/// LU factorization `P A = L U` of a square matrix, with partial pivoting.
+///
+/// `L` has a unit diagonal and shares storage with `U`. A column with no
+/// nonzero pivot is left as it is, so singular matrices factor without
+/// error and later divide by zero.
+pub(crate) struct Lu {
+    n: usize,
+    factors: Vec<f64>,
+    /// `perm[i]` is the row of `A` that ends up in row `i`
+    perm: Vec<usize>,
+    /// Sign of the permutation, ±1
+    sign: f64,
+}
+
+impl Lu {
+    pub(crate) fn new(a: &[f64], n: usize) -> Self {
+        let mut factors = a.to_vec();
+        let mut perm: Vec<usize> = (0..n).collect();
+        let mut sign = 1.0;
+        for col in 0..n {
+            let pivot = (col..n)
+                .max_by(|&i, &j| {
+                    let (a, b) = (factors[i * n + col].abs(), factors[j * n + col].abs());
+                    a.total_cmp(&b)
+                })
+                .expect("col < n");
+            if pivot != col {
+                for j in 0..n {
+                    factors.swap(col * n + j, pivot * n + j);
+                }
+                perm.swap(col, pivot);
+                sign = -sign;
+            }
+            let diagonal = factors[col * n + col];
+            if diagonal == 0.0 {
+                continue;
+            }
+            for i in col + 1..n {
+                let factor = factors[i * n + col] / diagonal;
+                factors[i * n + col] = factor;
+                for j in col + 1..n {
+                    factors[i * n + j] -= factor * factors[col * n + j];
+                }
+            }
+        }
+        Lu {
+            n,
+            factors,
+            perm,
+            sign,
+        }
+    }
+
+    /// Determinant: the signed product of the pivots.
+    pub(crate) fn det(&self) -> f64 {
+        self.sign * self.diagonal().product::<f64>()
+    }
+
+    /// ln|det A|, summed from the pivots so it does not overflow.
+    pub(crate) fn log_abs_det(&self) -> f64 {
+        self.diagonal().map(|d| d.abs().ln()).sum()
+    }
+
+    fn diagonal(&self) -> impl Iterator<Item = f64> + '_ {
+        (0..self.n).map(|i| self.factors[i * self.n + i])
+    }
+
+    /// `X` solving `A X = B` for an `n × cols` right-hand side `B`.
+    pub(crate) fn solve(&self, b: &[f64], cols: usize) -> Vec<f64> {
+        let n = self.n;
+        let lu = &self.factors;
+        let mut x = vec![0.0; n * cols];
+        for (i, &row) in self.perm.iter().enumerate() {
+            x[i * cols..(i + 1) * cols].copy_from_slice(&b[row * cols..(row + 1) * cols]);
+        }
+        // L Y = P B, then U X = Y
+        for i in 0..n {
+            for p in 0..i {
+                for c in 0..cols {
+                    x[i * cols + c] -= lu[i * n + p] * x[p * cols + c];
+                }
+            }
+        }
+        for i in (0..n).rev() {
+            for p in i + 1..n {
+                for c in 0..cols {
+                    x[i * cols + c] -= lu[i * n + p] * x[p * cols + c];
+                }
+            }
+            for c in 0..cols {
+                x[i * cols + c] /= lu[i * n + i];
+            }
+        }
+        x
+    }
+
+    /// `X` solving `Aᵀ X = B` for an `n × cols` right-hand side `B`.
+    pub(crate) fn solve_transpose(&self, b: &[f64], cols: usize) -> Vec<f64> {
+        let n = self.n;
+        let lu = &self.factors;
+        let mut w = b.to_vec();
+        // Aᵀ = Uᵀ Lᵀ P: Uᵀ Z = B, then Lᵀ W = Z, then X = Pᵀ W
+        for i in 0..n {
+            for p in 0..i {
+                for c in 0..cols {
+                    w[i * cols + c] -= lu[p * n + i] * w[p * cols + c];
+                }
+            }
+            for c in 0..cols {
+                w[i * cols + c] /= lu[i * n + i];
+            }
+        }
+        for i in (0..n).rev() {
+            for p in i + 1..n {
+                for c in 0..cols {
+                    w[i * cols + c] -= lu[p * n + i] * w[p * cols + c];
+                }
+            }
+        }
+        let mut x = vec![0.0; n * cols];
+        for (i, &row) in self.perm.iter().enumerate() {
+            x[row * cols..(row + 1) * cols].copy_from_slice(&w[i * cols..(i + 1) * cols]);
+        }
+        x
+    }
+
+    /// A⁻¹, by solving against the identity.
+    pub(crate) fn inverse(&self) -> Vec<f64> {
+        let n = self.n;
+        let mut identity = vec![0.0; n * n];
+        for i in 0..n {
+            identity[i * n + i] = 1.0;
+        }
+        self.solve(&identity, n)
+    }
+
+    /// A⁻ᵀ, by solving the transposed system against the identity.
+    pub(crate) fn inverse_transpose(&self) -> Vec<f64> {
+        let n = self.n;
+        let mut identity = vec![0.0; n * n];
+        for i in 0..n {
+            identity[i * n + i] = 1.0;
+        }
+        self.solve_transpose(&identity, n)
+    }
+}
+
+/// Cofactor matrix of a square matrix, the transpose of its adjugate and the
+/// gradient of its determinant.
+///
+/// This is `det A · A⁻ᵀ` when every LU pivot is nonzero. Otherwise `A⁻ᵀ` is
+/// infinite, so each cofactor is taken from the determinant of its minor.
+pub(crate) fn cofactors(a: &[f64], n: usize) -> Vec<f64> {
+    let lu = Lu::new(a, n);
+    if lu.diagonal().all(|d| d != 0.0) {
+        let det = lu.det();
+        return lu.inverse_transpose().iter().map(|v| det * v).collect();
+    }
+    let mut out = vec![0.0; n * n];
+    let mut minor = Vec::with_capacity((n - 1) * (n - 1));
+    for i in 0..n {
+        for j in 0..n {
+            minor.clear();
+            for (_, row) in a.chunks(n).enumerate().filter(|&(r, _)| r != i) {
+                minor.extend(
+                    row.iter()
+                        .enumerate()
+                        .filter(|&(c, _)| c != j)
+                        .map(|(_, v)| v),
+                );
+            }
+            let sign = if (i + j) % 2 == 0 { 1.0 } else { -1.0 };
+            out[i * n + j] = sign * Lu::new(&minor, n - 1).det();
+        }
+    }
+    out
+}
+
+/// Lower-triangular `L` with `A = L Lᵀ`, reading only the lower triangle of `A`.
+///
+/// Entries become `NaN` from the first non-positive pivot on when `A` is not
+/// positive definite.
+pub(crate) fn cholesky(a: &[f64], n: usize) -> Vec<f64> {
+    let mut l = vec![0.0; n * n];
+    for j in 0..n {
+        let dot: f64 = (0..j).map(|p| l[j * n + p] * l[j * n + p]).sum();
+        let diagonal = (a[j * n + j] - dot).sqrt();
+        l[j * n + j] = diagonal;
+        for i in j + 1..n {
+            let dot: f64 = (0..j).map(|p| l[i * n + p] * l[j * n + p]).sum();
+            l[i * n + j] = (a[i * n + j] - dot) / diagonal;
+        }
+    }
+    l
+}
+
+/// Adjoint of [`cholesky`]: given `L` and `L̄`, the adjoint of the lower
+/// triangle of `A`.
+///
+/// With P = Φ(Lᵀ L̄), where Φ keeps the lower triangle and halves the
+/// diagonal, the symmetric adjoint is S = ½ L⁻ᵀ (P + Pᵀ) L⁻¹. An entry below
+/// the diagonal stands for both itself and its mirror, so it receives 2 Sᵢⱼ;
+/// the upper triangle is never read and receives zero.
+pub(crate) fn cholesky_backward(l: &[f64], l_bar: &[f64], n: usize) -> Vec<f64> {
+    let mut p = matmul(&transpose(l, n, n), l_bar, n, n, n);
+    for i in 0..n {
+        p[i * n + i] *= 0.5;
+        for j in i + 1..n {
+            p[i * n + j] = 0.0;
+        }
+    }
+    let mut sym = p.clone();
+    for i in 0..n {
+        for j in 0..n {
+            sym[i * n + j] = 0.5 * (p[i * n + j] + p[j * n + i]);
+        }
+    }
+    // L⁻ᵀ sym L⁻¹ = (L⁻ᵀ (L⁻ᵀ sym)ᵀ)ᵀ, and sym is symmetric, so two
+    // triangular solves with Lᵀ give it
+    let left = solve_upper_transposed(l, &sym, n);
+    let s = solve_upper_transposed(l, &transpose(&left, n, n), n);
+    let mut a_bar = vec![0.0; n * n];
+    for i in 0..n {
+        a_bar[i * n + i] = s[i * n + i];
+        for j in 0..i {
+            a_bar[i * n + j] = s[i * n + j] + s[j * n + i];
+        }
+    }
+    a_bar
+}
+
+/// `X` solving `Lᵀ X = B` for lower-triangular `L` and `n × n` `B`.
+fn solve_upper_transposed(l: &[f64], b: &[f64], n: usize) -> Vec<f64> {
+    let mut x = b.to_vec();
+    for i in (0..n).rev() {
+        for p in i + 1..n {
+            for c in 0..n {
+                x[i * n + c] -= l[p * n + i] * x[p * n + c];
+            }
+        }
+        for c in 0..n {
+            x[i * n + c] /= l[i * n + i];
+        }
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::approx_eq_eps as approx_eq;
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (&a, &e) in actual.iter().zip(expected) {
+            assert!(approx_eq(a, e, 1e-12), "{actual:?} vs {expected:?}");
+        }
+    }
+
+    const A: [f64; 9] = [2.0, 1.0, -1.0, -3.0, -1.0, 2.0, -2.0, 1.0, 2.0];
+
+    #[test]
+    fn test_products() {
+        let a = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+        let b = [7.0, 8.0, 9.0, 10.0, 11.0, 12.0];
+        assert_eq!(matmul(&a, &b, 2, 3, 2), [58.0, 64.0, 139.0, 154.0]);
+        assert_eq!(transpose(&a, 2, 3), [1.0, 4.0, 2.0, 5.0, 3.0, 6.0]);
+        assert_eq!(matmul(&a, &[1.0, 1.0, 1.0], 2, 3, 1), [6.0, 15.0]);
+    }
+
+    #[test]
+    fn test_lu_solves_and_determinant() {
+        let lu = Lu::new(&A, 3);
+        assert!(approx_eq(lu.det(), -1.0, 1e-12));
+        assert!(approx_eq(lu.log_abs_det(), 0.0, 1e-12));
+        assert_close(&lu.solve(&[8.0, -11.0, -3.0], 1), &[2.0, 3.0, -1.0]);
+        let b = [1.0, 0.5, -2.0, 3.0, 0.0, 1.0];
+        let x = lu.solve_transpose(&b, 2);
+        assert_close(&matmul(&transpose(&A, 3, 3), &x, 3, 3, 2), &b);
+        let inverse = lu.inverse();
+        assert_close(
+            &matmul(&A, &inverse, 3, 3, 3),
+            &[1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0],
+        );
+        assert_close(&lu.inverse_transpose(), &transpose(&inverse, 3, 3));
+        // A pivot of zero on the diagonal needs the row swap
+        assert_eq!(Lu::new(&[0.0, 1.0, 1.0, 0.0], 2).det(), -1.0);
+    }
+
+    #[test]
+    fn test_singular_matrices() {
+        let lu = Lu::new(&[1.0, 2.0, 2.0, 4.0], 2);
+        assert_eq!(lu.det(), 0.0);
+        assert_eq!(lu.log_abs_det(), f64::NEG_INFINITY);
+        assert!(lu.solve(&[1.0, 1.0], 1).iter().any(|x| !x.is_finite()));
+    }
+
+    #[test]
+    fn test_cofactors() {
+        let det = Lu::new(&A, 3).det();
+        let expected: Vec<f64> = Lu::new(&A, 3)
+            .inverse_transpose()
+            .iter()
+            .map(|v| det * v)
+            .collect();
+        assert_close(&cofactors(&A, 3), &expected);
+        // Singular matrices keep a finite cofactor matrix
+        assert_eq!(cofactors(&[1.0, 2.0, 2.0, 4.0], 2), [4.0, -2.0, -2.0, 1.0]);
+        let rank_two = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0];
+        assert_close(
+            &cofactors(&rank_two, 3),
+            &[-3.0, 6.0, -3.0, 6.0, -12.0, 6.0, -3.0, 6.0, -3.0],
+        );
+        assert_eq!(cofactors(&[0.0; 9], 3), [0.0; 9]);
+        assert_eq!(cofactors(&[0.0], 1), [1.0]);
+    }
+
+    #[test]
+    fn test_cholesky() {
+        let a = [4.0, 12.0, -16.0, 12.0, 37.0, -43.0, -16.0, -43.0, 98.0];
+        let l = cholesky(&a, 3);
+        assert_close(&l, &[2.0, 0.0, 0.0, 6.0, 1.0, 0.0, -8.0, 5.0, 3.0]);
+        assert_close(&matmul(&l, &transpose(&l, 3, 3), 3, 3, 3), &a);
+        assert!(cholesky(&[1.0, 2.0, 2.0, 1.0], 2)[3].is_nan());
+    }
+}
@@ -0,0 +1,156 @@
+//! Shapes of tensor values and the broadcasting rule between them.
+
+use std::fmt;
+
+/// Shape of a value in a [`TensorAD`](crate::TensorAD) graph.
+///
+/// Elementwise ops broadcast like NumPy: shapes are aligned on their last
+/// dimension, and a dimension of size 1 (or a missing one) stretches to match
+/// the other shape. A `Vector(n)` therefore behaves as a single row of `n`
+/// elements next to a matrix, and a `Scalar` combines with anything.
+///
+/// # Examples
+///
+/// ```
+/// use petite_ad::Shape;
+///
+/// assert_eq!(Shape::Matrix(4, 3).broadcast(Shape::Vector(3)), Some(Shape::Matrix(4, 3)));
+/// assert_eq!(Shape::Matrix(4, 1).broadcast(Shape::Vector(3)), Some(Shape::Matrix(4, 3)));
+/// assert_eq!(Shape::Vector(2).broadcast(Shape::Vector(3)), None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Shape {
+    /// A single value
+    Scalar,
+    /// A vector of `n` values
+    Vector(usize),
+    /// A `rows × cols` matrix, stored row-major
+    Matrix(usize, usize),
+}
+
+impl Shape {
+    /// Number of elements.
+    pub fn len(self) -> usize {
+        let [rows, cols] = self.dims();
+        rows * cols
+    }
+
+    /// Returns `true` if the shape has no elements.
+    pub fn is_empty(self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of dimensions: 0, 1 or 2.
+    pub fn rank(self) -> usize {
+        match self {
+            Shape::Scalar => 0,
+            Shape::Vector(_) => 1,
+            Shape::Matrix(..) => 2,
+        }
+    }
+
+    /// Shape of an elementwise op on values of shapes `self` and `other`, or
+    /// `None` if they cannot be broadcast together.
+    pub fn broadcast(self, other: Shape) -> Option<Shape> {
+        let (a, b) = (self.dims(), other.dims());
+        let mut dims = [0; 2];
+        for (d, (&a, &b)) in dims.iter_mut().zip(a.iter().zip(&b)) {
+            *d = match (a, b) {
+                _ if a == b => a,
+                (1, b) => b,
+                (a, 1) => a,
+                _ => return None,
+            };
+        }
+        Some(Shape::from_dims(self.rank().max(other.rank()), dims))
+    }
+
+    /// `[rows, cols]`, with missing leading dimensions as 1.
+    pub(crate) fn dims(self) -> [usize; 2] {
+        match self {
+            Shape::Scalar => [1, 1],
+            Shape::Vector(n) => [1, n],
+            Shape::Matrix(rows, cols) => [rows, cols],
+        }
+    }
+
+    fn from_dims(rank: usize, [rows, cols]: [usize; 2]) -> Shape {
+        match rank {
+            0 => Shape::Scalar,
+            1 => Shape::Vector(cols),
+            _ => Shape::Matrix(rows, cols),
+        }
+    }
+
+    /// Flat index of the element of a `self`-shaped value that broadcasts to
+    /// element `flat` of a `target`-shaped value.
+    pub(crate) fn source_index(self, target: Shape, flat: usize) -> usize {
+        let [rows, cols] = self.dims();
+        let target_cols = target.dims()[1];
+        let (i, j) = (flat / target_cols, flat % target_cols);
+        let i = if rows == 1 { 0 } else { i };
+        let j = if cols == 1 { 0 } else { j };
+        i * cols + j
+    }
+}
+
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Shape::Scalar => write!(f, "scalar"),
+            Shape::Vector(n) => write!(f, "[{}]", n),
+            Shape::Matrix(rows, cols) => write!(f, "[{}, {}]", rows, cols),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_broadcast() {
+        use Shape::*;
+        assert_eq!(Scalar.broadcast(Scalar), Some(Scalar));
+        assert_eq!(Scalar.broadcast(Matrix(2, 3)), Some(Matrix(2, 3)));
+        assert_eq!(Vector(1).broadcast(Vector(5)), Some(Vector(5)));
+        assert_eq!(Vector(1).broadcast(Scalar), Some(Vector(1)));
+        assert_eq!(Matrix(2, 1).broadcast(Matrix(1, 3)), Some(Matrix(2, 3)));
+        assert_eq!(Vector(2).broadcast(Matrix(2, 3)), None);
+        assert_eq!(Matrix(2, 3).broadcast(Matrix(3, 2)), None);
+    }
+
+    #[test]
+    fn test_source_index() {
+        let target = Shape::Matrix(2, 3);
+        let sources: Vec<_> = (0..6)
+            .map(|k| {
+                (
+                    Shape::Vector(3).source_index(target, k),
+                    Shape::Matrix(2, 1).source_index(target, k),
+                    Shape::Scalar.source_index(target, k),
+                )
+            })
+            .collect();
+        assert_eq!(
+            sources,
+            [
+                (0, 0, 0),
+                (1, 0, 0),
+                (2, 0, 0),
+                (0, 1, 0),
+                (1, 1, 0),
+                (2, 1, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Shape::Scalar.to_string(), "scalar");
+        assert_eq!(Shape::Vector(4).to_string(), "[4]");
+        assert_eq!(Shape::Matrix(2, 3).to_string(), "[2, 3]");
+        assert_eq!(Shape::Matrix(2, 3).len(), 6);
+        assert!(Shape::Vector(0).is_empty());
+    }
+}
@@ -0,0 +1,506 @@
+//! Operations on shaped values and their reverse-mode rules.
+
+use super::linalg::{self, Lu};
+use super::shape::Shape;
+use super::value::Tensor;
+use crate::error::{Arity, AutodiffError, Result};
+use crate::multi::multi_ad::MultiAD;
+use crate::multi::subgradient::SubgradientPolicy;
+use crate::multi::validate::GraphOp;
+
+/// Operations in a graph whose nodes hold scalars, vectors or matrices.
+///
+/// Graphs use the same `(op, args)` layout as [`MultiAD`] graphs, with the
+/// inputs declared by `Inp` nodes (or, without any `Inp` nodes, taken
+/// implicitly at indices `0..num_inputs`) and the last node as the output.
+/// Any scalar [`MultiAD`] op lifts to shaped values through `Elementwise`,
+/// which broadcasts its arguments as described on [`Shape`].
+///
+/// # Examples
+///
+/// ```
+/// use petite_ad::{MultiAD, Tensor, TensorAD};
+///
+/// // f(x, y) = Σ sin(x) · y, with x a vector and y a scalar
+/// let exprs = vec![
+///     (TensorAD::Inp, vec![0]),
+///     (TensorAD::Inp, vec![1]),
+///     (TensorAD::Elementwise(MultiAD::Sin), vec![0]),
+///     (TensorAD::Elementwise(MultiAD::Mul), vec![2, 1]),
+///     (TensorAD::Sum, vec![3]),
+/// ];
+/// let inputs = [Tensor::vector([0.0, 1.0, 2.0]), Tensor::scalar(2.0)];
+/// let (value, grad_fn) = TensorAD::compute_grad(&exprs, &inputs).unwrap();
+/// let grads = grad_fn(&Tensor::scalar(1.0));
+///
+/// assert_eq!(value.as_scalar(), Some(2.0 * (1f64.sin() + 2f64.sin())));
+/// assert_eq!(grads[0].shape(), inputs[0].shape());
+/// assert_eq!(grads[1].as_scalar(), Some(1f64.sin() + 2f64.sin()));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TensorAD {
+    /// Input placeholder; takes the input number as its single argument
+    Inp,
+    /// Scalar constant; takes no arguments
+    ///
+    /// # Notes
+    /// - Broadcasts against any shape
+    Const(f64),
+    /// A scalar op applied to each element of its arguments
+    ///
+    /// # Notes
+    /// - Takes as many arguments as the scalar op, broadcast to a common shape
+    /// - The scalar op must take at least one argument
+    /// - Derivatives at kinks follow the default [`SubgradientPolicy`]
+    Elementwise(MultiAD),
+    /// Sum of all elements, giving a scalar
+    Sum,
+    /// Mean of all elements, giving a scalar
+    Mean,
+    /// Sum along one axis, removing it from the shape
+    ///
+    /// # Notes
+    /// - Axis 0 of a matrix sums over rows, giving a vector with one element
+    ///   per column; axis 1 sums over columns
+    /// - Axis 0 of a vector gives a scalar
+    SumAxis(usize),
+    /// Matrix product `A · B`
+    ///
+    /// # Notes
+    /// - A vector on the left is a row and on the right a column, so
+    ///   `[m, k] · [k]` gives `[m]` and `[k] · [k, n]` gives `[n]`
+    MatMul,
+    /// Inner product of two vectors of the same length, giving a scalar
+    Dot,
+    /// Transpose of a matrix
+    Transpose,
+    /// Solution `x` of `A x = b` for a square matrix `A`
+    ///
+    /// # Notes
+    /// - `b` is a vector, or a matrix whose columns are solved together
+    /// - Uses LU factorization with partial pivoting; the adjoint solves the
+    ///   transposed system with the same factors
+    /// - Infinite or `NaN` when `A` is singular
+    Solve,
+    /// Determinant of a square matrix
+    ///
+    /// # Notes
+    /// - The signed product of the LU pivots, so it may overflow for large
+    ///   matrices; `LogDet` does not
+    /// - The gradient is the cofactor matrix, which stays finite when `A` is
+    ///   singular
+    Det,
+    /// Log of the absolute determinant of a square matrix, ln|det A|
+    ///
+    /// # Notes
+    /// - Summed from the LU pivots, so it does not overflow where `Det` does
+    /// - -∞ when `A` is singular
+    LogDet,
+    /// Inverse of a square matrix
+    ///
+    /// # Notes
+    /// - Infinite or `NaN` when `A` is singular; prefer `Solve` to
+    ///   multiplying by the inverse
+    Inverse,
+    /// Lower-triangular Cholesky factor `L` with `A = L Lᵀ`
+    ///
+    /// # Notes
+    /// - Reads only the lower triangle of `A`, which is taken to be
+    ///   symmetric; the gradient is lower-triangular to match, with each entry
+    ///   below the diagonal standing for itself and its mirror
+    /// - `NaN` from the first non-positive pivot on when `A` is not positive
+    ///   definite
+    Cholesky,
+}
+
+impl TensorAD {
+    /// Operation name used in error messages.
+    pub(crate) fn op_name(&self) -> &'static str {
+        match self {
+            TensorAD::Inp => "Inp",
+            TensorAD::Const(_) => "Const",
+            TensorAD::Elementwise(op) => op.op_name(),
+            TensorAD::Sum => "Sum",
+            TensorAD::Mean => "Mean",
+            TensorAD::SumAxis(_) => "SumAxis",
+            TensorAD::MatMul => "MatMul",
+            TensorAD::Dot => "Dot",
+            TensorAD::Transpose => "Transpose",
+            TensorAD::Solve => "Solve",
+            TensorAD::Det => "Det",
+            TensorAD::LogDet => "LogDet",
+            TensorAD::Inverse => "Inverse",
+            TensorAD::Cholesky => "Cholesky",
+        }
+    }
+
+    /// Number of arguments this operation takes.
+    pub(crate) fn expected_arity(&self) -> Arity {
+        match self {
+            TensorAD::Const(_) => Arity::Exact(0),
+            TensorAD::Elementwise(op) => op.expected_arity(),
+            TensorAD::MatMul | TensorAD::Dot | TensorAD::Solve => Arity::Exact(2),
+            TensorAD::Inp
+            | TensorAD::Sum
+            | TensorAD::Mean
+            | TensorAD::SumAxis(_)
+            | TensorAD::Transpose
+            | TensorAD::Det
+            | TensorAD::LogDet
+            | TensorAD::Inverse
+            | TensorAD::Cholesky => Arity::Exact(1),
+        }
+    }
+
+    fn mismatch(&self, shapes: &[Shape]) -> AutodiffError {
+        AutodiffError::ShapeMismatch {
+            operation: self.op_name(),
+            shapes: shapes.to_vec(),
+        }
+    }
+
+    /// Shape of the output given the shapes of the arguments.
+    ///
+    /// Arity is assumed to have been checked; `Inp` nodes take their shape
+    /// from the inputs instead.
+    pub(crate) fn output_shape(&self, args: &[Shape]) -> Result<Shape> {
+        match self {
+            TensorAD::Inp => Ok(args[0]),
+            TensorAD::Const(_) | TensorAD::Sum | TensorAD::Mean => Ok(Shape::Scalar),
+            TensorAD::Elementwise(op) => {
+                let Some((&first, rest)) = args.split_first() else {
+                    return Err(AutodiffError::arity(op.op_name(), Arity::AtLeast(1), 0));
+                };
+                rest.iter()
+                    .try_fold(first, |shape, &s| shape.broadcast(s))
+                    .ok_or_else(|| self.mismatch(args))
+            }
+            TensorAD::SumAxis(axis) => match (args[0], axis) {
+                (Shape::Vector(_), 0) => Ok(Shape::Scalar),
+                (Shape::Matrix(_, cols), 0) => Ok(Shape::Vector(cols)),
+                (Shape::Matrix(rows, _), 1) => Ok(Shape::Vector(rows)),
+                _ => Err(self.mismatch(args)),
+            },
+            TensorAD::MatMul => product_dims(args[0], args[1])
+                .map(|(_, shape)| shape)
+                .ok_or_else(|| self.mismatch(args)),
+            TensorAD::Dot => match (args[0], args[1]) {
+                (Shape::Vector(a), Shape::Vector(b)) if a == b => Ok(Shape::Scalar),
+                _ => Err(self.mismatch(args)),
+            },
+            TensorAD::Transpose => match args[0] {
+                Shape::Matrix(rows, cols) => Ok(Shape::Matrix(cols, rows)),
+                _ => Err(self.mismatch(args)),
+            },
+            TensorAD::Solve => solve_dims(args[0], args[1])
+                .map(|_| args[1])
+                .ok_or_else(|| self.mismatch(args)),
+            TensorAD::Det | TensorAD::LogDet => square(args[0])
+                .map(|_| Shape::Scalar)
+                .ok_or_else(|| self.mismatch(args)),
+            TensorAD::Inverse | TensorAD::Cholesky => square(args[0])
+                .map(|_| args[0])
+                .ok_or_else(|| self.mismatch(args)),
+        }
+    }
+
+    /// Forward pass over argument values whose shapes have been checked.
+    pub(crate) fn forward(&self, args: &[&Tensor]) -> Result<Tensor> {
+        let shapes: Vec<Shape> = args.iter().map(|a| a.shape()).collect();
+        let shape = self.output_shape(&shapes)?;
+        Ok(match self {
+            TensorAD::Inp => args[0].clone(),
+            TensorAD::Const(c) => Tensor::scalar(*c),
+            TensorAD::Elementwise(op) => {
+                let mut scalars = vec![0.0; args.len()];
+                let data = (0..shape.len())
+                    .map(|k| {
+                        gather(args, shape, k, &mut scalars);
+                        op.forward(&scalars)
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Tensor::from_shape(shape, data)?
+            }
+            TensorAD::Sum => Tensor::scalar(args[0].data().iter().sum()),
+            TensorAD::Mean => {
+                let data = args[0].data();
+                Tensor::scalar(data.iter().sum::<f64>() / data.len() as f64)
+            }
+            TensorAD::SumAxis(axis) => {
+                let mut out = Tensor::zeros(shape);
+                let [_, cols] = args[0].shape().dims();
+                let down_columns = sums_down_columns(args[0].shape(), *axis);
+                for (k, &v) in args[0].data().iter().enumerate() {
+                    let target = if down_columns { k % cols } else { k / cols };
+                    out.data_mut()[target] += v;
+                }
+                out
+            }
+            TensorAD::MatMul => {
+                let ([m, k, n], _) = product_dims(shapes[0], shapes[1]).expect("checked shape");
+                let data = linalg::matmul(args[0].data(), args[1].data(), m, k, n);
+                Tensor::from_shape(shape, data)?
+            }
+            TensorAD::Dot => {
+                let (a, b) = (args[0].data(), args[1].data());
+                Tensor::scalar(a.iter().zip(b).map(|(x, y)| x * y).sum())
+            }
+            TensorAD::Transpose => {
+                let [rows, cols] = shapes[0].dims();
+                Tensor::from_shape(shape, linalg::transpose(args[0].data(), rows, cols))?
+            }
+            TensorAD::Solve => {
+                let (n, cols) = solve_dims(shapes[0], shapes[1]).expect("checked shape");
+                let x = Lu::new(args[0].data(), n).solve(args[1].data(), cols);
+                Tensor::from_shape(shape, x)?
+            }
+            TensorAD::Det => Tensor::scalar(lu_of(args[0]).det()),
+            TensorAD::LogDet => Tensor::scalar(lu_of(args[0]).log_abs_det()),
+            TensorAD::Inverse => Tensor::from_shape(shape, lu_of(args[0]).inverse())?,
+            TensorAD::Cholesky => {
+                let n = square(shape).expect("checked shape");
+                Tensor::from_shape(shape, linalg::cholesky(args[0].data(), n))?
+            }
+        })
+    }
+
+    /// Reverse pass: adds the contribution of `adjoint`, the adjoint of the
+    /// output `out`, to each argument's adjoint in `grads`, which have the
+    /// arguments' shapes.
+    pub(crate) fn backward(
+        &self,
+        args: &[&Tensor],
+        out: &Tensor,
+        adjoint: &Tensor,
+        grads: &mut [Tensor],
+    ) {
+        match self {
+            TensorAD::Inp => grads[0].accumulate(adjoint),
+            TensorAD::Const(_) => {}
+            TensorAD::Elementwise(op) => {
+                let shape = adjoint.shape();
+                let mut scalars = vec![0.0; args.len()];
+                let mut partials = vec![0.0; args.len()];
+                for (k, &a) in adjoint.data().iter().enumerate() {
+                    gather(args, shape, k, &mut scalars);
+                    op.partials(&scalars, SubgradientPolicy::default(), &mut partials);
+                    for (grad, &p) in grads.iter_mut().zip(&partials) {
+                        let source = grad.shape().source_index(shape, k);
+                        grad.data_mut()[source] += a * p;
+                    }
+                }
+            }
+            TensorAD::Sum | TensorAD::Mean => {
+                let mut a = adjoint.data()[0];
+                if *self == TensorAD::Mean {
+                    a /= args[0].data().len() as f64;
+                }
+                for g in grads[0].data_mut() {
+                    *g += a;
+                }
+            }
+            TensorAD::SumAxis(axis) => {
+                let [_, cols] = args[0].shape().dims();
+                let down_columns = sums_down_columns(args[0].shape(), *axis);
+                for (k, g) in grads[0].data_mut().iter_mut().enumerate() {
+                    let target = if down_columns { k % cols } else { k / cols };
+                    *g += adjoint.data()[target];
+                }
+            }
+            TensorAD::MatMul => {
+                // Ā = C̄ Bᵀ, B̄ = Aᵀ C̄
+                let ([m, k, n], _) =
+                    product_dims(args[0].shape(), args[1].shape()).expect("checked shape");
+                let (a, b, c_bar) = (args[0].data(), args[1].data(), adjoint.data());
+                let a_bar = linalg::matmul(c_bar, &linalg::transpose(b, k, n), m, n, k);
+                let b_bar = linalg::matmul(&linalg::transpose(a, m, k), c_bar, k, m, n);
+                add_into(&mut grads[0], &a_bar);
+                add_into(&mut grads[1], &b_bar);
+            }
+            TensorAD::Dot => {
+                let c_bar = adjoint.data()[0];
+                let (a, b) = (args[0].data(), args[1].data());
+                add_into(
+                    &mut grads[0],
+                    &b.iter().map(|v| c_bar * v).collect::<Vec<_>>(),
+                );
+                add_into(
+                    &mut grads[1],
+                    &a.iter().map(|v| c_bar * v).collect::<Vec<_>>(),
+                );
+            }
+            TensorAD::Transpose => {
+                let [rows, cols] = out.shape().dims();
+                add_into(
+                    &mut grads[0],
+                    &linalg::transpose(adjoint.data(), rows, cols),
+                );
+            }
+            TensorAD::Solve => {
+                // x = A⁻¹ b: b̄ = A⁻ᵀ x̄, Ā = -b̄ xᵀ
+                let (n, cols) =
+                    solve_dims(args[0].shape(), args[1].shape()).expect("checked shape");
+                let b_bar = Lu::new(args[0].data(), n).solve_transpose(adjoint.data(), cols);
+                let x_t = linalg::transpose(out.data(), n, cols);
+                let mut a_bar = linalg::matmul(&b_bar, &x_t, n, cols, n);
+                for v in &mut a_bar {
+                    *v = -*v;
+                }
+                add_into(&mut grads[0], &a_bar);
+                add_into(&mut grads[1], &b_bar);
+            }
+            TensorAD::Det | TensorAD::LogDet => {
+                // ∂det A = cof A (det A · A⁻ᵀ where A is invertible),
+                // ∂ln|det A| = A⁻ᵀ
+                let scale = adjoint.data()[0];
+                let partials = if *self == TensorAD::Det {
+                    let n = square(args[0].shape()).expect("checked shape");
+                    linalg::cofactors(args[0].data(), n)
+                } else {
+                    lu_of(args[0]).inverse_transpose()
+                };
+                let a_bar: Vec<f64> = partials.iter().map(|v| scale * v).collect();
+                add_into(&mut grads[0], &a_bar);
+            }
+            TensorAD::Inverse => {
+                // Y = A⁻¹: Ā = -Yᵀ Ȳ Yᵀ
+                let n = square(out.shape()).expect("checked shape");
+                let y_t = linalg::transpose(out.data(), n, n);
+                let left = linalg::matmul(&y_t, adjoint.data(), n, n, n);
+                let mut a_bar = linalg::matmul(&left, &y_t, n, n, n);
+                for v in &mut a_bar {
+                    *v = -*v;
+                }
+                add_into(&mut grads[0], &a_bar);
+            }
+            TensorAD::Cholesky => {
+                let n = square(out.shape()).expect("checked shape");
+                let a_bar = linalg::cholesky_backward(out.data(), adjoint.data(), n);
+                add_into(&mut grads[0], &a_bar);
+            }
+        }
+    }
+}
+
+impl GraphOp for TensorAD {
+    fn op_name(&self) -> &'static str {
+        TensorAD::op_name(self)
+    }
+
+    fn expected_arity(&self) -> Arity {
+        TensorAD::expected_arity(self)
+    }
+
+    fn is_input(&self) -> bool {
+        *self == TensorAD::Inp
+    }
+}
+
+/// `[m, k, n]` of a product of these shapes, with a vector on the left as a
+/// row and on the right as a column, and the shape of the product.
+fn product_dims(a: Shape, b: Shape) -> Option<([usize; 3], Shape)> {
+    match (a, b) {
+        (Shape::Matrix(m, k), Shape::Matrix(k2, n)) if k == k2 => {
+            Some(([m, k, n], Shape::Matrix(m, n)))
+        }
+        (Shape::Matrix(m, k), Shape::Vector(k2)) if k == k2 => Some(([m, k, 1], Shape::Vector(m))),
+        (Shape::Vector(k), Shape::Matrix(k2, n)) if k == k2 => Some(([1, k, n], Shape::Vector(n))),
+        _ => None,
+    }
+}
+
+/// Size of a square matrix.
+fn square(shape: Shape) -> Option<usize> {
+    match shape {
+        Shape::Matrix(rows, cols) if rows == cols => Some(rows),
+        _ => None,
+    }
+}
+
+/// Size of `A` and number of right-hand sides of `Solve(A, b)`.
+fn solve_dims(a: Shape, b: Shape) -> Option<(usize, usize)> {
+    let n = square(a)?;
+    match b {
+        Shape::Vector(m) if m == n => Some((n, 1)),
+        Shape::Matrix(m, cols) if m == n => Some((n, cols)),
+        _ => None,
+    }
+}
+
+/// LU factorization of a square matrix argument.
+fn lu_of(a: &Tensor) -> Lu {
+    Lu::new(a.data(), square(a.shape()).expect("checked shape"))
+}
+
+/// Adds `values`, laid out like `grad`, into `grad`.
+fn add_into(grad: &mut Tensor, values: &[f64]) {
+    for (g, v) in grad.data_mut().iter_mut().zip(values) {
+        *g += v;
+    }
+}
+
+/// Whether `SumAxis(axis)` on this shape sums down each column, keeping one
+/// element per column; otherwise it sums along each row.
+fn sums_down_columns(shape: Shape, axis: usize) -> bool {
+    shape.rank() == 2 && axis == 0
+}
+
+/// Reads element `k` of the broadcast arguments into `scalars`.
+fn gather(args: &[&Tensor], shape: Shape, k: usize, scalars: &mut [f64]) {
+    for (s, a) in scalars.iter_mut().zip(args) {
+        *s = a.data()[a.shape().source_index(shape, k)];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_shapes() {
+        use Shape::*;
+        let add = TensorAD::Elementwise(MultiAD::Add);
+        assert_eq!(
+            add.output_shape(&[Matrix(2, 3), Vector(3)]),
+            Ok(Matrix(2, 3))
+        );
+        assert_eq!(
+            add.output_shape(&[Vector(2), Vector(3)]),
+            Err(AutodiffError::ShapeMismatch {
+                operation: "Add",
+                shapes: vec![Vector(2), Vector(3)]
+            })
+        );
+        let clamp = TensorAD::Elementwise(MultiAD::Clamp);
+        assert_eq!(
+            clamp.output_shape(&[Matrix(2, 1), Scalar, Vector(4)]),
+            Ok(Matrix(2, 4))
+        );
+        assert_eq!(
+            TensorAD::Elementwise(MultiAD::Const(1.0)).output_shape(&[]),
+            Err(AutodiffError::arity("Const", Arity::AtLeast(1), 0))
+        );
+        assert_eq!(
+            TensorAD::SumAxis(0).output_shape(&[Matrix(2, 3)]),
+            Ok(Vector(3))
+        );
+        assert_eq!(
+            TensorAD::SumAxis(1).output_shape(&[Matrix(2, 3)]),
+            Ok(Vector(2))
+        );
+        assert_eq!(TensorAD::SumAxis(0).output_shape(&[Vector(3)]), Ok(Scalar));
+        assert!(TensorAD::SumAxis(1).output_shape(&[Vector(3)]).is_err());
+        assert!(TensorAD::SumAxis(0).output_shape(&[Scalar]).is_err());
+    }
+
+    #[test]
+    fn test_sum_axis_forward() {
+        let m = Tensor::from_rows(&[[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]).unwrap();
+        let columns = TensorAD::SumAxis(0).forward(&[&m]).unwrap();
+        assert_eq!(columns, Tensor::vector([5.0, 7.0, 9.0]));
+        let rows = TensorAD::SumAxis(1).forward(&[&m]).unwrap();
+        assert_eq!(rows, Tensor::vector([6.0, 15.0]));
+        let total = TensorAD::SumAxis(0).forward(&[&rows]).unwrap();
+        assert_eq!(total.as_scalar(), Some(21.0));
+    }
+}
@@ -0,0 +1,183 @@
+//! Shaped values: scalars, vectors and row-major matrices.
+
+use super::shape::Shape;
+use crate::error::{AutodiffError, Result};
+
+/// A scalar, vector or dense row-major matrix of `f64` values.
+///
+/// # Examples
+///
+/// ```
+/// use petite_ad::{Shape, Tensor};
+///
+/// let m = Tensor::matrix(2, 3, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]).unwrap();
+/// assert_eq!(m.shape(), Shape::Matrix(2, 3));
+/// assert_eq!(m.get(1, 0), 4.0);
+/// assert_eq!(Tensor::from_rows(&[[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]).unwrap(), m);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tensor {
+    shape: Shape,
+    data: Vec<f64>,
+}
+
+impl Tensor {
+    /// A scalar.
+    pub fn scalar(value: f64) -> Self {
+        Tensor {
+            shape: Shape::Scalar,
+            data: vec![value],
+        }
+    }
+
+    /// A vector holding `values`.
+    pub fn vector(values: impl Into<Vec<f64>>) -> Self {
+        let data = values.into();
+        Tensor {
+            shape: Shape::Vector(data.len()),
+            data,
+        }
+    }
+
+    /// A `rows × cols` matrix from row-major `values`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(AutodiffError::DimensionMismatch)` if `values` does not
+    /// hold `rows * cols` elements.
+    pub fn matrix(rows: usize, cols: usize, values: impl Into<Vec<f64>>) -> Result<Self> {
+        Self::from_shape(Shape::Matrix(rows, cols), values)
+    }
+
+    /// A matrix with one row per element of `rows`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(AutodiffError::DimensionMismatch)` if the rows have
+    /// different lengths.
+    pub fn from_rows<R: AsRef<[f64]>>(rows: &[R]) -> Result<Self> {
+        let cols = rows.first().map_or(0, |row| row.as_ref().len());
+        let mut data = Vec::with_capacity(rows.len() * cols);
+        for row in rows {
+            AutodiffError::check_dimension(cols, row.as_ref().len())?;
+            data.extend_from_slice(row.as_ref());
+        }
+        Self::matrix(rows.len(), cols, data)
+    }
+
+    /// A value of the given shape from row-major `values`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(AutodiffError::DimensionMismatch)` if `values` does not
+    /// hold `shape.len()` elements.
+    pub fn from_shape(shape: Shape, values: impl Into<Vec<f64>>) -> Result<Self> {
+        let data = values.into();
+        AutodiffError::check_dimension(shape.len(), data.len())?;
+        Ok(Tensor { shape, data })
+    }
+
+    /// A value of the given shape with every element equal to `value`.
+    pub fn full(shape: Shape, value: f64) -> Self {
+        Tensor {
+            shape,
+            data: vec![value; shape.len()],
+        }
+    }
+
+    /// A value of the given shape filled with zeros.
+    pub fn zeros(shape: Shape) -> Self {
+        Self::full(shape, 0.0)
+    }
+
+    /// The shape of this value.
+    pub fn shape(&self) -> Shape {
+        self.shape
+    }
+
+    /// The elements in row-major order.
+    pub fn data(&self) -> &[f64] {
+        &self.data
+    }
+
+    /// The elements in row-major order, mutably.
+    pub fn data_mut(&mut self) -> &mut [f64] {
+        &mut self.data
+    }
+
+    /// Consumes the value and returns its elements in row-major order.
+    pub fn into_data(self) -> Vec<f64> {
+        self.data
+    }
+
+    /// Element `(row, col)`; vectors and scalars are a single row.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the position is out of range.
+    pub fn get(&self, row: usize, col: usize) -> f64 {
+        let [rows, cols] = self.shape.dims();
+        assert!(
+            row < rows && col < cols,
+            "index ({row}, {col}) out of range for shape {}",
+            self.shape
+        );
+        self.data[row * cols + col]
+    }
+
+    /// The value of a scalar, or `None` for vectors and matrices.
+    pub fn as_scalar(&self) -> Option<f64> {
+        match self.shape {
+            Shape::Scalar => Some(self.data[0]),
+            _ => None,
+        }
+    }
+
+    /// Adds `other`, which must have the same shape, elementwise.
+    pub(crate) fn accumulate(&mut self, other: &Tensor) {
+        debug_assert_eq!(self.shape, other.shape);
+        for (a, b) in self.data.iter_mut().zip(&other.data) {
+            *a += b;
+        }
+    }
+}
+
+impl From<f64> for Tensor {
+    fn from(value: f64) -> Self {
+        Tensor::scalar(value)
+    }
+}
+
+impl From<Vec<f64>> for Tensor {
+    fn from(values: Vec<f64>) -> Self {
+        Tensor::vector(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constructors() {
+        assert_eq!(Tensor::scalar(2.0).as_scalar(), Some(2.0));
+        assert_eq!(Tensor::from(vec![1.0, 2.0]).shape(), Shape::Vector(2));
+        assert_eq!(Tensor::vector([1.0, 2.0]).as_scalar(), None);
+        assert_eq!(Tensor::zeros(Shape::Matrix(2, 2)).data(), &[0.0; 4]);
+        assert_eq!(
+            Tensor::matrix(2, 2, vec![1.0; 3]),
+            Err(AutodiffError::DimensionMismatch {
+                expected: 4,
+                actual: 3
+            })
+        );
+        assert_eq!(
+            Tensor::from_rows(&[vec![1.0, 2.0], vec![3.0]]),
+            Err(AutodiffError::DimensionMismatch {
+                expected: 2,
+                actual: 1
+            })
+        );
+        assert_eq!(Tensor::vector([5.0, 6.0]).get(0, 1), 6.0);
+    }
+}
@@ -389,3 +389,363 @@ fn test_sqrt_and_mul_chain() {
     assert!(approx_eq(grads[0], 5.0 / (2.0 * 16.0_f64.sqrt()), 1e-10));
     assert!(approx_eq(grads[1], 4.0, 1e-10));
 }
+
+#[test]
+fn test_inp_nodes_are_positional() {
+    // f(x, y) = y - x with the inputs declared out of order
+    let exprs = &multi_ops![
+        (inp, 1),    // y at index 0
+        (inp, 0),    // x at index 1
+        (sub, 0, 1), // y - x at index 2
+    ];
+    let (value, backprop_fn) = MultiAD::compute_grad(exprs, &[2.0, 7.0]).unwrap();
+    assert_eq!(value, 5.0);
+    // Gradients stay ordered by input number
+    assert_eq!(backprop_fn(1.0), vec![-1.0, 1.0]);
+    assert_eq!(MultiAD::compute(exprs, &[2.0, 7.0]).unwrap(), 5.0);
+    assert_eq!(
+        MultiAD::compute_jvp(exprs, &[2.0, 7.0], &[1.0, 0.0]).unwrap(),
+        (5.0, -1.0)
+    );
+}
+
+#[test]
+fn test_inp_nodes_declared_late_and_reused() {
+    // f(x, y) = sin(x) * y * x, declaring y only when it is needed
+    let exprs = &multi_ops![
+        (inp, 0),    // x at index 0
+        (sin, 0),    // sin(x) at index 1
+        (inp, 1),    // y at index 2
+        (mul, 1, 2), // sin(x) * y at index 3
+        (inp, 0),    // x again at index 4
+        (mul, 3, 4), // sin(x) * y * x at index 5
+    ];
+    let (x, y) = (0.8, 1.5);
+    let (value, backprop_fn) = MultiAD::compute_grad(exprs, &[x, y]).unwrap();
+    assert!(approx_eq(value, x.sin() * y * x, 1e-12));
+
+    let grads = backprop_fn(1.0);
+    assert!(approx_eq(grads[0], y * (x.cos() * x + x.sin()), 1e-12));
+    assert!(approx_eq(grads[1], x.sin() * x, 1e-12));
+
+    // An input can also be the output
+    let exprs = &multi_ops![(inp, 0), (inp, 1), (sin, 0), (inp, 1)];
+    let (value, backprop_fn) = MultiAD::compute_grad(exprs, &[x, y]).unwrap();
+    assert_eq!(value, y);
+    assert_eq!(backprop_fn(1.0), vec![0.0, 1.0]);
+}
+
+#[test]
+fn test_extended_unary_ops_against_f64() {
+    // (op, f64 reference, point inside the domain)
+    type Unary = fn(f64) -> f64;
+    let cases: [(MultiAD, Unary, f64); 12] = [
+        (MultiAD::Tanh, f64::tanh, 0.7),
+        (MultiAD::Sinh, f64::sinh, -1.3),
+        (MultiAD::Cosh, f64::cosh, 0.9),
+        (MultiAD::Asin, f64::asin, 0.4),
+        (MultiAD::Acos, f64::acos, -0.6),
+        (MultiAD::Atan, f64::atan, 2.2),
+        (MultiAD::Log1p, f64::ln_1p, 0.3),
+        (MultiAD::Expm1, f64::exp_m1, -0.8),
+        (MultiAD::Log2, f64::log2, 3.5),
+        (MultiAD::Log10, f64::log10, 0.2),
+        (MultiAD::Sigmoid, |x| 1.0 / (1.0 + (-x).exp()), 1.1),
+        (MultiAD::Softplus, |x| x.exp().ln_1p(), -0.5),
+    ];
+    let h = 1e-6;
+    for (op, f, x) in cases {
+        let exprs = vec![(MultiAD::Inp, vec![0]), (op, vec![0])];
+        let (value, backprop_fn) = MultiAD::compute_grad(&exprs, &[x]).unwrap();
+        assert!(approx_eq(value, f(x), 1e-12), "{op}({x})");
+        let expected = (f(x + h) - f(x - h)) / (2.0 * h);
+        assert!(approx_eq(backprop_fn(1.0)[0], expected, 1e-6), "{op}'({x})");
+    }
+}
+
+#[test]
+fn test_extended_binary_ops_against_f64() {
+    type Binary = fn(f64, f64) -> f64;
+    let cases: [(MultiAD, Binary); 2] =
+        [(MultiAD::Atan2, f64::atan2), (MultiAD::Hypot, f64::hypot)];
+    let h = 1e-6;
+    for (op, f) in cases {
+        // One point in each quadrant
+        for (a, b) in [(0.5, 1.5), (1.2, -0.4), (-0.3, -2.0), (-1.0, 0.7)] {
+            let exprs = multi_ops![(inp, 0), (inp, 1)].to_vec();
+            let exprs = [exprs, vec![(op, vec![0, 1])]].concat();
+            let (value, backprop_fn) = MultiAD::compute_grad(&exprs, &[a, b]).unwrap();
+            assert!(approx_eq(value, f(a, b), 1e-12));
+            let grads = backprop_fn(1.0);
+            assert!(approx_eq(
+                grads[0],
+                (f(a + h, b) - f(a - h, b)) / (2.0 * h),
+                1e-6
+            ));
+            assert!(approx_eq(
+                grads[1],
+                (f(a, b + h) - f(a, b - h)) / (2.0 * h),
+                1e-6
+            ));
+        }
+    }
+}
+
+#[test]
+fn test_sigmoid_and_softplus_are_stable() {
+    let value_and_grad = |op: MultiAD, x: f64| {
+        let exprs = vec![(MultiAD::Inp, vec![0]), (op, vec![0])];
+        let (value, backprop_fn) = MultiAD::compute_grad(&exprs, &[x]).unwrap();
+        (value, backprop_fn(1.0)[0])
+    };
+
+    // The naive forms overflow exp and give NaN or lose every digit
+    assert_eq!(value_and_grad(MultiAD::Sigmoid, 800.0), (1.0, 0.0));
+    assert_eq!(value_and_grad(MultiAD::Sigmoid, -800.0), (0.0, 0.0));
+    assert_eq!(value_and_grad(MultiAD::Softplus, 800.0), (800.0, 1.0));
+    assert_eq!(value_and_grad(MultiAD::Softplus, -800.0), (0.0, 0.0));
+
+    // Tiny results keep their relative precision
+    let (value, grad) = value_and_grad(MultiAD::Softplus, -40.0);
+    assert!(approx_eq(value / (-40.0_f64).exp(), 1.0, 1e-12));
+    assert!(approx_eq(grad / (-40.0_f64).exp(), 1.0, 1e-12));
+    let (value, _) = value_and_grad(MultiAD::Sigmoid, -40.0);
+    assert!(approx_eq(value / (-40.0_f64).exp(), 1.0, 1e-12));
+
+    // σ'(±x) ≈ e^-x and σ''(±x) ≈ ∓e^-x for large x, in every mode
+    let tail = (-40.0_f64).exp();
+    let sigmoid = [(MultiAD::Inp, vec![0]), (MultiAD::Sigmoid, vec![0])];
+    let softplus = [(MultiAD::Inp, vec![0]), (MultiAD::Softplus, vec![0])];
+    for x in [40.0, -40.0] {
+        assert!(approx_eq(
+            value_and_grad(MultiAD::Sigmoid, x).1 / tail,
+            1.0,
+            1e-12
+        ));
+        let (_, tangent) = MultiAD::compute_jvp(&sigmoid, &[x], &[1.0]).unwrap();
+        assert!(approx_eq(tangent / tail, 1.0, 1e-12));
+        let first = MultiAD::derivative_graph(&sigmoid, 1, 0).unwrap();
+        assert!(approx_eq(
+            MultiAD::compute(&first, &[x]).unwrap() / tail,
+            1.0,
+            1e-12
+        ));
+
+        let second = MultiAD::hessian(&sigmoid, &[x]).unwrap()[0][0];
+        assert!(approx_eq(second / tail, -x.signum(), 1e-12));
+        let second = MultiAD::hessian(&softplus, &[x]).unwrap()[0][0];
+        assert!(approx_eq(second / tail, 1.0, 1e-12));
+    }
+}
+
+#[test]
+fn test_special_ops_against_reference_values() {
+    // (op, x, f(x), f'(x)) with references from an arbitrary-precision library
+    let cases = [
+        (
+            MultiAD::Erf,
+            0.5,
+            0.520_499_877_813_046_5,
+            0.878_782_578_935_444_8,
+        ),
+        (
+            MultiAD::Erfc,
+            3.0,
+            2.209_049_699_858_544e-5,
+            -1.392_530_519_467_478e-4,
+        ),
+        (
+            MultiAD::Lgamma,
+            0.5,
+            0.572_364_942_924_700_1,
+            -1.963_510_026_021_423_5,
+        ),
+        (
+            MultiAD::Lgamma,
+            -0.5,
+            1.265_512_123_484_645_4,
+            0.036_489_973_978_576_52,
+        ),
+        (
+            MultiAD::Digamma,
+            1.0,
+            -0.577_215_664_901_532_9,
+            1.644_934_066_848_226_4,
+        ),
+        (
+            MultiAD::Trigamma,
+            1.0,
+            1.644_934_066_848_226_4,
+            -2.404_113_806_319_188_5,
+        ),
+        (
+            MultiAD::NormalCdf,
+            1.96,
+            0.975_002_104_851_779_6,
+            0.058_440_944_333_451_47,
+        ),
+        (MultiAD::NormalLogpdf, 1.5, -2.043_938_533_204_672_7, -1.5),
+        (
+            MultiAD::LambertW,
+            1.0,
+            0.567_143_290_409_783_8,
+            0.361_896_256_634_889_2,
+        ),
+        (
+            MultiAD::LambertW,
+            -0.2,
+            -0.259_171_101_819_073_77,
+            1.749_196_760_921_836,
+        ),
+    ];
+    for (op, x, value, derivative) in cases {
+        let exprs = vec![(MultiAD::Inp, vec![0]), (op, vec![0])];
+        let (actual, backprop_fn) = MultiAD::compute_grad(&exprs, &[x]).unwrap();
+        let grad = backprop_fn(1.0)[0];
+        assert!(
+            approx_eq(actual, value, 1e-14 * value.abs().max(1e-3)),
+            "{op}({x}) = {actual}"
+        );
+        assert!(
+            approx_eq(grad, derivative, 1e-13 * derivative.abs()),
+            "{op}'({x}) = {grad}"
+        );
+    }
+}
+
+#[test]
+fn test_special_ops_against_finite_differences() {
+    let h = 1e-6;
+    let ops = [
+        MultiAD::Erf,
+        MultiAD::Erfc,
+        MultiAD::Lgamma,
+        MultiAD::Digamma,
+        MultiAD::Trigamma,
+        MultiAD::NormalCdf,
+        MultiAD::NormalLogpdf,
+        MultiAD::LambertW,
+    ];
+    for op in ops {
+        for x in [-0.3, 0.8, 2.6, 7.5] {
+            let exprs = vec![(MultiAD::Inp, vec![0]), (op, vec![0])];
+            let f = |x: f64| op.forward(&[x]).unwrap();
+            let grad = MultiAD::compute_grad(&exprs, &[x]).unwrap().1(1.0)[0];
+            let expected = (f(x + h) - f(x - h)) / (2.0 * h);
+            assert!(
+                approx_eq(grad, expected, 1e-6 * expected.abs().max(1.0)),
+                "{op}'({x}): {grad} vs {expected}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_nary_ops_against_finite_differences() {
+    let h = 1e-6;
+    let point = [0.3, -1.2, 2.0, 0.7];
+    for op in [
+        MultiAD::Sum,
+        MultiAD::Prod,
+        MultiAD::Mean,
+        MultiAD::LogSumExp,
+        MultiAD::SoftmaxSum,
+    ] {
+        let exprs: Vec<_> = (0..point.len())
+            .map(|k| (MultiAD::Inp, vec![k]))
+            .chain([(op, vec![0, 1, 2, 3])])
+            .collect();
+        let (value, backprop_fn) = MultiAD::compute_grad(&exprs, &point).unwrap();
+        assert!(approx_eq(value, op.forward(&point).unwrap(), 1e-15));
+        for (i, grad) in backprop_fn(1.0).iter().enumerate() {
+            let (mut plus, mut minus) = (point, point);
+            plus[i] += h;
+            minus[i] -= h;
+            let f = |x: &[f64]| MultiAD::compute(&exprs, x).unwrap();
+            let expected = (f(&plus) - f(&minus)) / (2.0 * h);
+            assert!(
+                approx_eq(*grad, expected, 1e-6),
+                "{op}: {grad} vs {expected}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_nary_ops_replace_binary_chains() {
+    // sum, prod and mean agree with chains of binary nodes, and repeated
+    // arguments accumulate their gradients
+    let exprs = multi_ops![
+        (inp, 0),
+        (inp, 1),
+        (inp, 2),
+        (sum, 0, 1, 2, 0),
+        (prod, 0, 1, 2),
+        (mean, 0, 1),
+        (mul, 3, 4),
+        (add, 6, 5)
+    ];
+    let (x, y, z) = (1.5, -2.0, 0.5);
+    let (value, backprop_fn) = MultiAD::compute_grad(&exprs, &[x, y, z]).unwrap();
+    let s = 2.0 * x + y + z;
+    let p = x * y * z;
+    assert!(approx_eq(value, s * p + (x + y) / 2.0, 1e-12));
+    let grads = backprop_fn(1.0);
+    assert!(approx_eq(grads[0], 2.0 * p + s * y * z + 0.5, 1e-12));
+    assert!(approx_eq(grads[1], p + s * x * z + 0.5, 1e-12));
+    assert!(approx_eq(grads[2], p + s * x * y, 1e-12));
+}
+
+#[test]
+fn test_nary_ops_edge_cases() {
+    let grad = |op: MultiAD, point: &[f64]| {
+        let exprs: Vec<_> = (0..point.len())
+            .map(|k| (MultiAD::Inp, vec![k]))
+            .chain([(op, (0..point.len()).collect())])
+            .collect();
+        let (value, backprop_fn) = MultiAD::compute_grad(&exprs, point).unwrap();
+        (value, backprop_fn(1.0))
+    };
+    // Zero factors: exact partials, no 0/0
+    assert_eq!(
+        grad(MultiAD::Prod, &[0.0, 2.0, 3.0]),
+        (0.0, vec![6.0, 0.0, 0.0])
+    );
+    assert_eq!(
+        grad(MultiAD::Prod, &[0.0, 2.0, 0.0]),
+        (0.0, vec![0.0, 0.0, 0.0])
+    );
+    // Large arguments do not overflow
+    let (value, grads) = grad(MultiAD::LogSumExp, &[1000.0, 1000.0]);
+    assert!(approx_eq(value, 1000.0 + std::f64::consts::LN_2, 1e-12));
+    assert_eq!(grads, vec![0.5, 0.5]);
+    let (value, grads) = grad(MultiAD::SoftmaxSum, &[-900.0, 900.0]);
+    assert_eq!((value, grads), (900.0, vec![0.0, 1.0]));
+    // A single argument is the identity
+    for op in [
+        MultiAD::Sum,
+        MultiAD::Prod,
+        MultiAD::Mean,
+        MultiAD::LogSumExp,
+        MultiAD::SoftmaxSum,
+    ] {
+        assert_eq!(grad(op, &[0.25]), (0.25, vec![1.0]), "{op}");
+    }
+
+    // Compiled graphs size their buffers for the widest node
+    let exprs: Vec<_> = (0..6)
+        .map(|k| (MultiAD::Inp, vec![k]))
+        .chain([(MultiAD::Prod, (0..6).collect())])
+        .collect();
+    let graph = CompiledGraph::new(&exprs, 6).unwrap();
+    let mut grads = [0.0; 6];
+    let value = graph
+        .value_and_grad(
+            &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0],
+            &mut graph.workspace(),
+            &mut grads,
+        )
+        .unwrap();
+    assert_eq!(value, 720.0);
+    assert_eq!(grads, [720.0, 360.0, 240.0, 180.0, 144.0, 120.0]);
+}
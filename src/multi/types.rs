@@ -2,6 +2,9 @@
 
 use std::sync::Arc;
 
+use super::multi_ad::MultiAD;
+use super::tensor::Tensor;
+
 /// Dynamic trait object for multi-variable gradient functions
 pub type DynGradFn = dyn Fn(f64) -> Vec<f64> + 'static;
 
@@ -10,3 +13,13 @@ pub type BackwardResultBox = (f64, Box<DynGradFn>);
 
 /// Result type containing value and gradient function (Arc-wrapped for sharing)
 pub type BackwardResultArc = (f64, Arc<DynGradFn>);
+
+/// Graph parsed from a formula, with variable names in input order
+pub type ParsedExpr = (Vec<(MultiAD, Vec<usize>)>, Vec<String>);
+
+/// Gradient function of a tensor graph: maps the output's cotangent to one
+/// gradient per input, shaped like that input
+pub type TensorGradFn = dyn Fn(&Tensor) -> Vec<Tensor> + 'static;
+
+/// Result type containing a tensor graph's value and gradient function
+pub type TensorResultBox = (Tensor, Box<TensorGradFn>);
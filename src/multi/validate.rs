@@ -0,0 +1,252 @@
+//! Structural validation of computation graphs.
+//!
+//! Evaluation indexes node values directly, so a malformed graph would panic
+//! or silently compute the wrong thing. [`MultiAD::validate`] checks the whole
+//! graph up front and reports the first problem as an [`AutodiffError`].
+
+use super::multi_ad::MultiAD;
+use crate::error::{Arity, AutodiffError, Result};
+
+/// An op of a graph laid out as `(op, argument indices)` pairs.
+pub(crate) trait GraphOp {
+    fn op_name(&self) -> &'static str;
+    fn expected_arity(&self) -> Arity;
+    /// Whether this is an `Inp` node, whose argument is an input number
+    fn is_input(&self) -> bool;
+}
+
+impl GraphOp for MultiAD {
+    fn op_name(&self) -> &'static str {
+        MultiAD::op_name(self)
+    }
+
+    fn expected_arity(&self) -> Arity {
+        MultiAD::expected_arity(self)
+    }
+
+    fn is_input(&self) -> bool {
+        *self == MultiAD::Inp
+    }
+}
+
+/// Run the checks of [`MultiAD::validate`] over any graph, in graph order,
+/// calling `visit` with each node once its own checks pass. An error from
+/// `visit` stops the walk.
+pub(crate) fn walk<Op: GraphOp>(
+    exprs: &[(Op, Vec<usize>)],
+    num_inputs: usize,
+    mut visit: impl FnMut(&Op, &[usize]) -> Result<()>,
+) -> Result<()> {
+    if exprs.is_empty() {
+        return Err(AutodiffError::EmptyGraph);
+    }
+
+    let has_inp_nodes = exprs.iter().any(|(op, _)| op.is_input());
+    let first_node = if has_inp_nodes { 0 } else { num_inputs };
+    let num_nodes = first_node + exprs.len();
+    let mut declared = vec![false; num_inputs];
+
+    for (node, (op, args)) in (first_node..).zip(exprs) {
+        AutodiffError::check_arity(op.op_name(), op.expected_arity(), args.len())?;
+
+        if op.is_input() {
+            let input = args[0];
+            if input >= num_inputs {
+                return Err(AutodiffError::InputCountMismatch {
+                    expected: input + 1,
+                    actual: num_inputs,
+                });
+            }
+            declared[input] = true;
+        } else {
+            for &index in args {
+                if index >= num_nodes {
+                    return Err(AutodiffError::IndexOutOfBounds {
+                        index,
+                        max_index: num_nodes - 1,
+                    });
+                }
+                if index >= node {
+                    return Err(AutodiffError::ForwardReference { node, index });
+                }
+            }
+        }
+        visit(op, args)?;
+    }
+
+    if has_inp_nodes {
+        if let Some(input) = declared.iter().position(|&d| !d) {
+            return Err(AutodiffError::MissingInput { input });
+        }
+    }
+    Ok(())
+}
+
+impl MultiAD {
+    /// Check that a graph can be evaluated with `num_inputs` inputs.
+    ///
+    /// Reports, in graph order:
+    /// - `EmptyGraph` if there are no nodes
+    /// - `ArityError` if a node has the wrong number of arguments
+    /// - `InputCountMismatch` if an `Inp` node refers to an input that was not supplied
+    /// - `IndexOutOfBounds` if an argument refers past the last node
+    /// - `ForwardReference` if an argument refers to the node itself or a later node
+    /// - `MissingInput` if the graph declares inputs but skips one
+    ///
+    /// Node indices follow the graph layout: when the graph declares its
+    /// inputs with `Inp` nodes, node `i` is `exprs[i]`; graphs without any
+    /// `Inp` nodes take their inputs implicitly at indices `0..num_inputs`,
+    /// followed by the nodes of `exprs`, and are not checked for missing
+    /// inputs.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first problem found as `Err(AutodiffError)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petite_ad::{AutodiffError, MultiAD, multi_ops};
+    ///
+    /// let exprs = multi_ops![(inp, 0), (inp, 1), (add, 0, 1)];
+    /// assert!(MultiAD::validate(&exprs, 2).is_ok());
+    ///
+    /// // The Add node at index 2 cannot read index 3
+    /// let exprs = multi_ops![(inp, 0), (inp, 1), (add, 0, 3), (sin, 0)];
+    /// assert_eq!(
+    ///     MultiAD::validate(&exprs, 2),
+    ///     Err(AutodiffError::ForwardReference { node: 2, index: 3 })
+    /// );
+    /// ```
+    pub fn validate(exprs: &[(MultiAD, Vec<usize>)], num_inputs: usize) -> Result<()> {
+        walk(exprs, num_inputs, |_, _| Ok(()))
+    }
+
+    /// Number of inputs a graph declares: one past the largest `Inp` index.
+    pub(crate) fn declared_inputs(exprs: &[(MultiAD, Vec<usize>)]) -> usize {
+        exprs
+            .iter()
+            .filter(|(op, _)| *op == MultiAD::Inp)
+            .filter_map(|(_, args)| args.first())
+            .max()
+            .map_or(0, |&k| k + 1)
+    }
+
+    /// The graph with its inputs declared: a graph without `Inp` nodes gets
+    /// `(Inp, [k])` for each of its `num_inputs` implicit inputs in front, which
+    /// leaves every node at the same index. Other graphs are copied as they are.
+    pub(crate) fn with_input_nodes(
+        exprs: &[(MultiAD, Vec<usize>)],
+        num_inputs: usize,
+    ) -> Vec<(MultiAD, Vec<usize>)> {
+        if exprs.iter().any(|(op, _)| *op == MultiAD::Inp) {
+            return exprs.to_vec();
+        }
+        (0..num_inputs)
+            .map(|k| (MultiAD::Inp, vec![k]))
+            .chain(exprs.iter().cloned())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Arity;
+    use crate::multi_ops;
+
+    #[test]
+    fn test_valid_graphs() {
+        let exprs = multi_ops![(inp, 0), (inp, 1), (add, 0, 1), (sin, 2)];
+        assert_eq!(MultiAD::validate(&exprs, 2), Ok(()));
+
+        // Inputs declared late, reused and out of order
+        let exprs = multi_ops![(inp, 1), (sin, 0), (inp, 0), (inp, 1), (mul, 2, 3)];
+        assert_eq!(MultiAD::validate(&exprs, 2), Ok(()));
+
+        // Implicit inputs and constant-only graphs
+        assert_eq!(MultiAD::validate(&multi_ops![(sin, 0)], 1), Ok(()));
+        assert_eq!(MultiAD::validate(&multi_ops![(const, 1.0)], 0), Ok(()));
+    }
+
+    #[test]
+    fn test_input_nodes() {
+        let explicit = multi_ops![(inp, 1), (inp, 0), (add, 0, 1)];
+        assert_eq!(MultiAD::declared_inputs(&explicit), 2);
+        assert_eq!(MultiAD::with_input_nodes(&explicit, 2), explicit);
+
+        let implicit = multi_ops![(add, 0, 1), (mul, 0, 2)];
+        let declared = MultiAD::with_input_nodes(&implicit, 2);
+        assert_eq!(
+            declared,
+            multi_ops![(inp, 0), (inp, 1), (add, 0, 1), (mul, 0, 2)]
+        );
+        assert_eq!(MultiAD::validate(&declared, 2), Ok(()));
+    }
+
+    #[test]
+    fn test_reference_errors() {
+        assert_eq!(MultiAD::validate(&[], 2), Err(AutodiffError::EmptyGraph));
+
+        let exprs = multi_ops![(inp, 0), (sin, 1)];
+        assert_eq!(
+            MultiAD::validate(&exprs, 1),
+            Err(AutodiffError::ForwardReference { node: 1, index: 1 })
+        );
+
+        let exprs = multi_ops![(inp, 0), (sin, 0), (add, 1, 5)];
+        assert_eq!(
+            MultiAD::validate(&exprs, 1),
+            Err(AutodiffError::IndexOutOfBounds {
+                index: 5,
+                max_index: 2
+            })
+        );
+
+        let exprs = [(MultiAD::Mul, vec![0])];
+        assert_eq!(
+            MultiAD::validate(&exprs, 1),
+            Err(AutodiffError::arity("Mul", 2, 1))
+        );
+
+        let exprs = multi_ops![(inp, 0), (sum, 0, 0, 0)].to_vec();
+        assert_eq!(MultiAD::validate(&exprs, 1), Ok(()));
+        let exprs = [(MultiAD::Inp, vec![0]), (MultiAD::Sum, vec![])];
+        let err = MultiAD::validate(&exprs, 1).unwrap_err();
+        assert_eq!(err, AutodiffError::arity("Sum", Arity::AtLeast(1), 0));
+        assert_eq!(
+            err.to_string(),
+            "Arity error in Sum: expected at least 1, got 0"
+        );
+    }
+
+    #[test]
+    fn test_input_errors() {
+        let exprs = multi_ops![(inp, 0), (inp, 2), (add, 0, 1)];
+        assert_eq!(
+            MultiAD::validate(&exprs, 2),
+            Err(AutodiffError::InputCountMismatch {
+                expected: 3,
+                actual: 2
+            })
+        );
+        assert_eq!(
+            MultiAD::validate(&exprs, 3),
+            Err(AutodiffError::MissingInput { input: 1 })
+        );
+    }
+
+    #[test]
+    fn test_compute_never_panics_on_malformed_graphs() {
+        let malformed = [
+            multi_ops![(inp, 0), (sin, 1)].to_vec(),
+            multi_ops![(inp, 0), (add, 0, 9)].to_vec(),
+            Vec::new(),
+        ];
+        for exprs in &malformed {
+            assert!(MultiAD::compute(exprs, &[1.0]).is_err());
+            assert!(MultiAD::compute_grad(exprs, &[1.0]).is_err());
+            assert!(MultiAD::compute_jvp(exprs, &[1.0], &[1.0]).is_err());
+        }
+    }
+}
@@ -0,0 +1,718 @@
+//! Define-by-run graph construction.
+//!
+//! A [`Tape`] records every operation applied to its [`Var`] handles, so
+//! ordinary Rust arithmetic builds the same `(MultiAD, Vec<usize>)` graph
+//! that [`MultiAD::compute_grad`] accepts, without any manual indexing.
+
+use std::cell::RefCell;
+use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
+
+use super::multi_ad::MultiAD;
+use super::subgradient::SubgradientPolicy;
+
+/// A recorded node: either the `k`-th input or an operation on earlier nodes.
+#[derive(Debug, Clone)]
+enum Node {
+    Input(usize),
+    Op(MultiAD, Vec<usize>),
+}
+
+/// Records operations on [`Var`]s as they are evaluated.
+///
+/// Every node also stores its value, so the output of a recorded expression is
+/// available immediately and gradients can be taken with [`Tape::grad`].
+///
+/// # Examples
+///
+/// ```
+/// use petite_ad::{MultiAD, Tape};
+///
+/// let tape = Tape::new();
+/// let x = tape.var(0.6);
+/// let y = tape.var(1.4);
+/// let f = x.sin() * (x + y);
+///
+/// // Gradients straight from the tape...
+/// let grads = tape.grad(f);
+/// assert!((grads[1] - 0.6_f64.sin()).abs() < 1e-12);
+///
+/// // ...or as a graph for the rest of the API
+/// let graph = tape.graph(f);
+/// let (value, grad_fn) = MultiAD::compute_grad(&graph, &tape.inputs()).unwrap();
+/// assert_eq!(value, f.value());
+/// assert_eq!(grad_fn(1.0), grads);
+/// ```
+#[derive(Debug, Default)]
+pub struct Tape {
+    nodes: RefCell<Vec<Node>>,
+    values: RefCell<Vec<f64>>,
+    inputs: RefCell<Vec<f64>>,
+}
+
+/// A value recorded on a [`Tape`].
+///
+/// `Var` is a cheap copyable handle; arithmetic on it appends a node to the
+/// tape it came from. Combining `Var`s from different tapes panics.
+#[derive(Debug, Clone, Copy)]
+pub struct Var<'t> {
+    tape: &'t Tape,
+    id: usize,
+}
+
+impl Tape {
+    /// Creates an empty tape.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a new input variable with the given value.
+    ///
+    /// Inputs are numbered in creation order, which is the order expected by
+    /// [`Tape::inputs`] and by the graphs returned from [`Tape::graph`].
+    pub fn var(&self, value: f64) -> Var<'_> {
+        let mut inputs = self.inputs.borrow_mut();
+        let id = self.push(Node::Input(inputs.len()), value);
+        inputs.push(value);
+        Var { tape: self, id }
+    }
+
+    /// Adds one input variable per value.
+    pub fn vars(&self, values: &[f64]) -> Vec<Var<'_>> {
+        values.iter().map(|&value| self.var(value)).collect()
+    }
+
+    /// Adds a constant node with the given value.
+    ///
+    /// Constants are not inputs: they do not appear in [`Tape::inputs`] and
+    /// receive no gradient.
+    pub fn constant(&self, value: f64) -> Var<'_> {
+        self.apply(MultiAD::Const(value), &[])
+    }
+
+    /// Values of the input variables, in creation order.
+    pub fn inputs(&self) -> Vec<f64> {
+        self.inputs.borrow().clone()
+    }
+
+    /// Number of input variables.
+    pub fn num_inputs(&self) -> usize {
+        self.inputs.borrow().len()
+    }
+
+    /// Number of recorded nodes, inputs included.
+    pub fn len(&self) -> usize {
+        self.nodes.borrow().len()
+    }
+
+    /// Returns `true` if nothing has been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Export the computation of `output` as a graph.
+    ///
+    /// The graph starts with one `Inp` node per input variable, followed by
+    /// the operations `output` depends on, and ends with `output` itself.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `output` was recorded on a different tape.
+    pub fn graph(&self, output: Var<'_>) -> Vec<(MultiAD, Vec<usize>)> {
+        self.check_owner(output);
+        let nodes = self.nodes.borrow();
+        let num_inputs = self.num_inputs();
+
+        // Mark the nodes the output depends on
+        let mut live = vec![false; output.id + 1];
+        live[output.id] = true;
+        for id in (0..=output.id).rev() {
+            if let (true, Node::Op(_, args)) = (live[id], &nodes[id]) {
+                for &arg in args {
+                    live[arg] = true;
+                }
+            }
+        }
+
+        let mut graph: Vec<(MultiAD, Vec<usize>)> =
+            (0..num_inputs).map(|k| (MultiAD::Inp, vec![k])).collect();
+        let mut remap = vec![0; output.id + 1];
+        for (id, node) in nodes[..=output.id].iter().enumerate() {
+            match node {
+                Node::Input(k) => remap[id] = *k,
+                Node::Op(op, args) if live[id] => {
+                    remap[id] = graph.len();
+                    graph.push((*op, args.iter().map(|&arg| remap[arg]).collect()));
+                }
+                Node::Op(..) => {}
+            }
+        }
+
+        // The output must be the last node; an input output is declared again
+        if let Node::Input(k) = nodes[output.id] {
+            if k + 1 != num_inputs || graph.len() != num_inputs {
+                graph.push((MultiAD::Inp, vec![k]));
+            }
+        }
+        graph
+    }
+
+    /// Sum of `vars`, recorded as one n-ary node.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `vars` is empty or a variable was recorded on a different tape.
+    pub fn sum<'t>(&'t self, vars: &[Var<'t>]) -> Var<'t> {
+        self.reduce(MultiAD::Sum, vars)
+    }
+
+    /// Product of `vars`, recorded as one n-ary node.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `vars` is empty or a variable was recorded on a different tape.
+    pub fn prod<'t>(&'t self, vars: &[Var<'t>]) -> Var<'t> {
+        self.reduce(MultiAD::Prod, vars)
+    }
+
+    /// Arithmetic mean of `vars`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `vars` is empty or a variable was recorded on a different tape.
+    pub fn mean<'t>(&'t self, vars: &[Var<'t>]) -> Var<'t> {
+        self.reduce(MultiAD::Mean, vars)
+    }
+
+    /// Log-sum-exp of `vars`: ln(Σ exp(vᵢ)), evaluated without overflow.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `vars` is empty or a variable was recorded on a different tape.
+    pub fn logsumexp<'t>(&'t self, vars: &[Var<'t>]) -> Var<'t> {
+        self.reduce(MultiAD::LogSumExp, vars)
+    }
+
+    /// Softmax-weighted sum of `vars`: Σ softmax(v)ᵢ vᵢ.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `vars` is empty or a variable was recorded on a different tape.
+    pub fn softmax_sum<'t>(&'t self, vars: &[Var<'t>]) -> Var<'t> {
+        self.reduce(MultiAD::SoftmaxSum, vars)
+    }
+
+    /// Gradient of `output` with respect to every input variable.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `output` was recorded on a different tape.
+    pub fn grad(&self, output: Var<'_>) -> Vec<f64> {
+        self.check_owner(output);
+        let nodes = self.nodes.borrow();
+        let values = self.values.borrow();
+
+        let mut adjoints = vec![0.0; output.id + 1];
+        adjoints[output.id] = 1.0;
+        let mut grads = vec![0.0; self.num_inputs()];
+        let mut arg_values = Vec::new();
+        let mut partials = Vec::new();
+
+        for id in (0..=output.id).rev() {
+            let adjoint = adjoints[id];
+            if adjoint == 0.0 {
+                continue;
+            }
+            match &nodes[id] {
+                Node::Input(k) => grads[*k] += adjoint,
+                Node::Op(op, args) => {
+                    arg_values.clear();
+                    arg_values.extend(args.iter().map(|&arg| values[arg]));
+                    partials.resize(args.len(), 0.0);
+                    op.partials(&arg_values, SubgradientPolicy::default(), &mut partials);
+                    for (&arg, &d) in args.iter().zip(&partials) {
+                        if d != 0.0 {
+                            adjoints[arg] += adjoint * d;
+                        }
+                    }
+                }
+            }
+        }
+        grads
+    }
+
+    fn push(&self, node: Node, value: f64) -> usize {
+        let mut nodes = self.nodes.borrow_mut();
+        nodes.push(node);
+        self.values.borrow_mut().push(value);
+        nodes.len() - 1
+    }
+
+    fn record(&self, op: MultiAD, args: &[Var<'_>]) -> usize {
+        let arg_values: Vec<f64> = {
+            let values = self.values.borrow();
+            args.iter().map(|arg| values[arg.id]).collect()
+        };
+        let value = op
+            .forward(&arg_values)
+            .expect("Var methods always pass the correct arity");
+        self.push(Node::Op(op, args.iter().map(|arg| arg.id).collect()), value)
+    }
+
+    /// Record `op` applied to `args`, which must have the op's arity.
+    pub(super) fn apply<'t>(&'t self, op: MultiAD, args: &[Var<'t>]) -> Var<'t> {
+        for &arg in args {
+            self.check_owner(arg);
+        }
+        let id = self.record(op, args);
+        Var { tape: self, id }
+    }
+
+    /// Record an n-ary reduction over `vars`.
+    fn reduce<'t>(&'t self, op: MultiAD, vars: &[Var<'t>]) -> Var<'t> {
+        assert!(!vars.is_empty(), "{op} needs at least one variable");
+        self.apply(op, vars)
+    }
+
+    fn check_owner(&self, var: Var<'_>) {
+        assert!(
+            std::ptr::eq(self, var.tape),
+            "Var belongs to a different Tape"
+        );
+    }
+}
+
+impl MultiAD {
+    /// Trace a closure over `num_inputs` variables into a graph.
+    ///
+    /// The closure runs once on fresh [`Var`] inputs and every operation it
+    /// performs is recorded. Control flow is captured as taken during that run,
+    /// so branches on `Var::value` are baked into the graph.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petite_ad::{MultiAD, Var};
+    ///
+    /// fn f<'t>(xs: &[Var<'t>]) -> Var<'t> {
+    ///     xs[0].sin() * (xs[0] + xs[1])
+    /// }
+    ///
+    /// let graph = MultiAD::trace(2, f);
+    /// let (value, grad_fn) = MultiAD::compute_grad(&graph, &[0.6, 1.4]).unwrap();
+    /// assert!((value - 0.6_f64.sin() * 2.0).abs() < 1e-12);
+    /// assert!((grad_fn(1.0)[1] - 0.6_f64.sin()).abs() < 1e-12);
+    /// ```
+    pub fn trace<F>(num_inputs: usize, f: F) -> Vec<(MultiAD, Vec<usize>)>
+    where
+        F: for<'t> FnOnce(&[Var<'t>]) -> Var<'t>,
+    {
+        let tape = Tape::new();
+        let inputs = tape.vars(&vec![0.0; num_inputs]);
+        let output = f(&inputs);
+        tape.graph(output)
+    }
+}
+
+impl<'t> Var<'t> {
+    /// Value of this variable, computed when it was recorded.
+    pub fn value(&self) -> f64 {
+        self.tape.values.borrow()[self.id]
+    }
+
+    /// Tape this variable is recorded on.
+    pub fn tape(&self) -> &'t Tape {
+        self.tape
+    }
+
+    fn unary(self, op: MultiAD) -> Self {
+        self.tape.apply(op, &[self])
+    }
+
+    fn binary(self, op: MultiAD, rhs: Self) -> Self {
+        self.tape.apply(op, &[self, rhs])
+    }
+
+    /// Sine: sin(self)
+    pub fn sin(self) -> Self {
+        self.unary(MultiAD::Sin)
+    }
+
+    /// Cosine: cos(self)
+    pub fn cos(self) -> Self {
+        self.unary(MultiAD::Cos)
+    }
+
+    /// Tangent: tan(self)
+    pub fn tan(self) -> Self {
+        self.unary(MultiAD::Tan)
+    }
+
+    /// Exponential: exp(self)
+    pub fn exp(self) -> Self {
+        self.unary(MultiAD::Exp)
+    }
+
+    /// Natural logarithm: ln(self)
+    pub fn ln(self) -> Self {
+        self.unary(MultiAD::Ln)
+    }
+
+    /// Square root: sqrt(self)
+    pub fn sqrt(self) -> Self {
+        self.unary(MultiAD::Sqrt)
+    }
+
+    /// Absolute value: |self|
+    pub fn abs(self) -> Self {
+        self.unary(MultiAD::Abs)
+    }
+
+    /// Hyperbolic tangent: tanh(self)
+    pub fn tanh(self) -> Self {
+        self.unary(MultiAD::Tanh)
+    }
+
+    /// Hyperbolic sine: sinh(self)
+    pub fn sinh(self) -> Self {
+        self.unary(MultiAD::Sinh)
+    }
+
+    /// Hyperbolic cosine: cosh(self)
+    pub fn cosh(self) -> Self {
+        self.unary(MultiAD::Cosh)
+    }
+
+    /// Inverse sine: asin(self)
+    pub fn asin(self) -> Self {
+        self.unary(MultiAD::Asin)
+    }
+
+    /// Inverse cosine: acos(self)
+    pub fn acos(self) -> Self {
+        self.unary(MultiAD::Acos)
+    }
+
+    /// Inverse tangent: atan(self)
+    pub fn atan(self) -> Self {
+        self.unary(MultiAD::Atan)
+    }
+
+    /// ln(1 + self), accurate for small values
+    pub fn ln_1p(self) -> Self {
+        self.unary(MultiAD::Log1p)
+    }
+
+    /// exp(self) - 1, accurate for small values
+    pub fn exp_m1(self) -> Self {
+        self.unary(MultiAD::Expm1)
+    }
+
+    /// Base-2 logarithm: log2(self)
+    pub fn log2(self) -> Self {
+        self.unary(MultiAD::Log2)
+    }
+
+    /// Base-10 logarithm: log10(self)
+    pub fn log10(self) -> Self {
+        self.unary(MultiAD::Log10)
+    }
+
+    /// Logistic sigmoid: 1 / (1 + exp(-self))
+    pub fn sigmoid(self) -> Self {
+        self.unary(MultiAD::Sigmoid)
+    }
+
+    /// Softplus: ln(1 + exp(self))
+    pub fn softplus(self) -> Self {
+        self.unary(MultiAD::Softplus)
+    }
+
+    /// Four-quadrant inverse tangent of `self / x`, like `f64::atan2`
+    pub fn atan2(self, x: Self) -> Self {
+        self.binary(MultiAD::Atan2, x)
+    }
+
+    /// Euclidean norm: sqrt(self² + other²)
+    pub fn hypot(self, other: Self) -> Self {
+        self.binary(MultiAD::Hypot, other)
+    }
+
+    /// Rectified linear unit: max(self, 0)
+    pub fn relu(self) -> Self {
+        self.unary(MultiAD::Relu)
+    }
+
+    /// Round down: floor(self)
+    pub fn floor(self) -> Self {
+        self.unary(MultiAD::Floor)
+    }
+
+    /// Round up: ceil(self)
+    pub fn ceil(self) -> Self {
+        self.unary(MultiAD::Ceil)
+    }
+
+    /// Round to nearest, halves away from zero: round(self)
+    pub fn round(self) -> Self {
+        self.unary(MultiAD::Round)
+    }
+
+    /// Sign: -1, 0 or 1; unlike `f64::signum`, zero for zero
+    pub fn sign(self) -> Self {
+        self.unary(MultiAD::Sign)
+    }
+
+    /// Error function: erf(self)
+    pub fn erf(self) -> Self {
+        self.unary(MultiAD::Erf)
+    }
+
+    /// Complementary error function: 1 - erf(self)
+    pub fn erfc(self) -> Self {
+        self.unary(MultiAD::Erfc)
+    }
+
+    /// Log of the absolute value of the gamma function: ln|Γ(self)|
+    pub fn lgamma(self) -> Self {
+        self.unary(MultiAD::Lgamma)
+    }
+
+    /// Digamma function: ψ(self), the derivative of `lgamma`
+    pub fn digamma(self) -> Self {
+        self.unary(MultiAD::Digamma)
+    }
+
+    /// Trigamma function: ψ₁(self), the derivative of `digamma`
+    pub fn trigamma(self) -> Self {
+        self.unary(MultiAD::Trigamma)
+    }
+
+    /// Standard normal CDF: Φ(self)
+    pub fn normal_cdf(self) -> Self {
+        self.unary(MultiAD::NormalCdf)
+    }
+
+    /// Log of the standard normal density at `self`
+    pub fn normal_logpdf(self) -> Self {
+        self.unary(MultiAD::NormalLogpdf)
+    }
+
+    /// Principal branch of the Lambert W function: the w ≥ -1 with w eʷ = self
+    pub fn lambert_w(self) -> Self {
+        self.unary(MultiAD::LambertW)
+    }
+
+    /// Smaller of the two: min(self, other)
+    pub fn min(self, other: Self) -> Self {
+        self.binary(MultiAD::Min, other)
+    }
+
+    /// Larger of the two: max(self, other)
+    pub fn max(self, other: Self) -> Self {
+        self.binary(MultiAD::Max, other)
+    }
+
+    /// Clamp to `[lo, hi]`: clamp(self, lo, hi)
+    pub fn clamp(self, lo: Self, hi: Self) -> Self {
+        self.tape.apply(MultiAD::Clamp, &[self, lo, hi])
+    }
+
+    /// Power: self^exponent
+    pub fn powf(self, exponent: Self) -> Self {
+        self.binary(MultiAD::Pow, exponent)
+    }
+
+    /// Power with a constant integer exponent: self^n
+    pub fn powi(self, n: i32) -> Self {
+        self.binary(MultiAD::Pow, self.tape.constant(f64::from(n)))
+    }
+}
+
+/// Implements a binary operator for `Var ∘ Var`, `Var ∘ f64` and `f64 ∘ Var`;
+/// `f64` operands are recorded as constant nodes.
+macro_rules! impl_binary_op {
+    ($trait:ident, $method:ident, $op:expr) => {
+        impl<'t> $trait for Var<'t> {
+            type Output = Var<'t>;
+
+            fn $method(self, rhs: Self) -> Self::Output {
+                self.binary($op, rhs)
+            }
+        }
+
+        impl<'t> $trait<f64> for Var<'t> {
+            type Output = Var<'t>;
+
+            fn $method(self, rhs: f64) -> Self::Output {
+                self.binary($op, self.tape.constant(rhs))
+            }
+        }
+
+        impl<'t> $trait<Var<'t>> for f64 {
+            type Output = Var<'t>;
+
+            fn $method(self, rhs: Var<'t>) -> Self::Output {
+                rhs.tape.constant(self).binary($op, rhs)
+            }
+        }
+    };
+}
+
+impl_binary_op!(Add, add, MultiAD::Add);
+impl_binary_op!(Sub, sub, MultiAD::Sub);
+impl_binary_op!(Mul, mul, MultiAD::Mul);
+impl_binary_op!(Div, div, MultiAD::Div);
+impl_binary_op!(Rem, rem, MultiAD::Fmod);
+
+impl<'t> Neg for Var<'t> {
+    type Output = Var<'t>;
+
+    fn neg(self) -> Self::Output {
+        self.unary(MultiAD::Neg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::multi_ops;
+    use crate::test_utils::approx_eq_eps as approx_eq;
+
+    #[test]
+    fn test_graph_matches_multi_ops() {
+        let tape = Tape::new();
+        let x = tape.var(0.6);
+        let y = tape.var(1.4);
+        let f = (x + y) * x.sin();
+
+        let expected = multi_ops![(inp, 0), (inp, 1), (add, 0, 1), (sin, 0), (mul, 2, 3)];
+        assert_eq!(tape.graph(f), expected);
+        assert_eq!(tape.inputs(), vec![0.6, 1.4]);
+        assert_eq!(tape.len(), 5);
+    }
+
+    #[test]
+    fn test_tape_grad_matches_compute_grad() {
+        let tape = Tape::new();
+        let xs = tape.vars(&[0.5, 1.3]);
+        let (x, y) = (xs[0], xs[1]);
+        let out = (x * y).exp() / (x.cos() + y.powf(x)) - (-x).abs().sqrt() * y.ln().tan();
+        let (value, grad_fn) = MultiAD::compute_grad(&tape.graph(out), &tape.inputs()).unwrap();
+        assert_eq!(value, out.value());
+        for (a, b) in grad_fn(1.0).iter().zip(tape.grad(out)) {
+            assert!(approx_eq(*a, b, 1e-12));
+        }
+    }
+
+    #[test]
+    fn test_dead_nodes_are_dropped() {
+        let tape = Tape::new();
+        let x = tape.var(2.0);
+        let y = tape.var(3.0);
+        let _unused = x.exp();
+        let f = x * y;
+        let _after = f.sin();
+
+        assert_eq!(tape.graph(f), multi_ops![(inp, 0), (inp, 1), (mul, 0, 1)]);
+        assert_eq!(tape.grad(f), vec![3.0, 2.0]);
+    }
+
+    #[test]
+    fn test_input_as_output() {
+        let tape = Tape::new();
+        let x = tape.var(2.0);
+        let _y = tape.var(3.0);
+        let graph = tape.graph(x);
+        assert_eq!(graph.last(), Some(&(MultiAD::Inp, vec![0])));
+
+        let (value, grad_fn) = MultiAD::compute_grad(&graph, &tape.inputs()).unwrap();
+        assert_eq!(value, 2.0);
+        assert_eq!(grad_fn(1.0), vec![1.0, 0.0]);
+        assert_eq!(tape.grad(x), vec![1.0, 0.0]);
+    }
+
+    fn poly<'t>(xs: &[Var<'t>]) -> Var<'t> {
+        let (x, y, z) = (xs[0], xs[1], xs[2]);
+        x * y - z / (x + y).exp()
+    }
+
+    #[test]
+    fn test_trace_function() {
+        let graph = MultiAD::trace(3, poly);
+        assert_eq!(graph[..3], multi_ops![(inp, 0), (inp, 1), (inp, 2)]);
+
+        // The traced graph is reusable at any point
+        for inputs in [[1.0, 2.0, 3.0], [-0.5, 0.25, 4.0]] {
+            let tape = Tape::new();
+            let expected = poly(&tape.vars(&inputs));
+            let (value, grad_fn) = MultiAD::compute_grad(&graph, &inputs).unwrap();
+            assert!(approx_eq(value, expected.value(), 1e-12));
+            for (a, b) in grad_fn(1.0).iter().zip(tape.grad(expected)) {
+                assert!(approx_eq(*a, b, 1e-12));
+            }
+        }
+    }
+
+    #[test]
+    fn test_trace_closure() {
+        let scale = 3;
+        let graph = MultiAD::trace(2, |xs| {
+            let mut acc = xs[0];
+            for _ in 1..scale {
+                acc = acc * xs[1];
+            }
+            acc
+        });
+        assert_eq!(
+            graph,
+            multi_ops![(inp, 0), (inp, 1), (mul, 0, 1), (mul, 2, 1)]
+        );
+    }
+
+    #[test]
+    fn test_constants() {
+        let tape = Tape::new();
+        let x = tape.var(1.5);
+        let f = 2.0 * x.powi(3) - 1.0 / x + x * 0.5;
+
+        assert_eq!(tape.inputs(), vec![1.5]);
+        assert!(approx_eq(f.value(), 6.75 - 1.0 / 1.5 + 0.75, 1e-12));
+        let expected = 6.0 * 1.5 * 1.5 + 1.0 / (1.5 * 1.5) + 0.5;
+        assert!(approx_eq(tape.grad(f)[0], expected, 1e-12));
+
+        let graph = tape.graph(f);
+        assert!(graph.contains(&(MultiAD::Const(3.0), vec![])));
+        let (value, grad_fn) = MultiAD::compute_grad(&graph, &[1.5]).unwrap();
+        assert_eq!(value, f.value());
+        assert!(approx_eq(grad_fn(1.0)[0], expected, 1e-12));
+    }
+
+    #[test]
+    fn test_reductions() {
+        let tape = Tape::new();
+        let xs = tape.vars(&[1.0, 2.0, 3.0]);
+        let f = tape.sum(&xs) * tape.prod(&xs) + tape.mean(&xs) - tape.logsumexp(&xs);
+        let g = tape.softmax_sum(&[f, xs[0]]);
+
+        let graph = tape.graph(g);
+        assert!(graph.contains(&(MultiAD::Sum, vec![0, 1, 2])));
+        let (value, grad_fn) = MultiAD::compute_grad(&graph, &tape.inputs()).unwrap();
+        assert_eq!(value, g.value());
+        assert_eq!(grad_fn(1.0), tape.grad(g));
+    }
+
+    #[test]
+    #[should_panic(expected = "sum needs at least one variable")]
+    fn test_empty_reduction_panics() {
+        let tape = Tape::new();
+        tape.sum(&[]);
+    }
+
+    #[test]
+    #[should_panic(expected = "different Tape")]
+    fn test_mixing_tapes_panics() {
+        let a = Tape::new();
+        let b = Tape::new();
+        let _ = a.var(1.0) + b.var(2.0);
+    }
+}
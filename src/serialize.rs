@@ -0,0 +1,778 @@
+//! Versioned text and JSON formats for graphs and chains.
+//!
+//! The text format mirrors the macros: a header line, an optional line of
+//! input names, then one `multi_ops!` tuple per line for graphs or one
+//! `mono_ops!` name per line for chains. `#` starts a comment.
+//!
+//! ```text
+//! petite-ad graph v1
+//! inputs x y
+//! (inp, 0)
+//! (inp, 1)
+//! (const, 2.5)
+//! (mul, 1, 2)
+//! (add, 0, 3)
+//! ```
+//!
+//! The JSON format holds the same data in one object:
+//!
+//! ```text
+//! {"format": "petite-ad", "version": 1, "kind": "graph",
+//!  "inputs": ["x", "y"], "nodes": [["inp", 0], ["inp", 1], ["const", 2.5], ...]}
+//! ```
+//!
+//! Loading checks the header and version, reports syntax problems with their
+//! line and column, and runs [`MultiAD::validate`] on graphs.
+
+mod json;
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use crate::error::{Arity, AutodiffError, Result};
+use crate::multi::types::ParsedExpr;
+use crate::{MonoAD, MultiAD};
+use json::{quote, Json};
+
+/// Name written at the start of every file.
+const FORMAT: &str = "petite-ad";
+
+/// Version written by this crate; loading any other version is an error.
+const VERSION: usize = 1;
+
+/// What a file holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Graph,
+    Chain,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Graph => "graph",
+            Kind::Chain => "chain",
+        }
+    }
+}
+
+/// Check the kind read from a file, found at `line`/`column`.
+fn check_kind(kind: &str, expected: Kind, line: usize, column: usize) -> Result<()> {
+    if kind != expected.name() {
+        return Err(AutodiffError::parse(
+            format!("expected a {}, found '{kind}'", expected.name()),
+            line,
+            column,
+        ));
+    }
+    Ok(())
+}
+
+/// Check the version read from a file, found at `line`/`column`.
+fn check_version(version: usize, line: usize, column: usize) -> Result<()> {
+    if version != VERSION {
+        return Err(AutodiffError::parse(
+            format!("unsupported version {version}, expected {VERSION}"),
+            line,
+            column,
+        ));
+    }
+    Ok(())
+}
+
+/// A number as written by both formats: shortest round-trip form.
+fn format_number(x: f64) -> String {
+    format!("{x:?}")
+}
+
+/// Check that input names are distinct identifiers, given as
+/// `(name, line, column)`.
+fn check_names<'a>(names: impl IntoIterator<Item = (&'a str, usize, usize)>) -> Result<()> {
+    let mut seen = HashSet::new();
+    for (name, line, column) in names {
+        let mut chars = name.chars();
+        let valid = chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(AutodiffError::parse(
+                format!("invalid input name '{name}'"),
+                line,
+                column,
+            ));
+        }
+        if !seen.insert(name) {
+            return Err(AutodiffError::parse(
+                format!("duplicate input name '{name}'"),
+                line,
+                column,
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Build a node from its op name and arguments, checking the argument count.
+fn node<T>(
+    op: &str,
+    args: &[T],
+    number: impl Fn(&T) -> Result<f64>,
+    index: impl Fn(&T) -> Result<usize>,
+    error: impl Fn(String) -> AutodiffError,
+) -> Result<(MultiAD, Vec<usize>)> {
+    let (op, value_arg) = match op {
+        "const" => (MultiAD::Const(0.0), true),
+        "param" => (MultiAD::Param(0), true),
+        name => (
+            name.parse()
+                .map_err(|_| error(format!("unknown op '{name}'")))?,
+            false,
+        ),
+    };
+    let arity = if value_arg {
+        Arity::Exact(1)
+    } else {
+        op.expected_arity()
+    };
+    if !arity.accepts(args.len()) {
+        return Err(error(format!(
+            "{op} expects {arity} argument(s), got {}",
+            args.len()
+        )));
+    }
+    Ok(match op {
+        MultiAD::Const(_) => (MultiAD::Const(number(&args[0])?), Vec::new()),
+        MultiAD::Param(_) => (MultiAD::Param(index(&args[0])?), Vec::new()),
+        op => (op, args.iter().map(index).collect::<Result<_>>()?),
+    })
+}
+
+/// Validate a loaded graph and check it has one input per name. A graph
+/// without `Inp` nodes takes one input per name.
+fn check_graph(graph: &[(MultiAD, Vec<usize>)], names: &[String]) -> Result<()> {
+    if !graph.iter().any(|(op, _)| *op == MultiAD::Inp) {
+        return MultiAD::validate(graph, names.len());
+    }
+    let num_inputs = MultiAD::declared_inputs(graph);
+    if !names.is_empty() && names.len() != num_inputs {
+        return Err(AutodiffError::InputCountMismatch {
+            expected: num_inputs,
+            actual: names.len(),
+        });
+    }
+    MultiAD::validate(graph, num_inputs)
+}
+
+/// Non-empty lines with comments removed, as `(line number, text)`.
+fn content_lines(src: &str) -> impl Iterator<Item = (usize, &str)> {
+    src.lines().enumerate().filter_map(|(i, line)| {
+        let text = line.split('#').next().unwrap_or_default().trim_end();
+        (!text.trim().is_empty()).then_some((i + 1, text))
+    })
+}
+
+/// Fields of a line split on whitespace, with their 1-based columns.
+fn words(text: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (column, (offset, c)) in text.char_indices().enumerate() {
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some((column + 1, offset)),
+            (true, Some((col, from))) => {
+                words.push((col, &text[from..offset]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some((col, from)) = start {
+        words.push((col, &text[from..]));
+    }
+    words
+}
+
+/// Read the `petite-ad <kind> v<version>` header line.
+fn read_header<'s>(
+    lines: &mut impl Iterator<Item = (usize, &'s str)>,
+    expected: Kind,
+) -> Result<()> {
+    let Some((line, text)) = lines.next() else {
+        return Err(AutodiffError::parse("missing header", 1, 1));
+    };
+    let fields = words(text);
+    let header = format!("{FORMAT} {} v{VERSION}", expected.name());
+    match fields.as_slice() {
+        [(_, FORMAT), (kind_col, kind), (version_col, version)] => {
+            let version = version
+                .strip_prefix('v')
+                .and_then(|v| v.parse().ok())
+                .ok_or_else(|| {
+                    AutodiffError::parse(format!("invalid version '{version}'"), line, *version_col)
+                })?;
+            check_kind(kind, expected, line, *kind_col)?;
+            check_version(version, line, *version_col)
+        }
+        _ => Err(AutodiffError::parse(
+            format!("expected header '{header}'"),
+            line,
+            1,
+        )),
+    }
+}
+
+/// Parse a `(op, arg, ...)` line.
+fn read_tuple(line: usize, text: &str) -> Result<(MultiAD, Vec<usize>)> {
+    let start = text.len() - text.trim_start().len();
+    let inner = text
+        .trim()
+        .strip_prefix('(')
+        .and_then(|t| t.strip_suffix(')'))
+        .ok_or_else(|| {
+            AutodiffError::parse("expected a node like '(add, 0, 1)'", line, start + 1)
+        })?;
+
+    // Field texts with the column where each starts
+    let mut fields = Vec::new();
+    let mut column = text[..start].chars().count() + 2;
+    for field in inner.split(',') {
+        let lead = field.len() - field.trim_start().len();
+        fields.push((column + field[..lead].chars().count(), field.trim()));
+        column += field.chars().count() + 1;
+    }
+    let (op_col, op) = fields[0];
+    let error_at =
+        |column: usize| move |message: String| AutodiffError::parse(message, line, column);
+    let args = &fields[1..];
+    node(
+        op,
+        args,
+        |&(col, text)| {
+            text.parse()
+                .map_err(|_| error_at(col)(format!("invalid number '{text}'")))
+        },
+        |&(col, text)| {
+            text.parse()
+                .map_err(|_| error_at(col)(format!("invalid index '{text}'")))
+        },
+        error_at(op_col),
+    )
+}
+
+impl MultiAD {
+    /// Write a graph and its input names in the versioned text format.
+    ///
+    /// The graph takes `num_inputs` inputs, laid out as for
+    /// [`MultiAD::validate`]. `names` may be empty; otherwise it holds one
+    /// identifier per input, as returned by [`MultiAD::parse`]. A graph
+    /// without `Inp` nodes is written with one in front per input, which keeps
+    /// every node index, so it loads back with its inputs declared.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petite_ad::{MultiAD, multi_ops};
+    ///
+    /// let exprs = multi_ops![(inp, 0), (const, 2.5), (mul, 0, 1)];
+    /// let text = MultiAD::to_text(&exprs, 1, &["x".to_string()]);
+    /// assert_eq!(text, "petite-ad graph v1\ninputs x\n(inp, 0)\n(const, 2.5)\n(mul, 0, 1)\n");
+    /// assert_eq!(MultiAD::from_text(&text).unwrap(), (exprs.to_vec(), vec!["x".to_string()]));
+    /// ```
+    pub fn to_text(exprs: &[(MultiAD, Vec<usize>)], num_inputs: usize, names: &[String]) -> String {
+        let mut text = format!("{FORMAT} {} v{VERSION}\n", Kind::Graph.name());
+        if !names.is_empty() {
+            let _ = writeln!(text, "inputs {}", names.join(" "));
+        }
+        for (op, args) in &MultiAD::with_input_nodes(exprs, num_inputs) {
+            let fields: Vec<String> = match op {
+                MultiAD::Const(c) => vec![format_number(*c)],
+                MultiAD::Param(k) => vec![k.to_string()],
+                _ => args.iter().map(usize::to_string).collect(),
+            };
+            let _ = if fields.is_empty() {
+                writeln!(text, "({op})")
+            } else {
+                writeln!(text, "({op}, {})", fields.join(", "))
+            };
+        }
+        text
+    }
+
+    /// Load a graph and its input names from the text format.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AutodiffError::ParseError` with the line and column of a bad
+    /// header, unsupported version, unknown op, wrong argument count or bad
+    /// number, and any error of [`MultiAD::validate`] for the loaded graph,
+    /// whose node indices count node lines from zero. Input names must be
+    /// distinct identifiers, one per input; a graph without `Inp` nodes takes
+    /// one input per name.
+    pub fn from_text(src: &str) -> Result<ParsedExpr> {
+        let mut lines = content_lines(src);
+        read_header(&mut lines, Kind::Graph)?;
+
+        let mut names = Vec::new();
+        let mut graph = Vec::new();
+        for (line, text) in lines {
+            let fields = words(text);
+            if fields[0].1 == "inputs" {
+                if !graph.is_empty() || !names.is_empty() {
+                    return Err(AutodiffError::parse(
+                        "the inputs line must come first",
+                        line,
+                        fields[0].0,
+                    ));
+                }
+                check_names(fields[1..].iter().map(|&(column, n)| (n, line, column)))?;
+                names = fields[1..].iter().map(|(_, n)| n.to_string()).collect();
+                continue;
+            }
+            graph.push(read_tuple(line, text)?);
+        }
+        check_graph(&graph, &names)?;
+        Ok((graph, names))
+    }
+
+    /// Write a graph and its input names in the versioned JSON format.
+    ///
+    /// Non-finite constants are written as the strings `"NaN"`, `"inf"` and
+    /// `"-inf"`. Implicit inputs are declared as in [`MultiAD::to_text`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petite_ad::{MultiAD, multi_ops};
+    ///
+    /// let exprs = multi_ops![(inp, 0), (sin, 0)];
+    /// let json = MultiAD::to_json(&exprs, 1, &[]);
+    /// assert!(json.contains(r#""nodes": [["inp", 0], ["sin", 0]]"#));
+    /// assert_eq!(MultiAD::from_json(&json).unwrap().0, exprs);
+    /// ```
+    pub fn to_json(exprs: &[(MultiAD, Vec<usize>)], num_inputs: usize, names: &[String]) -> String {
+        let nodes: Vec<String> = MultiAD::with_input_nodes(exprs, num_inputs)
+            .iter()
+            .map(|(op, args)| {
+                let mut fields = vec![quote(&op.to_string())];
+                match op {
+                    MultiAD::Const(c) if c.is_finite() => fields.push(format_number(*c)),
+                    MultiAD::Const(c) => fields.push(quote(&c.to_string())),
+                    MultiAD::Param(k) => fields.push(k.to_string()),
+                    _ => fields.extend(args.iter().map(usize::to_string)),
+                }
+                format!("[{}]", fields.join(", "))
+            })
+            .collect();
+        format!(
+            "{{\"format\": {}, \"version\": {VERSION}, \"kind\": {}, \"inputs\": [{}], \"nodes\": [{}]}}\n",
+            quote(FORMAT),
+            quote(Kind::Graph.name()),
+            names.iter().map(|n| quote(n)).collect::<Vec<_>>().join(", "),
+            nodes.join(", ")
+        )
+    }
+
+    /// Load a graph and its input names from the JSON format.
+    ///
+    /// The `inputs` field is optional.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AutodiffError::ParseError` with the position of invalid JSON,
+    /// a missing or mistyped field, an unsupported version, an unknown op or
+    /// a wrong argument count, and any error of [`MultiAD::validate`] for the
+    /// loaded graph.
+    pub fn from_json(src: &str) -> Result<ParsedExpr> {
+        let doc = read_json_header(src, Kind::Graph)?;
+        let names: Vec<String> = match doc.field("inputs")? {
+            Some(inputs) => {
+                let names = inputs
+                    .as_array()?
+                    .iter()
+                    .map(|n| Ok((n.as_str()?, n.line, n.column)))
+                    .collect::<Result<Vec<_>>>()?;
+                check_names(names.iter().copied())?;
+                names.into_iter().map(|(n, _, _)| n.to_string()).collect()
+            }
+            None => Vec::new(),
+        };
+        let graph = doc
+            .require("nodes")?
+            .as_array()?
+            .iter()
+            .map(|entry| {
+                let fields = entry.as_array()?;
+                let Some((op, args)) = fields.split_first() else {
+                    return Err(entry.error("expected [op, args...]"));
+                };
+                node(
+                    op.as_str()?,
+                    args,
+                    |arg| match arg.as_str() {
+                        Ok(text) => text
+                            .parse()
+                            .map_err(|_| arg.error(format!("invalid number '{text}'"))),
+                        Err(_) => arg.as_f64(),
+                    },
+                    Json::as_usize,
+                    |message| op.error(message),
+                )
+            })
+            .collect::<Result<Vec<_>>>()?;
+        check_graph(&graph, &names)?;
+        Ok((graph, names))
+    }
+}
+
+/// Parse a JSON document and check its format, version and kind.
+fn read_json_header(src: &str, expected: Kind) -> Result<Json> {
+    let doc = Json::parse(src)?;
+    let format = doc.require("format")?;
+    if format.as_str()? != FORMAT {
+        return Err(format.error(format!("expected format '{FORMAT}'")));
+    }
+    let version = doc.require("version")?;
+    let kind = doc.require("kind")?;
+    check_kind(kind.as_str()?, expected, kind.line, kind.column)?;
+    check_version(version.as_usize()?, version.line, version.column)?;
+    Ok(doc)
+}
+
+impl MonoAD {
+    /// Write a chain in the versioned text format, one op per line.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petite_ad::{MonoAD, mono_ops};
+    ///
+    /// let ops = mono_ops![sin, exp];
+    /// let text = MonoAD::to_text(&ops);
+    /// assert_eq!(text, "petite-ad chain v1\nsin\nexp\n");
+    /// assert_eq!(MonoAD::from_text(&text).unwrap(), ops);
+    /// ```
+    pub fn to_text(exprs: &[MonoAD]) -> String {
+        let mut text = format!("{FORMAT} {} v{VERSION}\n", Kind::Chain.name());
+        for op in exprs {
+            let _ = writeln!(text, "{op}");
+        }
+        text
+    }
+
+    /// Load a chain from the text format.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AutodiffError::ParseError` with the position of a bad header,
+    /// unsupported version or unknown op.
+    pub fn from_text(src: &str) -> Result<Vec<MonoAD>> {
+        let mut lines = content_lines(src);
+        read_header(&mut lines, Kind::Chain)?;
+        lines
+            .map(|(line, text)| {
+                let column = text.len() - text.trim_start().len() + 1;
+                text.trim().parse().map_err(|_| {
+                    AutodiffError::parse(format!("unknown op '{}'", text.trim()), line, column)
+                })
+            })
+            .collect()
+    }
+
+    /// Write a chain in the versioned JSON format.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use petite_ad::{MonoAD, mono_ops};
+    ///
+    /// let json = MonoAD::to_json(&mono_ops![cos, neg]);
+    /// assert!(json.contains(r#""ops": ["cos", "neg"]"#));
+    /// assert_eq!(MonoAD::from_json(&json).unwrap(), mono_ops![cos, neg]);
+    /// ```
+    pub fn to_json(exprs: &[MonoAD]) -> String {
+        let ops: Vec<String> = exprs.iter().map(|op| quote(&op.to_string())).collect();
+        format!(
+            "{{\"format\": {}, \"version\": {VERSION}, \"kind\": {}, \"ops\": [{}]}}\n",
+            quote(FORMAT),
+            quote(Kind::Chain.name()),
+            ops.join(", ")
+        )
+    }
+
+    /// Load a chain from the JSON format.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AutodiffError::ParseError` with the position of invalid JSON,
+    /// a missing or mistyped field, an unsupported version or an unknown op.
+    pub fn from_json(src: &str) -> Result<Vec<MonoAD>> {
+        let doc = read_json_header(src, Kind::Chain)?;
+        doc.require("ops")?
+            .as_array()?
+            .iter()
+            .map(|op| {
+                let name = op.as_str()?;
+                name.parse()
+                    .map_err(|_| op.error(format!("unknown op '{name}'")))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::approx_eq_eps as approx_eq;
+    use crate::{mono_ops, multi_ops};
+
+    fn sample() -> ParsedExpr {
+        let (mut graph, names) = MultiAD::parse("a * sin(b) - 1.5e-3 / a").unwrap();
+        graph.push((MultiAD::Param(0), vec![]));
+        let output = graph.len();
+        graph.push((MultiAD::Add, vec![output - 2, output - 1]));
+        (graph, names)
+    }
+
+    #[test]
+    fn test_op_names_round_trip() {
+        let ops = multi_ops![
+            (inp, 0),
+            (add, 0, 0),
+            (sub, 0, 0),
+            (mul, 0, 0),
+            (div, 0, 0),
+            (pow, 0, 0),
+            (sin, 0),
+            (cos, 0),
+            (tan, 0),
+            (exp, 0),
+            (ln, 0),
+            (sqrt, 0),
+            (abs, 0),
+            (neg, 0),
+            (tanh, 0),
+            (sinh, 0),
+            (cosh, 0),
+            (asin, 0),
+            (acos, 0),
+            (atan, 0),
+            (atan2, 0, 0),
+            (log1p, 0),
+            (expm1, 0),
+            (log2, 0),
+            (log10, 0),
+            (sigmoid, 0),
+            (softplus, 0),
+            (hypot, 0, 0),
+            (min, 0, 0),
+            (max, 0, 0),
+            (clamp, 0, 0, 0),
+            (relu, 0),
+            (floor, 0),
+            (ceil, 0),
+            (round, 0),
+            (sign, 0),
+            (fmod, 0, 0),
+            (erf, 0),
+            (erfc, 0),
+            (lgamma, 0),
+            (digamma, 0),
+            (trigamma, 0),
+            (normal_cdf, 0),
+            (normal_logpdf, 0),
+            (lambert_w, 0),
+            (sum, 0, 0, 0),
+            (prod, 0),
+            (mean, 0, 0),
+            (logsumexp, 0, 0),
+            (softmax_sum, 0, 0)
+        ];
+        for (op, _) in &ops {
+            assert_eq!(op.to_string().parse::<MultiAD>(), Ok(*op));
+        }
+        assert_eq!(MultiAD::Const(1.0).to_string(), "const");
+        assert!("const".parse::<MultiAD>().is_err());
+        assert!("Sin".parse::<MultiAD>().is_err());
+        for op in mono_ops![sin, cos, exp, neg] {
+            assert_eq!(op.to_string().parse::<MonoAD>(), Ok(op));
+        }
+    }
+
+    #[test]
+    fn test_graph_round_trips() {
+        let (graph, names) = sample();
+        assert_eq!(
+            MultiAD::from_text(&MultiAD::to_text(&graph, names.len(), &names)),
+            Ok((graph.clone(), names.clone()))
+        );
+        assert_eq!(
+            MultiAD::from_json(&MultiAD::to_json(&graph, names.len(), &names)),
+            Ok((graph.clone(), names.clone()))
+        );
+
+        // Non-finite constants survive both formats
+        let exprs = multi_ops![(const, f64::INFINITY), (const, f64::NAN), (add, 0, 1)].to_vec();
+        assert_eq!(
+            MultiAD::from_text(&MultiAD::to_text(&exprs, 0, &[]))
+                .unwrap()
+                .0,
+            exprs
+        );
+        assert_eq!(
+            MultiAD::from_json(&MultiAD::to_json(&exprs, 0, &[]))
+                .unwrap()
+                .0,
+            exprs
+        );
+
+        // Implicit inputs are written as Inp nodes
+        let exprs = multi_ops![(mul, 0, 1), (sin, 2)].to_vec();
+        let declared = MultiAD::with_input_nodes(&exprs, 2);
+        assert_eq!(
+            MultiAD::from_text(&MultiAD::to_text(&exprs, 2, &[])),
+            Ok((declared.clone(), vec![]))
+        );
+        assert_eq!(
+            MultiAD::from_json(&MultiAD::to_json(&exprs, 2, &[])),
+            Ok((declared, vec![]))
+        );
+        let names = ["x", "y", "z"].map(String::from).to_vec();
+        let (graph, _) = MultiAD::from_text(&MultiAD::to_text(&exprs, 3, &names)).unwrap();
+        assert_eq!(
+            MultiAD::compute(&graph, &[2.0, 3.0, 0.0]),
+            MultiAD::compute(&exprs, &[2.0, 3.0, 0.0])
+        );
+
+        // x * z reads input 2 past a dead sin(x) node
+        let exprs = multi_ops![(sin, 0), (mul, 0, 2)].to_vec();
+        let (graph, _) = MultiAD::from_json(&MultiAD::to_json(&exprs, 3, &[])).unwrap();
+        assert_eq!(graph, MultiAD::with_input_nodes(&exprs, 3));
+        assert_eq!(MultiAD::compute(&graph, &[2.0, 5.0, 7.0]), Ok(14.0));
+
+        let chain = mono_ops![sin, neg, exp, cos];
+        assert_eq!(MonoAD::from_text(&MonoAD::to_text(&chain)).unwrap(), chain);
+        assert_eq!(MonoAD::from_json(&MonoAD::to_json(&chain)).unwrap(), chain);
+    }
+
+    #[test]
+    fn test_hand_written_files() {
+        let text = "
+            # f(x, y) = x * y + 2
+            petite-ad graph v1
+            inputs x y
+            (inp, 0)
+            (inp, 1)   # y
+            (mul,0,1)
+            (const, 2)
+            (add, 2, 3)
+        ";
+        let (graph, names) = MultiAD::from_text(text).unwrap();
+        assert_eq!(names, ["x", "y"]);
+        assert!(approx_eq(
+            MultiAD::compute(&graph, &[3.0, 4.0]).unwrap(),
+            14.0,
+            1e-12
+        ));
+
+        let json = r#"{
+            "kind": "graph", "version": 1, "format": "petite-ad",
+            "nodes": [["inp", 0], ["const", "-inf"], ["param", 2], ["mul", 0, 1]]
+        }"#;
+        let (graph, names) = MultiAD::from_json(json).unwrap();
+        assert!(names.is_empty());
+        assert_eq!(graph[2], (MultiAD::Param(2), vec![]));
+    }
+
+    #[test]
+    fn test_text_errors() {
+        let err = |src: &str| MultiAD::from_text(src).unwrap_err();
+        assert_eq!(
+            err("petite-ad graph v2\n(inp, 0)"),
+            AutodiffError::parse("unsupported version 2, expected 1", 1, 17)
+        );
+        assert_eq!(
+            err("petite-ad chain v1\n"),
+            AutodiffError::parse("expected a graph, found 'chain'", 1, 11)
+        );
+        assert_eq!(
+            err("(inp, 0)"),
+            AutodiffError::parse("expected header 'petite-ad graph v1'", 1, 1)
+        );
+        assert_eq!(
+            err("petite-ad graph v1\n(inp, 0)\n  (mul, 0)"),
+            AutodiffError::parse("mul expects 2 argument(s), got 1", 3, 4)
+        );
+        assert_eq!(
+            err("petite-ad graph v1\n(inp, 0)\n(sech, 0)"),
+            AutodiffError::parse("unknown op 'sech'", 3, 2)
+        );
+        assert_eq!(
+            err("petite-ad graph v1\n(inp, 0)\n(const,  two)"),
+            AutodiffError::parse("invalid number 'two'", 3, 10)
+        );
+        assert_eq!(
+            err("petite-ad graph v1\n(inp, 0)\nsin 0"),
+            AutodiffError::parse("expected a node like '(add, 0, 1)'", 3, 1)
+        );
+        // Structural problems come from validation, by node index
+        assert_eq!(
+            err("petite-ad graph v1\n(inp, 0)\n(sin, 1)"),
+            AutodiffError::ForwardReference { node: 1, index: 1 }
+        );
+        assert_eq!(
+            err("petite-ad graph v1\ninputs x y\n(inp, 0)\n(sin, 0)"),
+            AutodiffError::InputCountMismatch {
+                expected: 1,
+                actual: 2
+            }
+        );
+        assert_eq!(
+            err("petite-ad graph v1\ninputs 2x\n(inp, 0)"),
+            AutodiffError::parse("invalid input name '2x'", 2, 8)
+        );
+        assert_eq!(
+            err("petite-ad graph v1\n\n  inputs x  x\n(inp, 0)\n(inp, 1)"),
+            AutodiffError::parse("duplicate input name 'x'", 3, 13)
+        );
+        assert_eq!(
+            MonoAD::from_text("petite-ad chain v1\nsin\n  tan"),
+            Err(AutodiffError::parse("unknown op 'tan'", 3, 3))
+        );
+    }
+
+    #[test]
+    fn test_json_errors() {
+        let err = |src: &str| MultiAD::from_json(src).unwrap_err();
+        assert_eq!(
+            err(r#"{"format": "petite-ad", "version": 1, "kind": "graph"}"#),
+            AutodiffError::parse("missing field 'nodes'", 1, 1)
+        );
+        assert_eq!(
+            err(r#"{"format": "petite-ad", "version": 3, "kind": "graph", "nodes": []}"#),
+            AutodiffError::parse("unsupported version 3, expected 1", 1, 36)
+        );
+        assert_eq!(
+            err(r#"{"format": "x", "version": 1}"#),
+            AutodiffError::parse("expected format 'petite-ad'", 1, 12)
+        );
+        assert_eq!(
+            err(r#"{"format": "petite-ad", "version": 1, "kind": "graph", "nodes": [["add", 0]]}"#),
+            AutodiffError::parse("add expects 2 argument(s), got 1", 1, 67)
+        );
+        assert_eq!(
+            err(r#"{"format": "petite-ad", "version": 1, "kind": "graph", "nodes": []}"#),
+            AutodiffError::EmptyGraph
+        );
+        assert_eq!(
+            err(r#"{"format": "petite-ad", "version": 1, "kind": "graph",
+                    "inputs": ["x", "x y"], "nodes": [["inp", 0], ["inp", 1]]}"#),
+            AutodiffError::parse("invalid input name 'x y'", 2, 37)
+        );
+        assert_eq!(
+            MonoAD::from_json(
+                r#"{"format": "petite-ad", "version": 1, "kind": "chain", "ops": [1]}"#
+            ),
+            Err(AutodiffError::parse("expected a string", 1, 64))
+        );
+    }
+}
@@ -0,0 +1,371 @@
+//! Minimal JSON reader and writer, enough for the serialisation formats.
+//!
+//! Every parsed value keeps the 1-based line and column where it starts, so
+//! structural problems found after parsing can still point into the source.
+
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::error::{AutodiffError, Result};
+
+/// Deepest nesting of arrays and objects the reader accepts, so that deeply
+/// nested input is an error rather than a stack overflow.
+const MAX_DEPTH: usize = 128;
+
+/// A JSON value without its position.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+/// A JSON value and where it starts in the source.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Json {
+    pub(crate) value: Value,
+    pub(crate) line: usize,
+    pub(crate) column: usize,
+}
+
+impl Json {
+    /// Parse a complete JSON document.
+    pub(crate) fn parse(src: &str) -> Result<Json> {
+        let mut reader = Reader {
+            chars: src.chars().peekable(),
+            line: 1,
+            column: 1,
+            depth: 0,
+        };
+        let value = reader.value()?;
+        reader.skip_whitespace();
+        if reader.chars.peek().is_some() {
+            return Err(reader.error("unexpected text after the document"));
+        }
+        Ok(value)
+    }
+
+    /// A parse error pointing at this value.
+    pub(crate) fn error(&self, message: impl Into<String>) -> AutodiffError {
+        AutodiffError::parse(message, self.line, self.column)
+    }
+
+    /// Field `key` of an object, if present.
+    pub(crate) fn field(&self, key: &str) -> Result<Option<&Json>> {
+        match &self.value {
+            Value::Object(fields) => Ok(fields.iter().find(|(k, _)| k == key).map(|(_, v)| v)),
+            _ => Err(self.error("expected an object")),
+        }
+    }
+
+    /// Field `key` of an object, which must be present.
+    pub(crate) fn require(&self, key: &str) -> Result<&Json> {
+        self.field(key)?
+            .ok_or_else(|| self.error(format!("missing field '{key}'")))
+    }
+
+    pub(crate) fn as_array(&self) -> Result<&[Json]> {
+        match &self.value {
+            Value::Array(items) => Ok(items),
+            _ => Err(self.error("expected an array")),
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> Result<&str> {
+        match &self.value {
+            Value::String(s) => Ok(s),
+            _ => Err(self.error("expected a string")),
+        }
+    }
+
+    pub(crate) fn as_f64(&self) -> Result<f64> {
+        match &self.value {
+            Value::Number(x) => Ok(*x),
+            _ => Err(self.error("expected a number")),
+        }
+    }
+
+    pub(crate) fn as_usize(&self) -> Result<usize> {
+        match self.value {
+            Value::Number(x) if x >= 0.0 && x.fract() == 0.0 && x <= u32::MAX as f64 => {
+                Ok(x as usize)
+            }
+            _ => Err(self.error("expected a non-negative integer")),
+        }
+    }
+}
+
+/// Write `s` as a JSON string literal.
+pub(crate) fn quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Recursive-descent reader tracking the current position.
+struct Reader<'s> {
+    chars: Peekable<Chars<'s>>,
+    line: usize,
+    column: usize,
+    /// Arrays and objects currently open, bounded by [`MAX_DEPTH`]
+    depth: usize,
+}
+
+impl Reader<'_> {
+    fn error(&self, message: impl Into<String>) -> AutodiffError {
+        AutodiffError::parse(message, self.line, self.column)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.chars.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.bump();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<()> {
+        self.skip_whitespace();
+        match self.chars.peek() {
+            Some(&c) if c == expected => {
+                self.bump();
+                Ok(())
+            }
+            Some(&c) => Err(self.error(format!("expected '{expected}', found '{c}'"))),
+            None => Err(self.error(format!("expected '{expected}', found end of input"))),
+        }
+    }
+
+    fn value(&mut self) -> Result<Json> {
+        self.skip_whitespace();
+        let (line, column) = (self.line, self.column);
+        let value = match self.chars.peek() {
+            Some('{' | '[') if self.depth == MAX_DEPTH => {
+                return Err(self.error("nesting too deep"));
+            }
+            Some('{') => self.nested(Self::object)?,
+            Some('[') => self.nested(Self::array)?,
+            Some('"') => Value::String(self.string()?),
+            Some('-' | '0'..='9') => Value::Number(self.number()?),
+            Some('t' | 'f' | 'n') => self.keyword()?,
+            Some(&c) => return Err(self.error(format!("unexpected character '{c}'"))),
+            None => return Err(self.error("unexpected end of input")),
+        };
+        Ok(Json {
+            value,
+            line,
+            column,
+        })
+    }
+
+    /// Read an array or object one level deeper.
+    fn nested(&mut self, read: fn(&mut Self) -> Result<Value>) -> Result<Value> {
+        self.depth += 1;
+        let value = read(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn object(&mut self) -> Result<Value> {
+        self.expect('{')?;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&'}') {
+            self.bump();
+            return Ok(Value::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.chars.peek() != Some(&'"') {
+                return Err(self.error("expected a field name"));
+            }
+            let key = self.string()?;
+            self.expect(':')?;
+            fields.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.chars.peek() {
+                Some(',') => {
+                    self.bump();
+                }
+                Some('}') => {
+                    self.bump();
+                    return Ok(Value::Object(fields));
+                }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&']') {
+            self.bump();
+            return Ok(Value::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.chars.peek() {
+                Some(',') => {
+                    self.bump();
+                }
+                Some(']') => {
+                    self.bump();
+                    return Ok(Value::Array(items));
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(s),
+                Some('\\') => match self.bump() {
+                    Some('"') => s.push('"'),
+                    Some('\\') => s.push('\\'),
+                    Some('/') => s.push('/'),
+                    Some('b') => s.push('\u{8}'),
+                    Some('f') => s.push('\u{c}'),
+                    Some('n') => s.push('\n'),
+                    Some('r') => s.push('\r'),
+                    Some('t') => s.push('\t'),
+                    Some('u') => {
+                        let hex: String = (0..4).filter_map(|_| self.bump()).collect();
+                        let c = u32::from_str_radix(&hex, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| self.error(format!("invalid escape '\\u{hex}'")))?;
+                        s.push(c);
+                    }
+                    _ => return Err(self.error("invalid escape sequence")),
+                },
+                Some(c) => s.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<f64> {
+        let mut text = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if !matches!(c, '-' | '+' | '.' | 'e' | 'E' | '0'..='9') {
+                break;
+            }
+            text.push(c);
+            self.bump();
+        }
+        text.parse()
+            .map_err(|_| self.error(format!("invalid number '{text}'")))
+    }
+
+    fn keyword(&mut self) -> Result<Value> {
+        let mut word = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if !c.is_ascii_alphabetic() {
+                break;
+            }
+            word.push(c);
+            self.bump();
+        }
+        match word.as_str() {
+            "true" => Ok(Value::Bool(true)),
+            "false" => Ok(Value::Bool(false)),
+            "null" => Ok(Value::Null),
+            _ => Err(self.error(format!("unexpected word '{word}'"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_values() {
+        let json =
+            Json::parse(r#" {"a": [1, -2.5e1, "x\"A"], "b": {}, "c": [true, null]} "#).unwrap();
+        let a = json.require("a").unwrap().as_array().unwrap();
+        assert_eq!(a[0].as_usize(), Ok(1));
+        assert_eq!(a[1].as_f64(), Ok(-25.0));
+        assert_eq!(a[2].as_str(), Ok("x\"A"));
+        assert_eq!(json.field("missing"), Ok(None));
+        assert_eq!(
+            json.require("c").unwrap().as_array().unwrap()[1].value,
+            Value::Null
+        );
+        assert_eq!(quote("a\"b\\\n"), r#""a\"b\\\n""#);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            Json::parse("{\n  \"a\": [1 2]\n}"),
+            Err(AutodiffError::parse("expected ',' or ']'", 2, 11))
+        );
+        assert_eq!(
+            Json::parse("[1] x"),
+            Err(AutodiffError::parse(
+                "unexpected text after the document",
+                1,
+                5
+            ))
+        );
+        assert_eq!(
+            Json::parse("\"abc"),
+            Err(AutodiffError::parse("unterminated string", 1, 5))
+        );
+        let nested = |depth| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(Json::parse(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(
+            Json::parse(&nested(200_000)),
+            Err(AutodiffError::parse("nesting too deep", 1, MAX_DEPTH + 1))
+        );
+        assert_eq!(
+            Json::parse(&"{\"a\": ".repeat(200_000)),
+            Err(AutodiffError::parse(
+                "nesting too deep",
+                1,
+                6 * MAX_DEPTH + 1
+            ))
+        );
+        let json = Json::parse("{\"n\": -1}").unwrap();
+        assert_eq!(
+            json.require("n").unwrap().as_usize(),
+            Err(AutodiffError::parse(
+                "expected a non-negative integer",
+                1,
+                7
+            ))
+        );
+    }
+}
@@ -1,5 +1,10 @@
 //! Shared test utilities for the autodiff crate.
 
+use crate::multi::f1::F1;
+use crate::multi::f2::F2;
+use crate::multi::f3::F3;
+use crate::multi::MultiFn;
+
 /// Check if two f64 values are approximately equal within a small epsilon.
 ///
 /// Uses a default epsilon of 1e-10 for high precision comparisons.
@@ -15,3 +20,8 @@ pub(crate) fn approx_eq(a: f64, b: f64) -> bool {
 pub(crate) fn approx_eq_eps(a: f64, b: f64, epsilon: f64) -> bool {
     (a - b).abs() < epsilon
 }
+
+/// The analytic example functions `F1`, `F2` and `F3`, each at a smooth point.
+pub(crate) fn analytic_fns() -> [&'static dyn MultiFn; 3] {
+    [&F1(0.5, 1.0), &F2(0.5, 1.0), &F3(0.5, 1.3)]
+}